@@ -1,5 +1,5 @@
 import * as dat from "dat.gui";
-import { Renderer, Resolution, Mode } from "fluid-simulation";
+import { Renderer, Resolution, Mode, Solver, Advection, SimulationConfig, Boundaries, BoundaryMode, Edge, Obstacle, RigidBody, Particles, ParticleColor, Streamlines } from "fluid-simulation";
 import { resizeCanvas, isMobile, pixelScaling, randomColor, defaultBlueColor, defaultRedColor } from "./utils";
 import Pointer from "./pointer";
 
@@ -7,6 +7,8 @@ enum Configuration {
     NONE,
     SPELLS,
     SPIN,
+    WIND_TUNNEL,
+    FLOATING,
 };
 
 const params = {
@@ -16,7 +18,15 @@ const params = {
     simResolution: isMobile() ? Resolution.EIGHT : Resolution.FOUR,
     pointerRadius: isMobile() ? 0.4 : 0.2,
     pointerStrength: 10.0,
+    solver: Solver.JACOBI,
     iterations: 20,
+    cycles: 2,
+    omega: 1.7,
+    tolerance: 0.0,
+    maxIterations: 200,
+    solverIterations: 0,
+    solverResidual: 0.0,
+    advection: Advection.SEMI_LAGRANGIAN,
     viscosity: 0.5,
     dissipation: 2.0,
     curl: 0.25,
@@ -25,6 +35,21 @@ const params = {
     useRandomColor: true,
     config: Configuration.NONE,
     obstacle: false,
+    boundaryLeft: BoundaryMode.FREE_SLIP,
+    boundaryRight: BoundaryMode.FREE_SLIP,
+    boundaryBottom: BoundaryMode.FREE_SLIP,
+    boundaryTop: BoundaryMode.FREE_SLIP,
+    inflowSpeed: 200.0,
+    particles: false,
+    particleCount: 65536,
+    particleLifetime: 4.0,
+    particleSize: 1.0,
+    particleStreak: 0.0,
+    particleColorBySpeed: true,
+    streamlineSpacing: 32.0,
+    streamlineLength: 160.0,
+    legendMin: 0.0,
+    legendMax: 0.0,
 };
 let wasPaused = false;
 
@@ -47,7 +72,16 @@ const config = {
     obstacleXOffset: 0.0,
     obstacleYOffset: 0.0,
     obstacleCircle: true,
+    airfoilCode: 2412,
+    angleOfAttack: 8.0,
+    inflow: 200.0,
+    drag: 0.0,
+    lift: 0.0,
+    boats: 3,
+    leaves: 6,
+    density: 1.0,
 };
+let bodyIds: number[] = [];
 const pointer = new Pointer([0, 0]);
 const pointerColor = new Float32Array(params.color.map((v) => v / 255.0));
 
@@ -56,12 +90,54 @@ const canvas = document.getElementById(canvasId) as HTMLCanvasElement;
 resizeCanvas(canvas);
 
 const renderer = Renderer.create(canvasId, params.simResolution, params.dyeResolution);
+const simulationConfig = new SimulationConfig();
 
 const resizeSimulation = () => {
     resizeCanvas(canvas);
     renderer.resize(params.simResolution, params.dyeResolution);
 }
 
+const setBoundaries = () => {
+    const boundaries = new Boundaries();
+    const edges: [Edge, BoundaryMode, number[]][] = [
+        [Edge.LEFT, params.boundaryLeft, [1.0, 0.0]],
+        [Edge.RIGHT, params.boundaryRight, [-1.0, 0.0]],
+        [Edge.BOTTOM, params.boundaryBottom, [0.0, 1.0]],
+        [Edge.TOP, params.boundaryTop, [0.0, -1.0]],
+    ];
+
+    // Inflows point into the canvas
+    edges.forEach(([edge, mode, direction]) => {
+        boundaries.set_mode(edge, Number(mode));
+        boundaries.set_inflow(edge, new Float32Array(direction.map((v) => v * params.inflowSpeed)));
+    });
+
+    renderer.set_boundaries(boundaries);
+    boundaries.free();
+}
+
+const setParticles = () => {
+    if (!params.particles) {
+        renderer.set_particles(undefined);
+        return;
+    }
+
+    const particles = new Particles(params.particleCount);
+    particles.set_lifetime(params.particleLifetime);
+    particles.set_size(params.particleSize);
+    particles.set_streak(params.particleStreak);
+    particles.set_color_mode(params.particleColorBySpeed ? ParticleColor.SPEED : ParticleColor.SOLID);
+    renderer.set_particles(particles);
+}
+
+const setStreamlines = () => {
+    const streamlines = new Streamlines();
+    streamlines.set_spacing(params.streamlineSpacing);
+    streamlines.set_length(params.streamlineLength);
+    renderer.set_streamlines(streamlines);
+    streamlines.free();
+}
+
 const generateColor = () => {
     if (!params.useRandomColor) return;
 
@@ -88,18 +164,38 @@ const createGUI = () => {
         {
             "Dye": Mode.DYE,
             "Velocity": Mode.VELOCITY,
+            "Streamlines": Mode.STREAMLINES,
+            "Pressure": Mode.PRESSURE,
+            "Divergence": Mode.DIVERGENCE,
+            "Curl": Mode.CURL,
+            "Speed": Mode.SPEED,
+            "Obstacles": Mode.OBSTACLES,
         },
     )
         .name("Mode");
+    visualsFolder.add(params, "legendMin").name("Legend min").listen();
+    visualsFolder.add(params, "legendMax").name("Legend max").listen();
     visualsFolder.add(params, "dyeResolution", resolutions)
         .name("Quality")
         .onFinishChange(resizeSimulation);
     visualsFolder.open();
 
+    const streamlinesFolder = visualsFolder.addFolder("Streamlines");
+    streamlinesFolder.add(params, "streamlineSpacing", 8.0, 128.0, 1.0).name("Spacing").onFinishChange(setStreamlines);
+    streamlinesFolder.add(params, "streamlineLength", 20.0, 600.0, 10.0).name("Length").onFinishChange(setStreamlines);
+
+    const particlesFolder = visualsFolder.addFolder("Particles");
+    particlesFolder.add(params, "particles").name("Show").onFinishChange(setParticles);
+    particlesFolder.add(params, "particleCount", 1024, isMobile() ? 65536 : 262144, 1024).name("Count").onFinishChange(setParticles);
+    particlesFolder.add(params, "particleLifetime", 0.5, 20.0, 0.1).name("Lifetime").onFinishChange(setParticles);
+    particlesFolder.add(params, "particleSize", 1.0, 8.0, 0.5).name("Size").onFinishChange(setParticles);
+    particlesFolder.add(params, "particleStreak", 0.0, 0.2, 0.005).name("Streak").onFinishChange(setParticles);
+    particlesFolder.add(params, "particleColorBySpeed").name("Color by speed").onFinishChange(setParticles);
+
     const simulationFolder = gui.addFolder("Simulation");
     simulationFolder.add(params, "viscosity", 0.0, 5.0, 0.01).name("Viscosity");
     simulationFolder.add(params, "dissipation", 0.0, 5.0, 0.01).name("Dye diffusion");
-    simulationFolder.add(params, "curl", 0.0, 2.0, 0.01).name("Vorticity amount");
+    simulationFolder.add(params, "curl", 0.0, 1.0, 0.01).name("Vorticity amount");
     simulationFolder.open();
 
     const advancedFolder = simulationFolder.addFolder("Advanced");
@@ -110,7 +206,29 @@ const createGUI = () => {
             resizeSimulation();
         });
     advancedFolder.add(params, "pressure", 0.0, 1.0, 0.01).name("Pressure");
+    advancedFolder.add(params, "advection", { "Semi-Lagrangian": Advection.SEMI_LAGRANGIAN, "MacCormack": Advection.MACCORMACK, "BFECC": Advection.BFECC }).name("Advection");
+    advancedFolder.add(params, "solver", { "Jacobi": Solver.JACOBI, "Multigrid": Solver.MULTIGRID, "SOR": Solver.SOR }).name("Solver");
     advancedFolder.add(params, "iterations", 10, isMobile() ? 50 : 80, 1).name("Solver iterations").listen();
+    advancedFolder.add(params, "cycles", 1, 8, 1).name("Multigrid cycles");
+    advancedFolder.add(params, "omega", 1.0, 1.95, 0.01).name("SOR omega");
+    advancedFolder.add(params, "tolerance", 0.0, 10.0, 0.01).name("Solver tolerance");
+    advancedFolder.add(params, "maxIterations", 10, 500, 1).name("Max iterations");
+    advancedFolder.add(params, "solverIterations").name("Iterations used").listen();
+    advancedFolder.add(params, "solverResidual").name("Residual").listen();
+
+    const boundaryModes = {
+        "No-slip wall": BoundaryMode.NO_SLIP,
+        "Free-slip wall": BoundaryMode.FREE_SLIP,
+        "Periodic": BoundaryMode.PERIODIC,
+        "Outflow": BoundaryMode.OUTFLOW,
+        "Inflow": BoundaryMode.INFLOW,
+    };
+    const boundaryFolder = advancedFolder.addFolder("Boundaries");
+    boundaryFolder.add(params, "boundaryLeft", boundaryModes).name("Left").onFinishChange(setBoundaries).listen();
+    boundaryFolder.add(params, "boundaryRight", boundaryModes).name("Right").onFinishChange(setBoundaries).listen();
+    boundaryFolder.add(params, "boundaryBottom", boundaryModes).name("Bottom").onFinishChange(setBoundaries).listen();
+    boundaryFolder.add(params, "boundaryTop", boundaryModes).name("Top").onFinishChange(setBoundaries).listen();
+    boundaryFolder.add(params, "inflowSpeed", 0.0, 1000.0, 1.0).name("Inflow speed").onFinishChange(setBoundaries);
     
     const configurationFolder = gui.addFolder("Configuration");
     let settingsFolder: dat.GUI | undefined;
@@ -122,6 +240,8 @@ const createGUI = () => {
             "None": Configuration.NONE,
             "Spells": Configuration.SPELLS,
             "Spin": Configuration.SPIN,
+            "Wind tunnel": Configuration.WIND_TUNNEL,
+            "Floating": Configuration.FLOATING,
         },
     )
         .name("Configuration")
@@ -131,6 +251,19 @@ const createGUI = () => {
                 settingsFolder = undefined;
             }
 
+            if (renderer.wind_tunnel()) {
+                renderer.set_wind_tunnel(undefined);
+                renderer.set_measure_forces(false);
+                renderer.clear_obstacles();
+                params.boundaryLeft = params.boundaryRight = BoundaryMode.FREE_SLIP;
+                params.boundaryBottom = params.boundaryTop = BoundaryMode.FREE_SLIP;
+            }
+
+            if (bodyIds.length) {
+                for (const id of bodyIds) renderer.remove_obstacle(id);
+                bodyIds = [];
+            }
+
             if (value == Configuration.SPELLS) {
                 config.lRadius = config.rRadius = 0.2;
                 config.lStrength = config.rStrength = 10.0;
@@ -171,6 +304,85 @@ const createGUI = () => {
                 settingsFolder.add(config, "lXOffset", -1.0, 1.0, 0.01).name("X");
                 settingsFolder.add(config, "lYOffset", -1.0, 1.0, 0.01).name("Y");
 
+                settingsFolder.open();
+            } else if (value == Configuration.WIND_TUNNEL) {
+                const windTunnel = () => {
+                    // The last two digits are the thickness, a NACA code without any is not an airfoil
+                    if (config.airfoilCode % 100 == 0) return;
+
+                    renderer.wind_tunnel_preset(
+                        config.airfoilCode,
+                        config.angleOfAttack * Math.PI / 180.0,
+                        config.inflow,
+                    );
+
+                    // The wind tunnel replaces the boundaries
+                    params.boundaryLeft = BoundaryMode.INFLOW;
+                    params.boundaryRight = BoundaryMode.OUTFLOW;
+                    params.boundaryBottom = params.boundaryTop = BoundaryMode.FREE_SLIP;
+                    params.inflowSpeed = config.inflow;
+                };
+                windTunnel();
+                renderer.set_measure_forces(true);
+
+                settingsFolder = configurationFolder.addFolder("Wind Tunnel Settings");
+                settingsFolder.add(config, "airfoilCode", 1, 9999, 1).name("NACA code").onFinishChange(windTunnel);
+                settingsFolder.add(config, "angleOfAttack", -20.0, 20.0, 0.1).name("Angle of attack").onFinishChange(windTunnel);
+                settingsFolder.add(config, "inflow", 0.0, 1000.0, 1.0).name("Inflow").onFinishChange(windTunnel);
+                settingsFolder.add(config, "drag").name("Drag").listen();
+                settingsFolder.add(config, "lift").name("Lift").listen();
+
+                settingsFolder.open();
+            } else if (value == Configuration.FLOATING) {
+                const floating = () => {
+                    for (const id of bodyIds) renderer.remove_obstacle(id);
+                    bodyIds = [];
+
+                    const size = Math.min(canvas.width, canvas.height);
+                    const float = (obstacle: Obstacle) => {
+                        const id = renderer.add_obstacle(obstacle);
+                        renderer.set_rigid_body(id, RigidBody.from_density(obstacle, config.density));
+                        obstacle.free();
+                        bodyIds.push(id);
+                    };
+                    const position = () => new Float32Array([
+                        (0.1 + 0.8 * Math.random()) * canvas.width,
+                        (0.1 + 0.8 * Math.random()) * canvas.height,
+                    ]);
+
+                    for (let i = 0; i < config.boats; i++) {
+                        float(Obstacle.capsule(
+                            0.08 * size,
+                            0.02 * size,
+                            position(),
+                            2.0 * Math.PI * Math.random(),
+                            new Float32Array([0.8, 0.5, 0.2]),
+                        ));
+                    }
+
+                    // A leaf is a lens with a pointed tip at either end
+                    const leaf = new Float32Array(2 * 16);
+                    for (let i = 0; i < 16; i++) {
+                        const t = Math.PI * i / 8;
+                        leaf[2 * i] = 0.04 * size * Math.cos(t);
+                        leaf[2 * i + 1] = 0.015 * size * Math.sin(t) * Math.abs(Math.sin(t));
+                    }
+                    for (let i = 0; i < config.leaves; i++) {
+                        float(Obstacle.polygon(
+                            leaf,
+                            position(),
+                            2.0 * Math.PI * Math.random(),
+                            new Float32Array([0.3, 0.7 + 0.2 * Math.random(), 0.2]),
+                        ));
+                    }
+                };
+                floating();
+
+                settingsFolder = configurationFolder.addFolder("Floating Settings");
+                settingsFolder.add(config, "boats", 0, 10, 1).name("Boats").onFinishChange(floating);
+                settingsFolder.add(config, "leaves", 0, 20, 1).name("Leaves").onFinishChange(floating);
+                settingsFolder.add(config, "density", 0.5, 5.0, 0.1).name("Density").onFinishChange(floating);
+
                 settingsFolder.open();
             }
         });
@@ -300,16 +512,40 @@ const update = (timestamp: number) => {
         else if (params.config == Configuration.SPIN) spinConfig(radius, timestamp / 1000);
     }
 
-    renderer.update(
-        params.isPaused,
-        timestamp / 1000,
-        params.mode,
-        params.iterations,
-        params.viscosity,
-        params.dissipation,
-        -params.curl,
-        params.pressure,
-    );
+    simulationConfig.set_pause(params.isPaused);
+    simulationConfig.set_mode(params.mode);
+    simulationConfig.set_solver(Number(params.solver));
+    simulationConfig.set_iterations(params.iterations);
+    simulationConfig.set_cycles(params.cycles);
+    simulationConfig.set_omega(params.omega);
+    simulationConfig.set_tolerance(params.tolerance);
+    simulationConfig.set_max_iterations(params.maxIterations);
+    simulationConfig.set_advection(Number(params.advection));
+    simulationConfig.set_viscosity(params.viscosity);
+    simulationConfig.set_dissipation(params.dissipation);
+    simulationConfig.set_curl(params.curl);
+    simulationConfig.set_pressure(params.pressure);
+
+    renderer.update(timestamp / 1000, simulationConfig);
+
+    // Diagnostic modes are auto-ranged, the other ones have no legend
+    const legend = renderer.legend();
+    params.legendMin = legend.length ? legend[0] : 0.0;
+    params.legendMax = legend.length ? legend[1] : 0.0;
+
+    // The residual is only measured when solving to a tolerance
+    params.solverIterations = renderer.pressure_iterations();
+    params.solverResidual = renderer.pressure_residual() ?? 0.0;
+
+    if (params.config == Configuration.WIND_TUNNEL) {
+        // The airfoil is the only obstacle, the flow comes from the left
+        const force = renderer.forces()[0];
+        if (force) {
+            config.drag = force.drag(new Float32Array([1.0, 0.0]))[0];
+            config.lift = force.lift(new Float32Array([1.0, 0.0]))[1];
+            force.free();
+        }
+    }
 
     pointer.resetMove();
 }
@@ -0,0 +1,508 @@
+//! Abstraction over the hardware (or software) that runs the simulation passes
+//!
+//! A [backend](SimulationBackend) owns textures and knows how to run every [pass](Pass) of the
+//! simulation on them. The [simulation](crate::simulation::Simulation) only ever talks to the
+//! backend, so the same solver logic runs on WebGL2 and on the CPU.
+
+use std::mem;
+use crate::boundary::BoundaryMode;
+use crate::colormap::Colormap;
+use crate::grid::{ Filter, Wrap };
+use crate::obstacle::ShapeKind;
+use crate::particles::ParticleColor;
+
+/// A texture owned by a [backend](SimulationBackend)
+pub trait BackendTexture {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+}
+
+/// A single pass of the simulation, together with its uniforms and input textures
+///
+/// Every variant corresponds to the fragment shader of the same name in `src/shaders`. Boundary modes
+/// and inflows are given per edge, in the order left, right, bottom, top.
+pub enum Pass<'a, T> {
+    Copy {
+        factor: f32,
+        offset: f32,
+        texture: &'a T,
+    },
+    Advection {
+        dissipation: f32,
+        delta_time: f32,
+        resolution: [f32; 2],
+        velocity: &'a T,
+        quantity: &'a T,
+        obstacles: &'a T,
+    },
+    /// Half the error of an [advection](Pass::Advection) of `quantity` into `advected`, found by advecting
+    /// `advected` back in time, for [CorrectedAdvection](Pass::CorrectedAdvection)
+    AdvectionError {
+        delta_time: f32,
+        resolution: [f32; 2],
+        velocity: &'a T,
+        quantity: &'a T,
+        advected: &'a T,
+    },
+    /// An [advection](Pass::Advection) of `quantity`, a texture of `quantity_resolution` texels, corrected
+    /// by the [error](Pass::AdvectionError) of a first one
+    ///
+    /// With `bfecc` the error is compensated before the advection, otherwise it is corrected after it
+    /// (MacCormack). The result is clamped to the texels it is interpolated from.
+    CorrectedAdvection {
+        bfecc: bool,
+        dissipation: f32,
+        delta_time: f32,
+        resolution: [f32; 2],
+        quantity_resolution: [f32; 2],
+        velocity: &'a T,
+        quantity: &'a T,
+        error: &'a T,
+        obstacles: &'a T,
+    },
+    Divergence {
+        boundary: [BoundaryMode; 4],
+        inflow: [[f32; 2]; 4],
+        r_half_texel_size: f32,
+        resolution: [f32; 2],
+        velocity: &'a T,
+        obstacles: &'a T,
+        obstacle_velocity: &'a T,
+    },
+    /// A Jacobi iteration of `x` towards the solution of the pressure equation with the divergence `b`,
+    /// weighted by `omega` (one for plain Jacobi)
+    PressureSolver {
+        boundary: [BoundaryMode; 4],
+        alpha: f32,
+        r_beta: f32,
+        omega: f32,
+        resolution: [f32; 2],
+        x: &'a T,
+        b: &'a T,
+        obstacles: &'a T,
+    },
+    /// A successive over-relaxation pass of the same equation as [PressureSolver](Pass::PressureSolver), which
+    /// only updates the texels whose column and row add up to `parity`
+    ///
+    /// Two passes of opposite parity make a red-black Gauss-Seidel iteration, over-relaxed by `omega`.
+    PressureSor {
+        boundary: [BoundaryMode; 4],
+        alpha: f32,
+        omega: f32,
+        parity: u32,
+        resolution: [f32; 2],
+        x: &'a T,
+        b: &'a T,
+        obstacles: &'a T,
+    },
+    /// Writes what is left of `b` once `x` is put into the pressure equation of [PressureSolver](Pass::PressureSolver),
+    /// zero on solid texels
+    Residual {
+        boundary: [BoundaryMode; 4],
+        alpha: f32,
+        resolution: [f32; 2],
+        x: &'a T,
+        b: &'a T,
+        obstacles: &'a T,
+    },
+    /// Averages the four texels of `texture` that every texel of a target half its size (`resolution`) covers
+    Restrict {
+        resolution: [f32; 2],
+        texture: &'a T,
+    },
+    /// Adds `correction`, a coarser level of a multigrid solve, interpolated onto `x`
+    Prolong {
+        x: &'a T,
+        correction: &'a T,
+    },
+    /// Sums the two by two texels of `texture`, a texture of `resolution` texels, that every texel of a target
+    /// half its size (rounded up) covers, squared if `square` is set
+    ///
+    /// Repeated down to a single texel it sums up a whole texture, for which the target should be a
+    /// [data texture](SimulationBackend::create_data_texture).
+    Reduce {
+        square: bool,
+        resolution: [f32; 2],
+        texture: &'a T,
+    },
+    GradientSubtract {
+        boundary: [BoundaryMode; 4],
+        inflow: [[f32; 2]; 4],
+        r_half_texel_size: f32,
+        resolution: [f32; 2],
+        velocity: &'a T,
+        pressure: &'a T,
+        obstacles: &'a T,
+        obstacle_velocity: &'a T,
+    },
+    Curl {
+        r_half_texel_size: f32,
+        resolution: [f32; 2],
+        velocity: &'a T,
+    },
+    Vorticity {
+        curl_scale: f32,
+        r_half_texel_size: f32,
+        resolution: [f32; 2],
+        curl: &'a T,
+        velocity: &'a T,
+    },
+    /// Adds a gaussian splat of `color` to `texture`, wrapped around every axis with a `period` larger than zero
+    Splat {
+        scaled_radius: f32,
+        position: [f32; 2],
+        period: [f32; 2],
+        color: [f32; 3],
+        texture: &'a T,
+        obstacles: &'a T,
+    },
+    /// Draws the obstacle over `obstacles`, either its color or, with `write_velocity`, its velocity and `index`
+    ///
+    /// `shape` is one of the analytic shapes, the meaning of `size` depends on it: the radius of a
+    /// circle, half the sides of a square or rectangle, half the length and the radius of a capsule, or
+    /// the inner and outer radius of an annulus.
+    Obstacle {
+        shape: ShapeKind,
+        write_velocity: bool,
+        index: u32,
+        size: [f32; 2],
+        position: [f32; 2],
+        rotation: [f32; 2],
+        linear_velocity: [f32; 2],
+        angular_velocity: f32,
+        obstacle_color: [f32; 3],
+        obstacles: &'a T,
+    },
+    /// Draws the obstacle over `obstacles`, either its color or, with `write_velocity`, its velocity and `index`
+    ObstacleMask {
+        write_velocity: bool,
+        index: u32,
+        threshold: f32,
+        position: [f32; 2],
+        half_size: [f32; 2],
+        rotation: [f32; 2],
+        linear_velocity: [f32; 2],
+        angular_velocity: f32,
+        obstacle_color: [f32; 3],
+        mask: &'a T,
+        obstacles: &'a T,
+    },
+    /// Draws the obstacle over `obstacles`, either its color or, with `write_velocity`, its velocity and `index`
+    ///
+    /// `vertices` holds one vertex per texel of its only row, relative to `position`.
+    ObstaclePolygon {
+        write_velocity: bool,
+        index: u32,
+        position: [f32; 2],
+        rotation: [f32; 2],
+        linear_velocity: [f32; 2],
+        angular_velocity: f32,
+        obstacle_color: [f32; 3],
+        vertices: &'a T,
+        obstacles: &'a T,
+    },
+    /// Writes the pressure (first two channels) and viscous force (last two) of the fluid on every solid texel
+    ///
+    /// The target is twice as tall as the grid of `resolution`: the forces fill its lower half, and the
+    /// upper half holds the obstacle index of every texel (first channel), so that a single readback
+    /// tells which obstacle every force acts on.
+    Force {
+        pressure_scale: f32,
+        viscous_scale: f32,
+        resolution: [f32; 2],
+        pressure: &'a T,
+        velocity: &'a T,
+        obstacles: &'a T,
+        obstacle_velocity: &'a T,
+    },
+    /// Moves every particle of `particles`, a position in texture coordinates, an age and a lifetime per
+    /// texel, along `velocity` and emits the particles that died again
+    ///
+    /// `emitters` holds one emitter per texel of its only row, as a position and a radius along either
+    /// axis in texture coordinates, particles are emitted anywhere if there are none.
+    Particles {
+        delta_time: f32,
+        lifetime: f32,
+        seed: f32,
+        period: [f32; 2],
+        resolution: [f32; 2],
+        particles: &'a T,
+        velocity: &'a T,
+        obstacles: &'a T,
+        emitters: Option<&'a T>,
+    },
+    /// Draws the first `count` particles of `particles` over the contents of the target, as points of
+    /// `size` texels or as streaks `streak` seconds long
+    ///
+    /// Unlike every other pass it only writes the texels the particles cover.
+    DrawParticles {
+        count: u32,
+        size: f32,
+        streak: f32,
+        color_mode: ParticleColor,
+        color: [f32; 3],
+        max_speed: f32,
+        resolution: [f32; 2],
+        particles: &'a T,
+        velocity: &'a T,
+    },
+    /// Traces a streamline from every seed through `velocity`, one per row of the target, with the position
+    /// in texture coordinates, the speed and whether the line still goes on after as many steps as the column
+    ///
+    /// `seeds` holds one seed per texel of its only row, as a position in texture coordinates, seeds lie on
+    /// a grid of `grid` cells if there are none. Steps are `step_size` texels of `velocity` long.
+    Streamlines {
+        step_size: f32,
+        grid: [f32; 2],
+        period: [f32; 2],
+        resolution: [f32; 2],
+        seeds: Option<&'a T>,
+        velocity: &'a T,
+        obstacles: &'a T,
+    },
+    /// Draws every streamline of `lines`, traced by a [Streamlines](Pass::Streamlines) pass, over the contents of the target
+    ///
+    /// Like [DrawParticles](Pass::DrawParticles) it only writes the texels the lines cover.
+    DrawStreamlines {
+        max_speed: f32,
+        lines: &'a T,
+    },
+    /// Maps the first channel of `texture`, or the length of the first two if `magnitude` is set, from
+    /// `range` onto `colormap`, and draws the solid texels of `obstacles` in their colors
+    Colormap {
+        colormap: Colormap,
+        range: [f32; 2],
+        magnitude: bool,
+        texture: &'a T,
+        obstacles: Option<&'a T>,
+    },
+    Streaklines {
+        count: u32,
+        radius: f32,
+        length: f32,
+        resolution: [f32; 2],
+        color: [f32; 3],
+        texture: &'a T,
+    },
+    ColorObstacle {
+        intensity: f32,
+        obstacles: &'a T,
+        texture: &'a T,
+    },
+}
+
+/// Backend that stores the simulation textures and runs the simulation passes
+pub trait SimulationBackend {
+    type Error;
+    type Texture: BackendTexture;
+
+    /// Allocate a new texture cleared to zero
+    fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        filter: Filter,
+    ) -> Result<Self::Texture, Self::Error>;
+
+    /// Allocate a new texture cleared to zero, with `f32` channels and [nearest](Filter::Nearest) filtering
+    ///
+    /// For data that needs more precision than the regular textures hold, like the positions of particles.
+    fn create_data_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<Self::Texture, Self::Error>;
+
+    /// Set the wrapping of `texture` along the x and the y axis, textures are created clamped
+    fn set_wrap(&mut self, texture: &mut Self::Texture, wrap: [Wrap; 2]);
+
+    /// Free a texture that is no longer used
+    fn delete_texture(&mut self, texture: Self::Texture);
+
+    /// Read the contents of `texture` as `RGBA` floats, row by row from the bottom
+    fn read_texture(&mut self, texture: &Self::Texture) -> Result<Vec<f32>, Self::Error>;
+
+    /// Overwrite the contents of `texture` with `RGBA` floats, row by row from the bottom
+    ///
+    /// `data` must hold exactly four values per texel.
+    fn write_texture(
+        &mut self,
+        texture: &mut Self::Texture,
+        data: &[f32],
+    ) -> Result<(), Self::Error>;
+
+    /// Run `pass` for every texel of `target`, or for every particle or line of [DrawParticles](Pass::DrawParticles)
+    /// and [DrawStreamlines](Pass::DrawStreamlines)
+    ///
+    /// `target` must not be one of the inputs of the pass.
+    fn dispatch(
+        &mut self,
+        pass: Pass<'_, Self::Texture>,
+        target: &mut Self::Texture,
+    ) -> Result<(), Self::Error>;
+
+    /// Draw `texture * factor + offset` to the output of the backend (the canvas, for WebGL)
+    fn present(
+        &mut self,
+        texture: &Self::Texture,
+        factor: f32,
+        offset: f32,
+    ) -> Result<(), Self::Error>;
+}
+
+/// A pair of textures that are read from and written to in turns
+pub struct PingPong<T> {
+    read: T,
+    write: T,
+    filter: Filter,
+    wrap: [Wrap; 2],
+    data: bool,
+}
+
+impl<T: BackendTexture> PingPong<T> {
+    pub fn new<B: SimulationBackend<Texture = T>>(
+        backend: &mut B,
+        width: u32,
+        height: u32,
+        filter: Option<Filter>,
+    ) -> Result<PingPong<T>, B::Error> {
+        let filter = filter.unwrap_or(Filter::Linear);
+
+        let read = backend.create_texture(width, height, filter)?;
+        let write = backend.create_texture(width, height, filter)?;
+
+        Ok(PingPong {
+            read,
+            write,
+            filter,
+            wrap: [Wrap::Clamp; 2],
+            data: false,
+        })
+    }
+
+    /// Create a pair of [data textures](SimulationBackend::create_data_texture)
+    pub fn new_data<B: SimulationBackend<Texture = T>>(
+        backend: &mut B,
+        width: u32,
+        height: u32,
+    ) -> Result<PingPong<T>, B::Error> {
+        let read = backend.create_data_texture(width, height)?;
+        let write = backend.create_data_texture(width, height)?;
+
+        Ok(PingPong {
+            read,
+            write,
+            filter: Filter::Nearest,
+            wrap: [Wrap::Clamp; 2],
+            data: true,
+        })
+    }
+
+    /// Create an empty buffer like this one, of `width` x `height` texels
+    fn new_like<B: SimulationBackend<Texture = T>>(
+        &self,
+        backend: &mut B,
+        width: u32,
+        height: u32,
+    ) -> Result<PingPong<T>, B::Error> {
+        let mut buffer = match self.data {
+            true => PingPong::new_data(backend, width, height)?,
+            false => PingPong::new(backend, width, height, Some(self.filter))?,
+        };
+        buffer.set_wrap(backend, self.wrap);
+
+        Ok(buffer)
+    }
+
+    /// Set the wrapping of both textures along the x and the y axis, it is kept when the buffer is resized
+    pub fn set_wrap<B: SimulationBackend<Texture = T>>(&mut self, backend: &mut B, wrap: [Wrap; 2]) {
+        backend.set_wrap(&mut self.read, wrap);
+        backend.set_wrap(&mut self.write, wrap);
+        self.wrap = wrap;
+    }
+
+    /// Resize the buffer
+    ///
+    /// If `copy` is `true` the current contents are resampled into the new size, otherwise the buffer is cleared.
+    pub fn resize<B: SimulationBackend<Texture = T>>(
+        &mut self,
+        backend: &mut B,
+        copy: bool,
+        width: u32,
+        height: u32,
+    ) -> Result<(), B::Error> {
+        if width == self.read.width() && height == self.read.height() {
+            return Ok(());
+        }
+
+        let mut new_buffer = self.new_like(
+            backend,
+            width,
+            height,
+        )?;
+
+        // COPY
+        if copy {
+            backend.dispatch(
+                Pass::Copy {
+                    factor: 1.0,
+                    offset: 0.0,
+                    texture: &self.read,
+                },
+                &mut new_buffer.read,
+            )?;
+        }
+
+        // DELETE AND SET
+        let old_buffer = mem::replace(self, new_buffer);
+        backend.delete_texture(old_buffer.read);
+        backend.delete_texture(old_buffer.write);
+
+        Ok(())
+    }
+
+    /// Replace the contents of the buffer with `data`, changing its size to `width` x `height`
+    pub fn upload<B: SimulationBackend<Texture = T>>(
+        &mut self,
+        backend: &mut B,
+        width: u32,
+        height: u32,
+        data: &[f32],
+    ) -> Result<(), B::Error> {
+        let mut new_buffer = self.new_like(
+            backend,
+            width,
+            height,
+        )?;
+        backend.write_texture(&mut new_buffer.read, data)?;
+
+        let old_buffer = mem::replace(self, new_buffer);
+        backend.delete_texture(old_buffer.read);
+        backend.delete_texture(old_buffer.write);
+
+        Ok(())
+    }
+
+    /// Delete both textures
+    pub fn delete<B: SimulationBackend<Texture = T>>(self, backend: &mut B) {
+        backend.delete_texture(self.read);
+        backend.delete_texture(self.write);
+    }
+
+    pub fn swap(&mut self) {
+        mem::swap(&mut self.read, &mut self.write);
+    }
+
+    pub fn read(&self) -> &T {
+        &self.read
+    }
+
+    pub fn write(&mut self) -> &mut T {
+        &mut self.write
+    }
+
+    /// Borrow the read and the write texture at the same time
+    pub fn split(&mut self) -> (&T, &mut T) {
+        (&self.read, &mut self.write)
+    }
+}
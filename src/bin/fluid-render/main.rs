@@ -0,0 +1,336 @@
+//! `fluid-render`: run the fluid simulation without a browser and write every frame to disk
+//!
+//! ```text
+//! fluid-render --output frames --steps 300 --script splats.txt --format png
+//! ```
+//!
+//! See [script] for the format of the script file and `fluid-render --help` for all options.
+
+mod image;
+mod script;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use fluid_simulation::{ Advection, BackendTexture, CpuBackend, CpuSolver, Mode, Obstacle, Precision, Resolution, RigidBody, SimulationConfig, Snapshot, Solver, WindTunnel };
+use crate::image::Format;
+use crate::script::{ Command, Script };
+
+const USAGE: &str = "\
+Usage: fluid-render --output <DIR> [OPTIONS]
+
+Options:
+    --output <DIR>            Directory the frames are written to
+    --script <FILE>           Script of splats and obstacles to apply
+    --load-state <FILE>       Snapshot to start from
+    --save-state <FILE>       Write a snapshot after the last step
+    --forces <FILE>           Write the force on every obstacle after every step as CSV
+    --steps <N>               Number of steps to simulate [default: 300]
+    --dt <SECONDS>            Fixed timestep of every step (> 0) [default: 0.0166667]
+    --width <PIXELS>          Width of the domain [default: 512]
+    --height <PIXELS>         Height of the domain [default: 512]
+    --sim-resolution <N>      Simulation scaling: 1, 2, 4, 8 or 16 [default: 4]
+    --dye-resolution <N>      Dye scaling: 1, 2, 4, 8 or 16 [default: 2]
+    --mode <MODE>             dye, velocity, streamlines, pressure, divergence, curl,
+                              speed or obstacles [default: dye]
+    --format <FORMAT>         png or ppm [default: png]
+    --solver <SOLVER>         Pressure solver: jacobi, multigrid, sor or cg, which solves
+                              exactly on the CPU [default: jacobi]
+    --iterations <N>          Pressure solver iterations [default: 20]
+    --cycles <N>              V-cycles of the multigrid solver [default: 2]
+    --omega <VALUE>           Over-relaxation of the SOR solver, strictly between 0 and 2
+                              [default: 1.7]
+    --tolerance <VALUE>       Residual at which the pressure solve stops early, 0 to
+                              always run every iteration [default: 0]
+    --max-iterations <N>      Iterations or cycles of a solve to a tolerance [default: 200]
+    --advection <SCHEME>      semi-lagrangian, maccormack or bfecc [default: semi-lagrangian]
+    --viscosity <VALUE>       [default: 0.5]
+    --dissipation <VALUE>     [default: 2.0]
+    --curl <VALUE>            Between 0 and 1 [default: 0.25]
+    --pressure <VALUE>        Between 0 and 1 [default: 0.8]
+    --help                    Print this message";
+
+struct Options {
+    output: PathBuf,
+    script: Option<PathBuf>,
+    load_state: Option<PathBuf>,
+    save_state: Option<PathBuf>,
+    forces: Option<PathBuf>,
+    steps: usize,
+    delta_time: f32,
+    width: u32,
+    height: u32,
+    sim_resolution: Resolution,
+    dye_resolution: Resolution,
+    format: Format,
+    config: SimulationConfig,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+        let mut output = None;
+        let mut options = Options {
+            output: PathBuf::new(),
+            script: None,
+            load_state: None,
+            save_state: None,
+            forces: None,
+            steps: 300,
+            delta_time: 1.0 / 60.0,
+            width: 512,
+            height: 512,
+            sim_resolution: Resolution::FOUR,
+            dye_resolution: Resolution::TWO,
+            format: Format::Png,
+            config: SimulationConfig::default(),
+        };
+
+        while let Some(flag) = args.next() {
+            if flag == "--help" || flag == "-h" {
+                return Ok(None);
+            }
+
+            let value = args.next().ok_or_else(|| format!("missing value for `{}`", flag))?;
+            match flag.as_str() {
+                "--output" => output = Some(PathBuf::from(value)),
+                "--script" => options.script = Some(PathBuf::from(value)),
+                "--load-state" => options.load_state = Some(PathBuf::from(value)),
+                "--save-state" => options.save_state = Some(PathBuf::from(value)),
+                "--forces" => options.forces = Some(PathBuf::from(value)),
+                "--steps" => options.steps = parse(&flag, &value)?,
+                "--dt" => options.delta_time = match parse(&flag, &value)? {
+                    dt if dt > 0.0 => dt,
+                    _ => return Err("`--dt` must be positive".into()),
+                },
+                "--width" => options.width = parse(&flag, &value)?,
+                "--height" => options.height = parse(&flag, &value)?,
+                "--sim-resolution" => options.sim_resolution = resolution(&value)?,
+                "--dye-resolution" => options.dye_resolution = resolution(&value)?,
+                "--mode" => options.config.set_mode(match value.as_str() {
+                    "dye" => Mode::DYE,
+                    "velocity" => Mode::VELOCITY,
+                    "streamlines" => Mode::STREAMLINES,
+                    "pressure" => Mode::PRESSURE,
+                    "divergence" => Mode::DIVERGENCE,
+                    "curl" => Mode::CURL,
+                    "speed" => Mode::SPEED,
+                    "obstacles" => Mode::OBSTACLES,
+                    _ => return Err(format!("unknown mode `{}`", value)),
+                }),
+                "--format" => options.format = match value.as_str() {
+                    "png" => Format::Png,
+                    "ppm" => Format::Ppm,
+                    _ => return Err(format!("unknown format `{}`", value)),
+                },
+                "--solver" => options.config.set_solver(match value.as_str() {
+                    "jacobi" => Solver::JACOBI,
+                    "multigrid" => Solver::MULTIGRID,
+                    "sor" => Solver::SOR,
+                    "cg" => Solver::CONJUGATE_GRADIENT,
+                    _ => return Err(format!("unknown solver `{}`", value)),
+                }),
+                "--iterations" => options.config.set_iterations(parse(&flag, &value)?),
+                "--cycles" => options.config.set_cycles(parse(&flag, &value)?),
+                "--omega" => options.config.set_omega(parse(&flag, &value)?).map_err(|e| e.to_string())?,
+                "--tolerance" => options.config.set_tolerance(parse(&flag, &value)?).map_err(|e| e.to_string())?,
+                "--max-iterations" => options.config.set_max_iterations(parse(&flag, &value)?),
+                "--advection" => options.config.set_advection(match value.as_str() {
+                    "semi-lagrangian" => Advection::SEMI_LAGRANGIAN,
+                    "maccormack" => Advection::MACCORMACK,
+                    "bfecc" => Advection::BFECC,
+                    _ => return Err(format!("unknown advection `{}`", value)),
+                }),
+                "--viscosity" => options.config.set_viscosity(parse(&flag, &value)?).map_err(|e| e.to_string())?,
+                "--dissipation" => options.config.set_dissipation(parse(&flag, &value)?).map_err(|e| e.to_string())?,
+                "--curl" => options.config.set_curl(parse(&flag, &value)?).map_err(|e| e.to_string())?,
+                "--pressure" => options.config.set_pressure(parse(&flag, &value)?).map_err(|e| e.to_string())?,
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+
+        options.output = output.ok_or("missing `--output`")?;
+
+        Ok(Some(options))
+    }
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{}` for `{}`", value, flag))
+}
+
+fn resolution(value: &str) -> Result<Resolution, String> {
+    match value {
+        "1" => Ok(Resolution::ONE),
+        "2" => Ok(Resolution::TWO),
+        "4" => Ok(Resolution::FOUR),
+        "8" => Ok(Resolution::EIGHT),
+        "16" => Ok(Resolution::SIXTEEN),
+        _ => Err(format!("invalid resolution `{}`, expected 1, 2, 4, 8 or 16", value)),
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let script = match &options.script {
+        Some(path) => {
+            let source = std::fs::read_to_string(path)
+                .map_err(|e| format!("unable to read `{}`: {}", path.display(), e))?;
+            Script::parse(&source)?
+        }
+        None => Script::empty(),
+    };
+
+    std::fs::create_dir_all(&options.output)
+        .map_err(|e| format!("unable to create `{}`: {}", options.output.display(), e))?;
+
+    let Ok(mut solver) = CpuSolver::new(
+        CpuBackend::new(),
+        options.width,
+        options.height,
+        options.sim_resolution,
+        options.dye_resolution,
+    );
+    solver.set_timestep(options.delta_time);
+    solver.set_measure_forces(options.forces.is_some());
+    let mut forces = String::from("step,id,pressure_x,pressure_y,viscous_x,viscous_y\n");
+
+    if let Some(path) = &options.load_state {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("unable to read `{}`: {}", path.display(), e))?;
+        let snapshot = Snapshot::from_bytes(&bytes)
+            .map_err(|e| format!("unable to load `{}`: {}", path.display(), e))?;
+        let Ok(()) = solver.restore(&snapshot);
+    }
+
+    for step in 0..options.steps {
+        for command in script.commands(step) {
+            let Ok(()) = match command {
+                Command::Splat { radius, position, velocity, color } => solver.splat(
+                    *radius,
+                    position,
+                    velocity,
+                    color,
+                ),
+                Command::Obstacle { radius, position, color, is_circle } => solver.set_obstacle(
+                    *radius,
+                    position,
+                    color,
+                    *is_circle,
+                    None,
+                ),
+                Command::Airfoil { code, chord, position, angle_of_attack, color } => solver.add_obstacle(
+                    Obstacle::naca(
+                        *code,
+                        *chord,
+                        position,
+                        *angle_of_attack,
+                        color,
+                    ).map_err(|e| e.to_string())?,
+                ).map(|_| ()),
+                Command::Body { size, position, density, color } => {
+                    let obstacle = Obstacle::rectangle(size, position, 0.0, color);
+                    let body = RigidBody::from_density(&obstacle, *density).map_err(|e| e.to_string())?;
+                    solver.add_obstacle(obstacle).map(|id| {
+                        solver.set_rigid_body(id, Some(body));
+                    })
+                }
+                Command::WindTunnel { inflow, streaklines } => {
+                    solver.set_wind_tunnel(inflow.map(|inflow| WindTunnel::new(
+                        inflow,
+                        *streaklines,
+                        &[1.0, 1.0, 1.0],
+                    )));
+                    Ok(())
+                }
+                Command::Boundaries { boundaries } => {
+                    solver.set_boundaries(boundaries.clone());
+                    Ok(())
+                }
+                Command::Particles { particles } => solver.set_particles(particles.clone()),
+                Command::Emitter { position, radius } => match solver.particles().cloned() {
+                    Some(mut particles) => {
+                        particles.add_emitter(position, *radius);
+                        solver.set_particles(Some(particles))
+                    }
+                    None => Ok(()),
+                },
+                Command::Streamlines { spacing, length, steps } => {
+                    let mut streamlines = solver.streamlines().clone();
+                    streamlines.set_spacing(*spacing);
+                    streamlines.set_length(*length);
+                    streamlines.set_steps(*steps);
+                    solver.set_streamlines(streamlines)
+                }
+                Command::Seed { position } => {
+                    let mut streamlines = solver.streamlines().clone();
+                    streamlines.add_seed(position);
+                    solver.set_streamlines(streamlines)
+                }
+            };
+        }
+
+        let Ok(()) = solver.step_fixed(1, &options.config);
+        let Ok(()) = solver.draw(options.config.mode());
+
+        for force in solver.forces() {
+            let (pressure, viscous) = (force.pressure(), force.viscous());
+            forces.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                step,
+                force.id(),
+                pressure[0],
+                pressure[1],
+                viscous[0],
+                viscous[1],
+            ));
+        }
+
+        // WRITE FRAME
+        let frame = solver.backend().frame();
+        let (width, height) = (frame.width(), frame.height());
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for y in (0..height as i64).rev() {
+            for x in 0..width as i64 {
+                let texel = frame.texel(x, y);
+                rgb.extend(texel[..3].iter().map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8));
+            }
+        }
+
+        let path = options.output.join(format!("frame_{:05}.{}", step, options.format.extension()));
+        image::write(&path, options.format, width, height, &rgb)
+            .map_err(|e| format!("unable to write `{}`: {}", path.display(), e))?;
+    }
+
+    if let Some(path) = &options.forces {
+        std::fs::write(path, forces)
+            .map_err(|e| format!("unable to write `{}`: {}", path.display(), e))?;
+    }
+
+    if let Some(path) = &options.save_state {
+        let Ok(snapshot) = solver.snapshot();
+        std::fs::write(path, snapshot.to_bytes(Precision::SINGLE, true))
+            .map_err(|e| format!("unable to write `{}`: {}", path.display(), e))?;
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
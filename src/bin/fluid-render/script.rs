@@ -0,0 +1,249 @@
+//! Scripted splats and obstacles for `fluid-render`
+//!
+//! A script is a text file with one command per line. Empty lines and lines starting with `#` are
+//! ignored. Every command starts with the step (or an inclusive range of steps, `first..last`) on which
+//! it is applied:
+//!
+//! ```text
+//! # STEPS   COMMAND   ARGUMENTS
+//! 0..60     splat     <radius> <x> <y> <vx> <vy> <r> <g> <b>
+//! 0         obstacle  <circle|square> <radius> <x> <y> <r> <g> <b>
+//! 120       obstacle  none
+//! 0         airfoil   <code> <chord> <x> <y> <angle of attack> <r> <g> <b>
+//! 0         body      <width> <height> <x> <y> <density> <r> <g> <b>
+//! 0         wind-tunnel <inflow> <streaklines>
+//! 240       wind-tunnel none
+//! 0         boundaries <left> <right> <bottom> <top>
+//! 0         particles <count> <lifetime> <size> <streak> <speed|r,g,b>
+//! 0         emitter   <x> <y> <radius>
+//! 300       particles none
+//! 0         streamlines <spacing> <length> <steps>
+//! 0         seed      <x> <y>
+//! ```
+//!
+//! Positions are given in pixels of the output domain, with the origin in the bottom left corner.
+//! Airfoils are added on top of the other obstacles, their angle of attack is in degrees. A body is a
+//! box that floats in the fluid as a rigid body, with a density relative to the fluid. The boundary
+//! of an edge is one of `no-slip`, `free-slip`, `periodic`, `outflow` or `inflow:<vx>,<vy>`. Tracer
+//! particles live for about `lifetime` seconds, are `size` pixels large and draw a streak `streak` seconds
+//! long, they are colored by their speed or in a solid color. An emitter is added to the current
+//! particles, which are otherwise emitted anywhere. The `streamlines` command sets the grid spacing, the
+//! length in pixels and the number of segments of the lines drawn with `--mode streamlines`, and seeds
+//! replace the grid with lines from the given points.
+
+use std::ops::RangeInclusive;
+use fluid_simulation::{ Boundaries, BoundaryMode, Edge, ParticleColor, Particles };
+
+pub enum Command {
+    Splat {
+        radius: f32,
+        position: [f32; 2],
+        velocity: [f32; 2],
+        color: [f32; 3],
+    },
+    Obstacle {
+        radius: Option<f32>,
+        position: [f32; 2],
+        color: [f32; 3],
+        is_circle: bool,
+    },
+    Airfoil {
+        code: u16,
+        chord: f32,
+        position: [f32; 2],
+        angle_of_attack: f32,
+        color: [f32; 3],
+    },
+    Body {
+        size: [f32; 2],
+        position: [f32; 2],
+        density: f32,
+        color: [f32; 3],
+    },
+    WindTunnel {
+        inflow: Option<f32>,
+        streaklines: u32,
+    },
+    Boundaries {
+        boundaries: Boundaries,
+    },
+    Particles {
+        particles: Option<Particles>,
+    },
+    Emitter {
+        position: [f32; 2],
+        radius: f32,
+    },
+    Streamlines {
+        spacing: f32,
+        length: f32,
+        steps: u32,
+    },
+    Seed {
+        position: [f32; 2],
+    },
+}
+
+pub struct Script {
+    commands: Vec<(RangeInclusive<usize>, Command)>,
+}
+
+impl Script {
+    pub fn empty() -> Script {
+        Script { commands: Vec::new() }
+    }
+
+    pub fn parse(source: &str) -> Result<Script, String> {
+        let mut commands = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let command = Script::parse_line(line)
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            commands.push(command);
+        }
+
+        Ok(Script { commands })
+    }
+
+    /// All commands that should be applied on `step`, in the order of the script
+    pub fn commands(&self, step: usize) -> impl Iterator<Item = &Command> {
+        self.commands.iter()
+            .filter(move |(steps, _)| steps.contains(&step))
+            .map(|(_, command)| command)
+    }
+
+    fn parse_line(line: &str) -> Result<(RangeInclusive<usize>, Command), String> {
+        let mut tokens = line.split_whitespace();
+        let steps = tokens.next().ok_or("missing step")?;
+        let steps = match steps.split_once("..") {
+            Some((first, last)) => parse::<usize>(first)?..=parse::<usize>(last)?,
+            None => {
+                let step = parse::<usize>(steps)?;
+                step..=step
+            }
+        };
+
+        let name = tokens.next().ok_or("missing command")?;
+        let arguments = tokens.collect::<Vec<_>>();
+        let command = match (name, arguments.as_slice()) {
+            ("splat", [radius, x, y, vx, vy, r, g, b]) => Command::Splat {
+                radius: parse(radius)?,
+                position: [parse(x)?, parse(y)?],
+                velocity: [parse(vx)?, parse(vy)?],
+                color: [parse(r)?, parse(g)?, parse(b)?],
+            },
+            ("obstacle", ["none"]) => Command::Obstacle {
+                radius: None,
+                position: [0.0, 0.0],
+                color: [0.0, 0.0, 0.0],
+                is_circle: true,
+            },
+            ("obstacle", [shape @ ("circle" | "square"), radius, x, y, r, g, b]) => Command::Obstacle {
+                radius: Some(parse(radius)?),
+                position: [parse(x)?, parse(y)?],
+                color: [parse(r)?, parse(g)?, parse(b)?],
+                is_circle: *shape == "circle",
+            },
+            ("airfoil", [code, chord, x, y, angle, r, g, b]) => Command::Airfoil {
+                code: match parse(code)? {
+                    code @ 0..=9999 if code % 100 != 0 => code,
+                    _ => return Err(format!("invalid NACA code `{}`", code)),
+                },
+                chord: parse(chord)?,
+                position: [parse(x)?, parse(y)?],
+                angle_of_attack: parse::<f32>(angle)?.to_radians(),
+                color: [parse(r)?, parse(g)?, parse(b)?],
+            },
+            ("body", [width, height, x, y, density, r, g, b]) => Command::Body {
+                size: [parse(width)?, parse(height)?],
+                position: [parse(x)?, parse(y)?],
+                density: match parse(density)? {
+                    density if density > 0.0 => density,
+                    _ => return Err(format!("invalid density `{}`", density)),
+                },
+                color: [parse(r)?, parse(g)?, parse(b)?],
+            },
+            ("wind-tunnel", ["none"]) => Command::WindTunnel {
+                inflow: None,
+                streaklines: 0,
+            },
+            ("wind-tunnel", [inflow, streaklines]) => Command::WindTunnel {
+                inflow: Some(parse(inflow)?),
+                streaklines: parse(streaklines)?,
+            },
+            ("boundaries", edges @ [_, _, _, _]) => {
+                let mut boundaries = Boundaries::new();
+                for (edge, token) in [Edge::LEFT, Edge::RIGHT, Edge::BOTTOM, Edge::TOP].into_iter().zip(edges) {
+                    let mode = match *token {
+                        "no-slip" => BoundaryMode::NO_SLIP,
+                        "free-slip" => BoundaryMode::FREE_SLIP,
+                        "periodic" => BoundaryMode::PERIODIC,
+                        "outflow" => BoundaryMode::OUTFLOW,
+                        token => match token.strip_prefix("inflow:").and_then(|velocity| velocity.split_once(',')) {
+                            Some((vx, vy)) => {
+                                boundaries.set_inflow(edge, &[parse(vx)?, parse(vy)?]);
+                                BoundaryMode::INFLOW
+                            }
+                            None => return Err(format!("invalid boundary `{}`", token)),
+                        },
+                    };
+                    boundaries.set_mode(edge, mode);
+                }
+
+                Command::Boundaries { boundaries }
+            }
+            ("particles", ["none"]) => Command::Particles { particles: None },
+            ("particles", [count, lifetime, size, streak, color]) => {
+                let mut particles = Particles::new(parse(count)?);
+                particles.set_lifetime(parse(lifetime)?);
+                particles.set_size(parse(size)?);
+                particles.set_streak(parse(streak)?);
+                if *color == "speed" {
+                    particles.set_color_mode(ParticleColor::SPEED);
+                } else {
+                    let values = color.split(',').map(parse).collect::<Result<Vec<f32>, _>>()?;
+                    if values.len() != 3 {
+                        return Err(format!("invalid color `{}`", color));
+                    }
+                    particles.set_color_mode(ParticleColor::SOLID);
+                    particles.set_color(&values);
+                }
+
+                Command::Particles { particles: Some(particles) }
+            }
+            ("emitter", [x, y, radius]) => Command::Emitter {
+                position: [parse(x)?, parse(y)?],
+                radius: parse(radius)?,
+            },
+            ("streamlines", [spacing, length, steps]) => Command::Streamlines {
+                spacing: parse(spacing)?,
+                length: parse(length)?,
+                steps: parse(steps)?,
+            },
+            ("seed", [x, y]) => Command::Seed {
+                position: [parse(x)?, parse(y)?],
+            },
+            ("splat", _) => return Err("expected `splat <radius> <x> <y> <vx> <vy> <r> <g> <b>`".into()),
+            ("obstacle", _) => return Err("expected `obstacle <circle|square> <radius> <x> <y> <r> <g> <b>` or `obstacle none`".into()),
+            ("airfoil", _) => return Err("expected `airfoil <code> <chord> <x> <y> <angle of attack> <r> <g> <b>`".into()),
+            ("body", _) => return Err("expected `body <width> <height> <x> <y> <density> <r> <g> <b>`".into()),
+            ("wind-tunnel", _) => return Err("expected `wind-tunnel <inflow> <streaklines>` or `wind-tunnel none`".into()),
+            ("boundaries", _) => return Err("expected `boundaries <left> <right> <bottom> <top>`".into()),
+            ("particles", _) => return Err("expected `particles <count> <lifetime> <size> <streak> <speed|r,g,b>` or `particles none`".into()),
+            ("emitter", _) => return Err("expected `emitter <x> <y> <radius>`".into()),
+            ("streamlines", _) => return Err("expected `streamlines <spacing> <length> <steps>`".into()),
+            ("seed", _) => return Err("expected `seed <x> <y>`".into()),
+            (name, _) => return Err(format!("unknown command `{}`", name)),
+        };
+
+        Ok((steps, command))
+    }
+}
+
+fn parse<T: std::str::FromStr>(token: &str) -> Result<T, String> {
+    token.parse().map_err(|_| format!("invalid number `{}`", token))
+}
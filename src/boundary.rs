@@ -0,0 +1,162 @@
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+use crate::bytes::{ Reader, UnexpectedEnd };
+use crate::grid::Wrap;
+
+#[repr(u8)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// How the fluid behaves at an edge of the domain
+pub enum BoundaryMode {
+    /// A wall the fluid sticks to
+    NO_SLIP,
+    /// A wall the fluid slides along, the default
+    #[default]
+    FREE_SLIP,
+    /// The fluid leaves through the edge and enters again through the opposite one, which has to be
+    /// periodic as well, otherwise the edge is a free-slip wall
+    PERIODIC,
+    /// The fluid leaves freely, the pressure outside of the edge is zero
+    OUTFLOW,
+    /// The fluid enters with the inflow velocity of the edge
+    INFLOW,
+}
+
+impl TryFrom<u8> for BoundaryMode {
+    type Error = u8;
+
+    /// Get the boundary mode with the value `value`, or `value` itself if there is none
+    fn try_from(value: u8) -> Result<BoundaryMode, u8> {
+        match value {
+            0 => Ok(BoundaryMode::NO_SLIP),
+            1 => Ok(BoundaryMode::FREE_SLIP),
+            2 => Ok(BoundaryMode::PERIODIC),
+            3 => Ok(BoundaryMode::OUTFLOW),
+            4 => Ok(BoundaryMode::INFLOW),
+            _ => Err(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// An edge of the domain
+pub enum Edge {
+    LEFT,
+    RIGHT,
+    BOTTOM,
+    TOP,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// The [boundary modes](BoundaryMode) of the four edges of the domain
+///
+/// By default every edge is a free-slip wall.
+///
+/// # Format
+/// [Snapshots](crate::Snapshot) and [recordings](crate::Recording) encode boundaries edge by edge, in
+/// the order left, right, bottom, top, as the mode (`u8`) followed by the inflow velocity (`f32` x 2),
+/// little endian.
+pub struct Boundaries {
+    modes: [BoundaryMode; 4],
+    inflow: [[f32; 2]; 4],
+}
+
+impl Boundaries {
+    /// Modes of the left, right, bottom and top edge, with unpaired periodic edges turned into free-slip walls
+    pub(crate) fn effective_modes(&self) -> [BoundaryMode; 4] {
+        let periodic = self.periodic();
+        let mut modes = self.modes;
+        for (i, mode) in modes.iter_mut().enumerate() {
+            if *mode == BoundaryMode::PERIODIC && !periodic[i / 2] {
+                *mode = BoundaryMode::FREE_SLIP;
+            }
+        }
+
+        modes
+    }
+
+    /// Inflow velocities of the left, right, bottom and top edge in pixels per second
+    pub(crate) fn inflows(&self) -> [[f32; 2]; 4] {
+        self.inflow
+    }
+
+    /// Whether the x and the y axis wrap around
+    pub(crate) fn periodic(&self) -> [bool; 2] {
+        [
+            self.modes[0] == BoundaryMode::PERIODIC && self.modes[1] == BoundaryMode::PERIODIC,
+            self.modes[2] == BoundaryMode::PERIODIC && self.modes[3] == BoundaryMode::PERIODIC,
+        ]
+    }
+
+    /// Wrapping of the textures of the simulation along the x and the y axis
+    pub(crate) fn wrap(&self) -> [Wrap; 2] {
+        self.periodic().map(|periodic| if periodic { Wrap::Repeat } else { Wrap::Clamp })
+    }
+
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        for (mode, inflow) in self.modes.iter().zip(self.inflow) {
+            bytes.push(*mode as u8);
+            bytes.extend(inflow[0].to_le_bytes());
+            bytes.extend(inflow[1].to_le_bytes());
+        }
+    }
+
+    /// Read boundaries, turning a mode that is not a valid [BoundaryMode] into an error with `invalid`
+    pub(crate) fn read<E: From<UnexpectedEnd>>(
+        reader: &mut Reader,
+        invalid: impl Fn(u8) -> E,
+    ) -> Result<Boundaries, E> {
+        let mut boundaries = Boundaries::default();
+        for i in 0..4 {
+            boundaries.modes[i] = BoundaryMode::try_from(reader.u8()?).map_err(&invalid)?;
+            boundaries.inflow[i] = [reader.f32()?, reader.f32()?];
+        }
+
+        Ok(boundaries)
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl Boundaries {
+    /// Create boundaries with a free-slip wall on every edge
+    #[cfg_attr(feature = "webgl", wasm_bindgen(constructor))]
+    pub fn new() -> Boundaries {
+        Boundaries::default()
+    }
+
+    /// Create boundaries that wrap around on every edge
+    pub fn periodic_all() -> Boundaries {
+        Boundaries {
+            modes: [BoundaryMode::PERIODIC; 4],
+            ..Boundaries::default()
+        }
+    }
+
+    pub fn mode(&self, edge: Edge) -> BoundaryMode {
+        self.modes[edge as usize]
+    }
+
+    pub fn set_mode(&mut self, edge: Edge, mode: BoundaryMode) {
+        self.modes[edge as usize] = mode;
+    }
+
+    /// Velocity of the fluid entering through `edge` in pixels per second, only used by [inflow](BoundaryMode::INFLOW) edges
+    pub fn inflow(&self, edge: Edge) -> Vec<f32> {
+        self.inflow[edge as usize].to_vec()
+    }
+
+    /// Set the velocity of the fluid entering through `edge`
+    ///
+    /// # Arguments
+    /// * `edge` - The [edge](Edge) to set the velocity of
+    /// * `velocity` - A float array that should have two values, an x and a y velocity in pixels per second
+    ///
+    /// # Panics
+    /// If `velocity` contains fewer than two values.
+    pub fn set_inflow(&mut self, edge: Edge, velocity: &[f32]) {
+        self.inflow[edge as usize] = [velocity[0], velocity[1]];
+    }
+}
@@ -0,0 +1,54 @@
+//! Reading of the little-endian binary formats used by [snapshots](crate::Snapshot) and
+//! [recordings](crate::Recording)
+
+/// Error returned by a [reader](Reader) that runs out of bytes
+pub(crate) struct UnexpectedEnd;
+
+/// Cursor over little-endian encoded bytes
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader {
+            bytes,
+        }
+    }
+
+    /// The bytes that have not been read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Skip `len` bytes, which must not be more than [remaining](Reader::remaining)
+    pub fn skip(&mut self, len: usize) {
+        self.bytes = &self.bytes[len..];
+    }
+
+    pub fn take<const N: usize>(&mut self) -> Result<[u8; N], UnexpectedEnd> {
+        if self.bytes.len() < N {
+            return Err(UnexpectedEnd);
+        }
+
+        let (value, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(value.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, UnexpectedEnd> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, UnexpectedEnd> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, UnexpectedEnd> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, UnexpectedEnd> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+}
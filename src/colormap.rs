@@ -0,0 +1,48 @@
+/// Color maps of the diagnostic [modes](crate::Mode), which turn a scalar field into colors
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Colormap {
+    /// Blue through a light gray to red, for values with a sign that are centered on zero
+    DIVERGING,
+    /// Viridis, from dark blue over green to yellow, for magnitudes
+    SEQUENTIAL,
+    /// Black to white
+    GRAY,
+}
+
+impl Colormap {
+    /// The color of `t` between 0 and 1, clamped
+    pub fn color(self, t: f32) -> [f32; 3] {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Colormap::DIVERGING => {
+                let (blue, gray, red) = ([0.230, 0.299, 0.754], [0.865; 3], [0.706, 0.016, 0.150]);
+                let (from, to, t) = match t < 0.5 {
+                    true => (blue, gray, 2.0 * t),
+                    false => (gray, red, 2.0 * t - 1.0),
+                };
+                [0, 1, 2].map(|i: usize| from[i] + (to[i] - from[i]) * t)
+            }
+            Colormap::SEQUENTIAL => {
+                // A polynomial fit of viridis
+                const COEFFICIENTS: [[f32; 3]; 7] = [
+                    [0.277_727_3, 0.005_407_345, 0.334_099_8],
+                    [0.105_093, 1.404_614, 1.384_59],
+                    [-0.330_861_8, 0.214_847_6, 0.095_095_16],
+                    [-4.634_23, -5.799_101, -19.332_44],
+                    [6.228_27, 14.179_93, 56.690_55],
+                    [4.776_385, -13.745_15, -65.353_03],
+                    [-5.435_456, 4.645_853, 26.312_44],
+                ];
+                [0, 1, 2].map(|i| {
+                    COEFFICIENTS
+                        .iter()
+                        .rev()
+                        .fold(0.0, |value, coefficients| value * t + coefficients[i])
+                        .clamp(0.0, 1.0)
+                })
+            }
+            Colormap::GRAY => [t; 3],
+        }
+    }
+}
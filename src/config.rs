@@ -0,0 +1,398 @@
+use std::fmt;
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{ Deserialize, Serialize };
+use crate::{ Advection, Mode, Solver };
+
+#[derive(Clone, Debug, PartialEq)]
+/// Error returned when a value of a [configuration](SimulationConfig) is out of range
+pub struct ConfigError {
+    name: &'static str,
+    value: f32,
+    min: f32,
+    max: f32,
+    /// Whether `min` and `max` are out of range themselves
+    exclusive: bool,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exclusive {
+            write!(f, "`{}` must be in ({}, {}), got {}", self.name, self.min, self.max, self.value)
+        } else if self.max == f32::INFINITY {
+            write!(f, "`{}` must be >= {}, got {}", self.name, self.min, self.value)
+        } else {
+            write!(f, "`{}` must be in [{}, {}], got {}", self.name, self.min, self.max, self.value)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(feature = "webgl")]
+impl From<ConfigError> for JsValue {
+    fn from(error: ConfigError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawConfig"))]
+/// Configuration of the [simulation](crate::Simulation)
+///
+/// Every setter validates its value, so a configuration is always valid. The defaults match the ones
+/// used by the web app.
+pub struct SimulationConfig {
+    pause: bool,
+    mode: Mode,
+    solver: Solver,
+    iterations: usize,
+    cycles: usize,
+    omega: f32,
+    tolerance: f32,
+    max_iterations: usize,
+    advection: Advection,
+    viscosity: f32,
+    dissipation: f32,
+    curl: f32,
+    pressure: f32,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> SimulationConfig {
+        SimulationConfig {
+            pause: false,
+            mode: Mode::DYE,
+            solver: Solver::JACOBI,
+            iterations: 20,
+            cycles: 2,
+            omega: 1.7,
+            tolerance: 0.0,
+            max_iterations: 200,
+            advection: Advection::SEMI_LAGRANGIAN,
+            viscosity: 0.5,
+            dissipation: 2.0,
+            curl: 0.25,
+            pressure: 0.8,
+        }
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl SimulationConfig {
+    /// Create a configuration with the default values
+    #[cfg_attr(feature = "webgl", wasm_bindgen(constructor))]
+    pub fn new() -> SimulationConfig {
+        SimulationConfig::default()
+    }
+
+    /// Should the simulation be paused?
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn pause(&self) -> bool {
+        self.pause
+    }
+
+    pub fn set_pause(&mut self, pause: bool) {
+        self.pause = pause;
+    }
+
+    /// Rendering [mode](Mode)
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// [Method](Solver) of the pressure solve
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn solver(&self) -> Solver {
+        self.solver
+    }
+
+    pub fn set_solver(&mut self, solver: Solver) {
+        self.solver = solver;
+    }
+
+    /// Number of pressure solver iterations (at least `MIN_PRESSURE_ITERATIONS` are always run)
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations;
+    }
+
+    /// Number of V-cycles of the [multigrid](Solver::MULTIGRID) pressure solver (at least one is always run)
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn set_cycles(&mut self, cycles: usize) {
+        self.cycles = cycles;
+    }
+
+    /// Over-relaxation of the [SOR](Solver::SOR) pressure solver (0, 2)
+    ///
+    /// One is plain red-black Gauss-Seidel, larger values converge faster up to an optimum just below two
+    /// that grows with the resolution. The solve only converges strictly between zero and two, so the ends
+    /// are rejected.
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn omega(&self) -> f32 {
+        self.omega
+    }
+
+    pub fn set_omega(&mut self, omega: f32) -> Result<(), ConfigError> {
+        self.omega = validate_exclusive("omega", omega, 0.0, 2.0)?;
+        Ok(())
+    }
+
+    /// Residual at which the pressure solve stops early (>= 0), or zero to always run the
+    /// [iterations](SimulationConfig::iterations) or [cycles](SimulationConfig::cycles)
+    ///
+    /// The residual is the root mean square of the divergence that is left over by the pressure equation,
+    /// in the same units as the divergence of the [legend](crate::Simulation::legend) (per second). Measuring
+    /// it reads back from the backend, so it is only done every ten Jacobi or SOR passes, which rounds the
+    /// passes of a solve up to a multiple of ten and hides part of what [SOR](Solver::SOR) saves. The
+    /// [conjugate gradient](Solver::CONJUGATE_GRADIENT) solver always runs to a tolerance, with zero as far
+    /// as the pressure texture can hold.
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: f32) -> Result<(), ConfigError> {
+        self.tolerance = validate("tolerance", tolerance, 0.0, f32::INFINITY)?;
+        Ok(())
+    }
+
+    /// Number of iterations, or V-cycles of the [multigrid](Solver::MULTIGRID) solver, after which a solve
+    /// to a [tolerance](SimulationConfig::tolerance), or any solve of the
+    /// [conjugate gradient](Solver::CONJUGATE_GRADIENT) solver, gives up
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+
+    /// [Scheme](Advection) the velocity and the dye are advected with
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn advection(&self) -> Advection {
+        self.advection
+    }
+
+    pub fn set_advection(&mut self, advection: Advection) {
+        self.advection = advection;
+    }
+
+    /// Energy loss of the fluid due to friction (>= 0)
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn viscosity(&self) -> f32 {
+        self.viscosity
+    }
+
+    pub fn set_viscosity(&mut self, viscosity: f32) -> Result<(), ConfigError> {
+        self.viscosity = validate("viscosity", viscosity, 0.0, f32::INFINITY)?;
+        Ok(())
+    }
+
+    /// Colored dye fading amount (>= 0)
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn dissipation(&self) -> f32 {
+        self.dissipation
+    }
+
+    pub fn set_dissipation(&mut self, dissipation: f32) -> Result<(), ConfigError> {
+        self.dissipation = validate("dissipation", dissipation, 0.0, f32::INFINITY)?;
+        Ok(())
+    }
+
+    /// Curl amount [0, 1]
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn curl(&self) -> f32 {
+        self.curl
+    }
+
+    pub fn set_curl(&mut self, curl: f32) -> Result<(), ConfigError> {
+        self.curl = validate("curl", curl, 0.0, 1.0)?;
+        Ok(())
+    }
+
+    /// Pressure coefficient for converging pressure calculation [0, 1]
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn pressure(&self) -> f32 {
+        self.pressure
+    }
+
+    pub fn set_pressure(&mut self, pressure: f32) -> Result<(), ConfigError> {
+        self.pressure = validate("pressure", pressure, 0.0, 1.0)?;
+        Ok(())
+    }
+}
+
+fn validate(name: &'static str, value: f32, min: f32, max: f32) -> Result<f32, ConfigError> {
+    if value >= min && value <= max {
+        Ok(value)
+    } else {
+        Err(ConfigError { name, value, min, max, exclusive: false })
+    }
+}
+
+fn validate_exclusive(name: &'static str, value: f32, min: f32, max: f32) -> Result<f32, ConfigError> {
+    if value > min && value < max {
+        Ok(value)
+    } else {
+        Err(ConfigError { name, value, min, max, exclusive: true })
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(default)]
+/// Unvalidated mirror of [SimulationConfig] used for deserialization
+struct RawConfig {
+    pause: bool,
+    mode: Mode,
+    solver: Solver,
+    iterations: usize,
+    cycles: usize,
+    omega: f32,
+    tolerance: f32,
+    max_iterations: usize,
+    advection: Advection,
+    viscosity: f32,
+    dissipation: f32,
+    curl: f32,
+    pressure: f32,
+}
+
+#[cfg(feature = "serde")]
+impl Default for RawConfig {
+    fn default() -> RawConfig {
+        let config = SimulationConfig::default();
+        RawConfig {
+            pause: config.pause,
+            mode: config.mode,
+            solver: config.solver,
+            iterations: config.iterations,
+            cycles: config.cycles,
+            omega: config.omega,
+            tolerance: config.tolerance,
+            max_iterations: config.max_iterations,
+            advection: config.advection,
+            viscosity: config.viscosity,
+            dissipation: config.dissipation,
+            curl: config.curl,
+            pressure: config.pressure,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawConfig> for SimulationConfig {
+    type Error = ConfigError;
+
+    fn try_from(raw: RawConfig) -> Result<SimulationConfig, ConfigError> {
+        let mut config = SimulationConfig {
+            pause: raw.pause,
+            mode: raw.mode,
+            solver: raw.solver,
+            iterations: raw.iterations,
+            cycles: raw.cycles,
+            max_iterations: raw.max_iterations,
+            advection: raw.advection,
+            ..SimulationConfig::default()
+        };
+        config.set_omega(raw.omega)?;
+        config.set_tolerance(raw.tolerance)?;
+        config.set_viscosity(raw.viscosity)?;
+        config.set_dissipation(raw.dissipation)?;
+        config.set_curl(raw.curl)?;
+        config.set_pressure(raw.pressure)?;
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn omega_excludes_its_ends() {
+        let mut config = SimulationConfig::new();
+        for omega in [0.0, 2.0, -0.5, 2.5, f32::NAN] {
+            assert!(config.set_omega(omega).is_err(), "omega {} was accepted", omega);
+        }
+        assert_eq!(config.omega(), 1.7);
+
+        for omega in [0.01, 1.0, 1.99] {
+            assert_eq!(config.set_omega(omega), Ok(()));
+            assert_eq!(config.omega(), omega);
+        }
+        assert_eq!(
+            config.set_omega(2.0).unwrap_err().to_string(),
+            "`omega` must be in (0, 2), got 2",
+        );
+    }
+
+    #[test]
+    fn setters_reject_values_out_of_range() {
+        type Setter = fn(&mut SimulationConfig, f32) -> Result<(), ConfigError>;
+        type Getter = fn(&SimulationConfig) -> f32;
+        type Field = (&'static str, Setter, Getter, &'static [f32], &'static [f32]);
+        let fields: [Field; 5] = [
+            ("tolerance", SimulationConfig::set_tolerance, SimulationConfig::tolerance, &[0.0, 0.5, 1e6], &[-0.1]),
+            ("viscosity", SimulationConfig::set_viscosity, SimulationConfig::viscosity, &[0.0, 2.0], &[-1.0]),
+            ("dissipation", SimulationConfig::set_dissipation, SimulationConfig::dissipation, &[0.0, 3.0], &[-0.5]),
+            ("curl", SimulationConfig::set_curl, SimulationConfig::curl, &[0.0, 0.5, 1.0], &[-0.1, 1.1]),
+            ("pressure", SimulationConfig::set_pressure, SimulationConfig::pressure, &[0.0, 1.0], &[-0.1, 1.5]),
+        ];
+
+        for (name, set, get, valid, invalid) in fields {
+            let mut config = SimulationConfig::new();
+            for &value in valid {
+                assert_eq!(set(&mut config, value), Ok(()), "{} {} was rejected", name, value);
+                assert_eq!(get(&config), value);
+            }
+
+            // A rejected value leaves the last valid one in place
+            let last = get(&config);
+            for &value in invalid.iter().chain(&[f32::NAN]) {
+                let error = set(&mut config, value).unwrap_err();
+                assert!(error.to_string().starts_with(&format!("`{}` must be", name)), "{}", error);
+                assert_eq!(get(&config), last);
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialization_rejects_values_out_of_range() {
+        use serde::de::value::{ Error, MapDeserializer };
+
+        let deserialize = |fields: Vec<(&'static str, f32)>| {
+            SimulationConfig::deserialize(MapDeserializer::<_, Error>::new(fields.into_iter()))
+        };
+
+        // Missing fields keep their defaults
+        let config = deserialize(vec![("curl", 0.5), ("dissipation", 2.0)]).unwrap();
+        assert_eq!((config.curl(), config.dissipation()), (0.5, 2.0));
+        assert_eq!(config.pressure(), SimulationConfig::new().pressure());
+
+        for (field, value) in [("curl", 1.5), ("curl", -0.5), ("dissipation", -1.0), ("omega", 2.0)] {
+            let error = deserialize(vec![(field, value)]).unwrap_err();
+            assert!(error.to_string().contains(&format!("`{}` must be", field)), "{}", error);
+        }
+    }
+}
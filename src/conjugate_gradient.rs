@@ -0,0 +1,309 @@
+//! Preconditioned conjugate gradient solve of the pressure equation on the CPU, see
+//! [Solver::CONJUGATE_GRADIENT](crate::Solver::CONJUGATE_GRADIENT)
+//!
+//! The pressure, the divergence and the obstacles are read back from the backend and the equation of the
+//! [pressure solver](crate::backend::Pass::PressureSolver) is solved in double precision, with the same
+//! stencil at the edges and along the obstacles. The matrix of that stencil is symmetric, so conjugate gradients converge
+//! to the exact solution, and a modified incomplete Cholesky factorisation of it cuts the iterations down
+//! to a few dozen even on large grids. The pressure is written back once the solve is done.
+
+use crate::backend::{ BackendTexture, PingPong, SimulationBackend };
+use crate::boundary::BoundaryMode;
+
+/// Share of the fill-in dropped by the incomplete factorisation that is added back onto its diagonal
+const TUNING: f64 = 0.97;
+/// Pivots of the factorisation below this share of their diagonal fall back to the diagonal
+const SAFETY: f64 = 0.25;
+/// Reduction of the residual beyond which the `f32` textures can not hold a better pressure
+const RELATIVE_TOLERANCE: f64 = 1e-6;
+
+/// The pressure equation on a grid, restricted to its fluid texels
+struct Stencil {
+    width: usize,
+    fluid: Vec<bool>,
+    /// Neighbours of every texel in the order left, right, bottom, top, across periodic edges
+    neighbours: Vec<[Option<usize>; 4]>,
+    /// Fluid neighbours of every fluid texel, plus two for every neighbour beyond an outflow
+    diagonal: Vec<f64>,
+    /// Whether the fluid texel is connected to a neighbour beyond an outflow, which pins its pressure
+    outflow: Vec<bool>,
+}
+
+impl Stencil {
+    fn new(
+        boundary: [BoundaryMode; 4],
+        size: [usize; 2],
+        obstacles: &[f32],
+        obstacles_size: [usize; 2],
+    ) -> Stencil {
+        let [width, height] = size;
+
+        // The obstacles are sampled at the center of every texel, with nearest filtering
+        let fluid: Vec<bool> = (0..width * height)
+            .map(|i| {
+                let x = (2 * (i % width) + 1) * obstacles_size[0] / (2 * width);
+                let y = (2 * (i / width) + 1) * obstacles_size[1] / (2 * height);
+                obstacles[(x.min(obstacles_size[0] - 1) + y.min(obstacles_size[1] - 1) * obstacles_size[0]) * 4] >= 0.5
+            })
+            .collect();
+
+        let periodic = boundary.map(|mode| mode == BoundaryMode::PERIODIC);
+        let neighbours: Vec<[Option<usize>; 4]> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                [
+                    if x > 0 { Some(i - 1) } else { periodic[0].then_some(i + width - 1) },
+                    if x + 1 < width { Some(i + 1) } else { periodic[1].then_some(i + 1 - width) },
+                    if y > 0 { Some(i - width) } else { periodic[2].then_some(i + (height - 1) * width) },
+                    if y + 1 < height { Some(i + width) } else { periodic[3].then_some(x) },
+                ]
+            })
+            .collect();
+
+        let mut diagonal = vec![0.0; width * height];
+        let mut outflow = vec![false; width * height];
+        for i in (0..width * height).filter(|&i| fluid[i]) {
+            for (neighbour, mode) in neighbours[i].iter().zip(boundary) {
+                match neighbour {
+                    Some(n) if fluid[*n] => diagonal[i] += 1.0,
+                    // Solid neighbours and walls mirror the pressure, so they drop out of the equation
+                    Some(_) => {}
+                    None if mode == BoundaryMode::OUTFLOW => {
+                        diagonal[i] += 2.0;
+                        outflow[i] = true;
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        Stencil { width, fluid, neighbours, diagonal, outflow }
+    }
+
+    /// Fluid neighbours of the fluid texel `i`
+    fn fluid_neighbours(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.neighbours[i].into_iter().flatten().filter(|&n| self.fluid[n])
+    }
+
+    /// `result = A * p`, zero at the solid texels
+    fn multiply(&self, p: &[f64], result: &mut [f64]) {
+        for i in 0..p.len() {
+            result[i] = match self.fluid[i] {
+                true => self.diagonal[i] * p[i] - self.fluid_neighbours(i).map(|n| p[n]).sum::<f64>(),
+                false => 0.0,
+            };
+        }
+    }
+
+    /// Whether the texels `i` and `i + 1` are both fluid and next to each other in the same row
+    fn right(&self, i: usize) -> bool {
+        self.fluid[i] && !(i + 1).is_multiple_of(self.width) && self.fluid[i + 1]
+    }
+
+    /// Whether the texels `i` and `i + width` are both fluid
+    fn top(&self, i: usize) -> bool {
+        self.fluid[i] && i + self.width < self.fluid.len() && self.fluid[i + self.width]
+    }
+
+    /// Inverse pivots of the modified incomplete Cholesky factorisation, zero at the solid texels
+    ///
+    /// Only the neighbours that come earlier in the row by row order are factorised, the ones across a
+    /// periodic edge are left to the conjugate gradients.
+    fn preconditioner(&self) -> Vec<f64> {
+        let width = self.width;
+        let mut precon = vec![0.0; self.fluid.len()];
+        for i in (0..self.fluid.len()).filter(|&i| self.fluid[i] && self.diagonal[i] > 0.0) {
+            let mut e = self.diagonal[i];
+            if !i.is_multiple_of(width) && self.right(i - 1) {
+                let p = precon[i - 1];
+                e -= p * p * if self.top(i - 1) { 1.0 + TUNING } else { 1.0 };
+            }
+            if i >= width && self.top(i - width) {
+                let p = precon[i - width];
+                e -= p * p * if self.right(i - width) { 1.0 + TUNING } else { 1.0 };
+            }
+            if e < SAFETY * self.diagonal[i] {
+                e = self.diagonal[i];
+            }
+            precon[i] = 1.0 / e.sqrt();
+        }
+
+        precon
+    }
+
+    /// `z = M^-1 * r` with the factorisation `precon`, using `q` as scratch space
+    fn precondition(&self, precon: &[f64], r: &[f64], q: &mut [f64], z: &mut [f64]) {
+        let width = self.width;
+        for i in 0..r.len() {
+            let mut t = r[i];
+            if !i.is_multiple_of(width) && self.right(i - 1) {
+                t += precon[i - 1] * q[i - 1];
+            }
+            if i >= width && self.top(i - width) {
+                t += precon[i - width] * q[i - width];
+            }
+            q[i] = t * precon[i];
+        }
+        for i in (0..r.len()).rev() {
+            let mut t = q[i];
+            if self.right(i) {
+                t += precon[i] * z[i + 1];
+            }
+            if self.top(i) {
+                t += precon[i] * z[i + width];
+            }
+            z[i] = t * precon[i];
+        }
+    }
+
+    /// Remove the mean of `rhs` from every group of connected fluid texels that touches no outflow
+    ///
+    /// The pressure of such a group is only known up to a constant, and the equation can only be solved
+    /// if its right-hand side sums up to zero.
+    fn remove_mean(&self, rhs: &mut [f64]) {
+        let mut visited = vec![false; self.fluid.len()];
+        let mut group = Vec::new();
+        for start in 0..self.fluid.len() {
+            if !self.fluid[start] || visited[start] {
+                continue;
+            }
+
+            group.clear();
+            group.push(start);
+            visited[start] = true;
+            let mut next = 0;
+            while next < group.len() {
+                let i = group[next];
+                next += 1;
+                for n in self.fluid_neighbours(i) {
+                    if !visited[n] {
+                        visited[n] = true;
+                        group.push(n);
+                    }
+                }
+            }
+
+            if group.iter().all(|&i| !self.outflow[i]) {
+                let mean = group.iter().map(|&i| rhs[i]).sum::<f64>() / group.len() as f64;
+                for &i in &group {
+                    rhs[i] -= mean;
+                }
+            }
+        }
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Solve the pressure equation for `x` with preconditioned conjugate gradients
+///
+/// The solve stops once the root mean square of the residual, in the units of `b`, is at most `tolerance`,
+/// once the `f32` pressure can not get any more accurate, or after `max_iterations`. Solid texels are
+/// left out of the equation and take the mean pressure of their fluid neighbours, which is what the
+/// gradient along the obstacles reads.
+///
+/// # Arguments
+/// * `boundary` - Boundary mode of every edge, in the order left, right, bottom, top
+/// * `alpha` - The `alpha` of the [pressure solver](crate::backend::Pass::PressureSolver)
+/// * `x` - The pressure, which is also the initial guess
+/// * `b` - The divergence of the velocity, of the same size as `x`
+/// * `obstacles` - The obstacles, of any size
+///
+/// # Returns
+/// The iterations that were run and the root mean square of the residual that is left, over the whole grid
+/// and in the units of `b` like the one of [ResidualNorm](crate::residual::ResidualNorm), but without the
+/// mean divergence of fluid that no outflow drains, which no pressure can remove.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn solve<B: SimulationBackend>(
+    backend: &mut B,
+    boundary: [BoundaryMode; 4],
+    alpha: f32,
+    tolerance: f32,
+    max_iterations: usize,
+    x: &mut PingPong<B::Texture>,
+    b: &B::Texture,
+    obstacles: &B::Texture,
+) -> Result<(usize, f32), B::Error> {
+    let size = [x.read().width() as usize, x.read().height() as usize];
+    let mut data = backend.read_texture(x.read())?;
+    let divergence = backend.read_texture(b)?;
+    let stencil = Stencil::new(
+        boundary,
+        size,
+        &backend.read_texture(obstacles)?,
+        [obstacles.width() as usize, obstacles.height() as usize],
+    );
+
+    // SETUP
+    let texels = size[0] * size[1];
+    let alpha = alpha as f64;
+    let mut pressure: Vec<f64> = (0..texels).map(|i| if stencil.fluid[i] { data[i * 4] as f64 } else { 0.0 }).collect();
+    let mut rhs: Vec<f64> = (0..texels).map(|i| if stencil.fluid[i] { alpha * divergence[i * 4] as f64 } else { 0.0 }).collect();
+    stencil.remove_mean(&mut rhs);
+
+    let mut r = vec![0.0; texels];
+    stencil.multiply(&pressure, &mut r);
+    for (r, rhs) in r.iter_mut().zip(&rhs) {
+        *r = rhs - *r;
+    }
+
+    // The residual of the pressure equation is divided by `alpha` to be in the units of `b`
+    let rms = |r: &[f64]| (dot(r, r) / texels as f64).sqrt() / alpha.abs();
+    let target = (tolerance as f64).max(RELATIVE_TOLERANCE * rms(&rhs).max(rms(&r)));
+
+    // ITERATIONS
+    let precon = stencil.preconditioner();
+    let (mut q, mut z, mut s) = (vec![0.0; texels], vec![0.0; texels], vec![0.0; texels]);
+    stencil.precondition(&precon, &r, &mut q, &mut z);
+    let mut p = z.clone();
+    let mut rho = dot(&z, &r);
+
+    let mut iterations = 0;
+    let mut residual = rms(&r);
+    while residual > target && iterations < max_iterations && rho != 0.0 {
+        stencil.multiply(&p, &mut s);
+        let step = rho / dot(&p, &s);
+        for i in 0..texels {
+            pressure[i] += step * p[i];
+            r[i] -= step * s[i];
+        }
+        iterations += 1;
+
+        residual = rms(&r);
+        if residual <= target {
+            break;
+        }
+
+        stencil.precondition(&precon, &r, &mut q, &mut z);
+        let rho_next = dot(&z, &r);
+        let beta = rho_next / rho;
+        rho = rho_next;
+        for i in 0..texels {
+            p[i] = z[i] + beta * p[i];
+        }
+    }
+
+    // PRESSURE
+    for i in 0..texels {
+        if stencil.fluid[i] {
+            data[i * 4] = pressure[i] as f32;
+        } else {
+            let (sum, count) = stencil.neighbours[i]
+                .into_iter()
+                .flatten()
+                .filter(|&n| stencil.fluid[n])
+                .fold((0.0, 0), |(sum, count), n| (sum + pressure[n], count + 1));
+            if count > 0 {
+                data[i * 4] = (sum / count as f64) as f32;
+            }
+        }
+    }
+
+    let (_, write) = x.split();
+    backend.write_texture(write, &data)?;
+    x.swap();
+
+    Ok((iterations, residual as f32))
+}
@@ -0,0 +1,1038 @@
+//! A CPU implementation of the [simulation backend](SimulationBackend)
+//!
+//! Every pass mirrors the fragment shader of the same name in `src/shaders`, texel by texel, so that
+//! the results can be used to check the GPU pipeline without a WebGL context.
+
+use std::convert::Infallible;
+use crate::backend::{ BackendTexture, Pass, SimulationBackend };
+use crate::boundary::BoundaryMode;
+use crate::grid::{ Filter, Grid, Wrap };
+use crate::obstacle::ShapeKind;
+use crate::particles::ParticleColor;
+use crate::simulation::Simulation;
+
+/// The [simulation](Simulation) running on the [CPU backend](CpuBackend)
+pub type CpuSolver = Simulation<CpuBackend>;
+
+/// Backend running every pass of the simulation on the CPU
+pub struct CpuBackend {
+    frame: Grid,
+}
+
+impl CpuBackend {
+    pub fn new() -> CpuBackend {
+        CpuBackend {
+            frame: Grid::new(0, 0, Filter::Nearest),
+        }
+    }
+
+    /// The last texture drawn with [present](SimulationBackend::present)
+    pub fn frame(&self) -> &Grid {
+        &self.frame
+    }
+}
+
+impl Default for CpuBackend {
+    fn default() -> CpuBackend {
+        CpuBackend::new()
+    }
+}
+
+impl SimulationBackend for CpuBackend {
+    type Error = Infallible;
+    type Texture = Grid;
+
+    fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        filter: Filter,
+    ) -> Result<Grid, Infallible> {
+        Ok(Grid::new(width, height, filter))
+    }
+
+    fn create_data_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<Grid, Infallible> {
+        Ok(Grid::new(width, height, Filter::Nearest))
+    }
+
+    fn set_wrap(&mut self, texture: &mut Grid, wrap: [Wrap; 2]) {
+        texture.set_wrap(wrap);
+    }
+
+    fn delete_texture(&mut self, _texture: Grid) {}
+
+    fn read_texture(&mut self, texture: &Grid) -> Result<Vec<f32>, Infallible> {
+        Ok(texture.data().iter().flatten().copied().collect())
+    }
+
+    /// # Panics
+    /// If `data` does not hold exactly four values per texel of `texture`.
+    fn write_texture(
+        &mut self,
+        texture: &mut Grid,
+        data: &[f32],
+    ) -> Result<(), Infallible> {
+        assert_eq!(data.len(), texture.data().len() * 4, "data does not match the size of the texture");
+        for (texel, value) in texture.data_mut().iter_mut().zip(data.chunks_exact(4)) {
+            texel.copy_from_slice(value);
+        }
+
+        Ok(())
+    }
+
+    fn dispatch(
+        &mut self,
+        pass: Pass<'_, Grid>,
+        target: &mut Grid,
+    ) -> Result<(), Infallible> {
+        match pass {
+            Pass::Copy { factor, offset, texture } => {
+                target.render(|_, uv| {
+                    texture.sample(uv).map(|v| v * factor + offset)
+                });
+            }
+            Pass::Advection { dissipation, delta_time, resolution, velocity, quantity, obstacles } => {
+                target.render(|_, uv| {
+                    let velocity = velocity.sample(uv);
+                    let velocity = [velocity[0] / resolution[0], velocity[1] / resolution[1]];
+                    let position = sub(uv, scale(velocity, delta_time));
+                    let obstacle = obstacles.sample(uv)[0];
+
+                    quantity.sample(position).map(|v| v * dissipation * obstacle)
+                });
+            }
+            Pass::AdvectionError { delta_time, resolution, velocity, quantity, advected } => {
+                target.render(|_, uv| {
+                    let velocity = velocity.sample(uv);
+                    let velocity = [velocity[0] / resolution[0], velocity[1] / resolution[1]];
+                    let position = sub(uv, scale(velocity, -delta_time));
+
+                    let (quantity, advected) = (quantity.sample(uv), advected.sample(position));
+                    std::array::from_fn(|i| 0.5 * (quantity[i] - advected[i]))
+                });
+            }
+            Pass::CorrectedAdvection {
+                bfecc,
+                dissipation,
+                delta_time,
+                resolution,
+                quantity_resolution,
+                velocity,
+                quantity,
+                error,
+                obstacles,
+            } => {
+                target.render(|_, uv| {
+                    let velocity = velocity.sample(uv);
+                    let velocity = [velocity[0] / resolution[0], velocity[1] / resolution[1]];
+                    let position = sub(uv, scale(velocity, delta_time));
+
+                    // BFECC advects the quantity with its error compensated, MacCormack corrects the advected quantity
+                    let error = error.sample(if bfecc { position } else { uv });
+                    let value = quantity.sample(position);
+
+                    // The correction may not leave the range of the texels the quantity is interpolated from
+                    let texel_size = [1.0 / quantity_resolution[0], 1.0 / quantity_resolution[1]];
+                    let corner: [f32; 2] = std::array::from_fn(|i| {
+                        ((position[i] * quantity_resolution[i] - 0.5).floor() + 0.5) * texel_size[i]
+                    });
+                    let texels = [
+                        quantity.sample(corner),
+                        quantity.sample([corner[0] + texel_size[0], corner[1]]),
+                        quantity.sample([corner[0], corner[1] + texel_size[1]]),
+                        quantity.sample([corner[0] + texel_size[0], corner[1] + texel_size[1]]),
+                    ];
+                    let obstacle = obstacles.sample(uv)[0];
+
+                    std::array::from_fn(|i| {
+                        let min = texels.iter().map(|texel| texel[i]).fold(f32::INFINITY, f32::min);
+                        let max = texels.iter().map(|texel| texel[i]).fold(f32::NEG_INFINITY, f32::max);
+                        (value[i] + error[i]).clamp(min, max) * dissipation * obstacle
+                    })
+                });
+            }
+            Pass::Divergence { boundary, inflow, r_half_texel_size, resolution, velocity, obstacles, obstacle_velocity } => {
+                // Velocity just outside of an edge, walls stand still
+                let ghost = |mode: BoundaryMode, inflow: f32, x_c: f32| match mode {
+                    BoundaryMode::OUTFLOW => x_c,
+                    BoundaryMode::INFLOW => 2.0 * inflow - x_c,
+                    _ => -x_c,
+                };
+                let [left, right, bottom, top] = boundary;
+
+                target.render(|frag_coord, uv| {
+                    let (l, r, b, t) = neighbours(uv, &resolution);
+
+                    let mut x_l = velocity.sample(l)[0];
+                    let mut x_r = velocity.sample(r)[0];
+                    let mut x_b = velocity.sample(b)[1];
+                    let mut x_t = velocity.sample(t)[1];
+                    let x_c = velocity.sample(uv);
+
+                    // Solid neighbours move with the velocity of their obstacle, the edges of the domain follow their
+                    // boundary mode, periodic edges sample the opposite side through the wrapping of the grid
+                    if frag_coord[0] < 1.0 && left != BoundaryMode::PERIODIC { x_l = ghost(left, inflow[0][0], x_c[0]); }
+                    else if obstacles.sample(l)[0] < 0.5 { x_l = 2.0 * obstacle_velocity.sample(l)[0] - x_c[0]; }
+                    else if frag_coord[0] > resolution[0] - 1.0 && right != BoundaryMode::PERIODIC { x_r = ghost(right, inflow[1][0], x_c[0]); }
+                    else if obstacles.sample(r)[0] < 0.5 { x_r = 2.0 * obstacle_velocity.sample(r)[0] - x_c[0]; }
+
+                    if frag_coord[1] < 1.0 && bottom != BoundaryMode::PERIODIC { x_b = ghost(bottom, inflow[2][1], x_c[1]); }
+                    else if obstacles.sample(b)[0] < 0.5 { x_b = 2.0 * obstacle_velocity.sample(b)[1] - x_c[1]; }
+                    else if frag_coord[1] > resolution[1] - 1.0 && top != BoundaryMode::PERIODIC { x_t = ghost(top, inflow[3][1], x_c[1]); }
+                    else if obstacles.sample(t)[0] < 0.5 { x_t = 2.0 * obstacle_velocity.sample(t)[1] - x_c[1]; }
+
+                    [(x_r - x_l + x_t - x_b) * r_half_texel_size, 0.0, 0.0, 0.0]
+                });
+            }
+            Pass::PressureSolver { boundary, alpha, r_beta, omega, resolution, x, b, obstacles } => {
+                target.render(|frag_coord, uv| {
+                    let [x_l, x_r, x_b, x_t, x_c] = pressure_stencil(x, obstacles, boundary, &resolution, frag_coord, uv);
+                    let b_c = b.sample(uv);
+                    let mut value = [0.0; 4];
+                    for i in 0..4 {
+                        let jacobi = (x_l[i] + x_r[i] + x_b[i] + x_t[i] + alpha * b_c[i]) * r_beta;
+                        value[i] = x_c[i] * (1.0 - omega) + jacobi * omega;
+                    }
+
+                    value
+                });
+            }
+            Pass::PressureSor { boundary, alpha, omega, parity, resolution, x, b, obstacles } => {
+                let [left, right, bottom, top] = boundary;
+
+                target.render(|frag_coord, uv| {
+                    let x_c = x.sample(uv);
+
+                    // Only the texels of one color are updated, from their neighbours that all have the other one
+                    if (frag_coord[0].floor() + frag_coord[1].floor()) as u32 % 2 != parity {
+                        return x_c;
+                    }
+
+                    let (l, r, b_, t) = neighbours(uv, &resolution);
+                    let mut x_l = x.sample(l);
+                    let mut x_r = x.sample(r);
+                    let mut x_b = x.sample(b_);
+                    let mut x_t = x.sample(t);
+
+                    // The neighbours that the Jacobi pass replaces move to the diagonal instead
+                    let edge = |mode: BoundaryMode| if mode == BoundaryMode::OUTFLOW { 1.0 } else { -1.0 };
+                    let mut diagonal = 4.0;
+                    if frag_coord[0] < 1.0 && left != BoundaryMode::PERIODIC { x_l = [0.0; 4]; diagonal += edge(left); }
+                    else if obstacles.sample(l)[0] < 0.5 { x_l = [0.0; 4]; diagonal -= 1.0; }
+                    else if frag_coord[0] > resolution[0] - 1.0 && right != BoundaryMode::PERIODIC { x_r = [0.0; 4]; diagonal += edge(right); }
+                    else if obstacles.sample(r)[0] < 0.5 { x_r = [0.0; 4]; diagonal -= 1.0; }
+
+                    if frag_coord[1] < 1.0 && bottom != BoundaryMode::PERIODIC { x_b = [0.0; 4]; diagonal += edge(bottom); }
+                    else if obstacles.sample(b_)[0] < 0.5 { x_b = [0.0; 4]; diagonal -= 1.0; }
+                    else if frag_coord[1] > resolution[1] - 1.0 && top != BoundaryMode::PERIODIC { x_t = [0.0; 4]; diagonal += edge(top); }
+                    else if obstacles.sample(t)[0] < 0.5 { x_t = [0.0; 4]; diagonal -= 1.0; }
+
+                    // Solid texels are not over-relaxed, which would blow them up. Fluid texels do not depend on
+                    // them, but the gradient along the obstacles does.
+                    let omega = if obstacles.sample(uv)[0] < 0.5 { 1.0 } else { omega };
+                    let b_c = b.sample(uv);
+                    let mut value = [0.0; 4];
+                    for i in 0..4 {
+                        let gauss_seidel = (x_l[i] + x_r[i] + x_b[i] + x_t[i] + alpha * b_c[i]) / diagonal;
+                        value[i] = x_c[i] + omega * (gauss_seidel - x_c[i]);
+                    }
+
+                    value
+                });
+            }
+            Pass::Residual { boundary, alpha, resolution, x, b, obstacles } => {
+                target.render(|frag_coord, uv| {
+                    if obstacles.sample(uv)[0] < 0.5 {
+                        return [0.0; 4];
+                    }
+
+                    let [x_l, x_r, x_b, x_t, x_c] = pressure_stencil(x, obstacles, boundary, &resolution, frag_coord, uv);
+                    let b_c = b.sample(uv);
+                    let mut value = [0.0; 4];
+                    for i in 0..4 {
+                        value[i] = b_c[i] + (x_l[i] + x_r[i] + x_b[i] + x_t[i] - 4.0 * x_c[i]) / alpha;
+                    }
+
+                    value
+                });
+            }
+            Pass::Restrict { resolution, texture } => {
+                let offset = [0.25 / resolution[0], 0.25 / resolution[1]];
+                target.render(|_, uv| {
+                    let mut value = [0.0; 4];
+                    for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+                        let texel = texture.sample([uv[0] + dx * offset[0], uv[1] + dy * offset[1]]);
+                        for i in 0..4 {
+                            value[i] += 0.25 * texel[i];
+                        }
+                    }
+
+                    value
+                });
+            }
+            Pass::Prolong { x, correction } => {
+                target.render(|_, uv| {
+                    let (x, correction) = (x.sample(uv), correction.sample(uv));
+                    [0, 1, 2, 3].map(|i| x[i] + correction[i])
+                });
+            }
+            Pass::Reduce { square, resolution, texture } => {
+                target.render(|frag_coord, _| {
+                    let texel = [frag_coord[0].floor() * 2.0 + 0.5, frag_coord[1].floor() * 2.0 + 0.5];
+
+                    let mut sum = [0.0; 4];
+                    for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+                        let coord = [texel[0] + dx, texel[1] + dy];
+                        if coord[0] < resolution[0] && coord[1] < resolution[1] {
+                            let value = texture.sample([coord[0] / resolution[0], coord[1] / resolution[1]]);
+                            for i in 0..4 {
+                                sum[i] += if square { value[i] * value[i] } else { value[i] };
+                            }
+                        }
+                    }
+
+                    sum
+                });
+            }
+            Pass::GradientSubtract {
+                boundary,
+                inflow,
+                r_half_texel_size,
+                resolution,
+                velocity,
+                pressure,
+                obstacles,
+                obstacle_velocity,
+            } => {
+                target.render(|frag_coord, uv| {
+                    let at = |dx: f32, dy: f32| pressure.sample([
+                        (frag_coord[0] + dx) / resolution[0],
+                        (frag_coord[1] + dy) / resolution[1],
+                    ])[0];
+
+                    let mut x_l = at(-1.0, 0.0);
+                    let mut x_r = at(1.0, 0.0);
+                    let mut x_b = at(0.0, -1.0);
+                    let mut x_t = at(0.0, 1.0);
+
+                    // The pressure outside of an outflow is zero
+                    let x_c = pressure.sample(uv)[0];
+                    let edges = [
+                        frag_coord[0] < 1.0,
+                        frag_coord[0] > resolution[0] - 1.0,
+                        frag_coord[1] < 1.0,
+                        frag_coord[1] > resolution[1] - 1.0,
+                    ];
+                    let is = |edge: usize, mode: BoundaryMode| edges[edge] && boundary[edge] == mode;
+                    if is(0, BoundaryMode::OUTFLOW) { x_l = -x_c; }
+                    if is(1, BoundaryMode::OUTFLOW) { x_r = -x_c; }
+                    if is(2, BoundaryMode::OUTFLOW) { x_b = -x_c; }
+                    if is(3, BoundaryMode::OUTFLOW) { x_t = -x_c; }
+
+                    let gradient = [x_r - x_l, x_t - x_b];
+                    let value = velocity.sample(uv);
+                    let value = sub([value[0], value[1]], scale(gradient, r_half_texel_size));
+                    let obstacle = obstacles.sample(uv)[0];
+                    let obstacle_velocity = obstacle_velocity.sample(uv);
+                    let mut value = [
+                        mix(obstacle_velocity[0], value[0], obstacle),
+                        mix(obstacle_velocity[1], value[1], obstacle),
+                    ];
+
+                    // The fluid sticks to no-slip walls and enters through inflows with their velocity
+                    if is(0, BoundaryMode::NO_SLIP) || is(1, BoundaryMode::NO_SLIP) { value[1] = 0.0; }
+                    if is(2, BoundaryMode::NO_SLIP) || is(3, BoundaryMode::NO_SLIP) { value[0] = 0.0; }
+                    for (edge, inflow) in inflow.iter().enumerate() {
+                        if is(edge, BoundaryMode::INFLOW) { value = *inflow; }
+                    }
+
+                    [value[0], value[1], 0.0, 0.0]
+                });
+            }
+            Pass::Curl { r_half_texel_size, resolution, velocity } => {
+                target.render(|_, uv| {
+                    let (l, r, b, t) = neighbours(uv, &resolution);
+
+                    let x_l = velocity.sample(l)[1];
+                    let x_r = velocity.sample(r)[1];
+                    let x_b = velocity.sample(b)[0];
+                    let x_t = velocity.sample(t)[0];
+
+                    [((x_t - x_b) - (x_r - x_l)) * r_half_texel_size, 0.0, 0.0, 0.0]
+                });
+            }
+            Pass::Vorticity { curl_scale, r_half_texel_size, resolution, curl, velocity } => {
+                target.render(|_, uv| {
+                    let (l, r, b, t) = neighbours(uv, &resolution);
+
+                    let x_l = curl.sample(l)[0].abs();
+                    let x_r = curl.sample(r)[0].abs();
+                    let x_b = curl.sample(b)[0].abs();
+                    let x_t = curl.sample(t)[0].abs();
+                    let x_c = curl.sample(uv)[0];
+
+                    let gradient = scale([x_t - x_b, x_l - x_r], r_half_texel_size);
+                    let vorticity = scale(gradient, 1.0 / length(gradient).max(0.0001));
+                    let force = scale(vorticity, x_c * curl_scale);
+
+                    let value = velocity.sample(uv);
+                    [value[0] + force[0], value[1] + force[1], 0.0, 0.0]
+                });
+            }
+            Pass::Splat { scaled_radius, position, period, color, texture, obstacles } => {
+                target.render(|frag_coord, uv| {
+                    let value = texture.sample(uv);
+                    let mut distance = sub(frag_coord, position);
+
+                    // Splats wrap around periodic axes, which have a period larger than zero
+                    for i in 0..2 {
+                        if period[i] > 0.0 {
+                            distance[i] -= period[i] * (distance[i] / period[i] + 0.5).floor();
+                        }
+                    }
+
+                    let splat = (-dot(distance, distance) / scaled_radius).exp();
+                    let obstacle = obstacles.sample(uv)[0];
+
+                    [
+                        (value[0] + color[0] * splat) * obstacle,
+                        (value[1] + color[1] * splat) * obstacle,
+                        (value[2] + color[2] * splat) * obstacle,
+                        0.0,
+                    ]
+                });
+            }
+            Pass::Obstacle {
+                shape,
+                write_velocity,
+                index,
+                size,
+                position,
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color,
+                obstacles,
+            } => {
+                target.render(|frag_coord, uv| {
+                    let offset = sub(frag_coord, position);
+                    let distance = [
+                        rotation[0] * offset[0] + rotation[1] * offset[1],
+                        rotation[0] * offset[1] - rotation[1] * offset[0],
+                    ];
+
+                    let inside = match shape {
+                        ShapeKind::CIRCLE => dot(distance, distance) < size[0] * size[0],
+                        ShapeKind::SQUARE | ShapeKind::RECTANGLE => {
+                            distance[0] * distance[0] < size[0] * size[0] && distance[1] * distance[1] < size[1] * size[1]
+                        }
+                        ShapeKind::CAPSULE => {
+                            let segment = [(distance[0].abs() - size[0]).max(0.0), distance[1]];
+                            dot(segment, segment) < size[1] * size[1]
+                        }
+                        ShapeKind::ANNULUS => {
+                            let distance_sqr = dot(distance, distance);
+                            distance_sqr >= size[0] * size[0] && distance_sqr < size[1] * size[1]
+                        }
+                        ShapeKind::POLYGON | ShapeKind::MASK => false,
+                    };
+
+                    if !inside {
+                        obstacles.sample(uv)
+                    } else if write_velocity {
+                        [
+                            linear_velocity[0] - angular_velocity * offset[1],
+                            linear_velocity[1] + angular_velocity * offset[0],
+                            index as f32,
+                            0.0,
+                        ]
+                    } else {
+                        [0.0, obstacle_color[0], obstacle_color[1], obstacle_color[2]]
+                    }
+                });
+            }
+            Pass::ObstacleMask {
+                write_velocity,
+                index,
+                threshold,
+                position,
+                half_size,
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color,
+                mask,
+                obstacles,
+            } => {
+                target.render(|frag_coord, uv| {
+                    let distance = sub(frag_coord, position);
+                    let local = [
+                        rotation[0] * distance[0] + rotation[1] * distance[1],
+                        rotation[0] * distance[1] - rotation[1] * distance[0],
+                    ];
+                    let mask_uv = [
+                        0.5 + 0.5 * local[0] / half_size[0],
+                        0.5 + 0.5 * local[1] / half_size[1],
+                    ];
+
+                    let inside = mask_uv.iter().all(|v| (0.0..=1.0).contains(v)) && mask.sample(mask_uv)[0] >= threshold;
+
+                    if !inside {
+                        obstacles.sample(uv)
+                    } else if write_velocity {
+                        [
+                            linear_velocity[0] - angular_velocity * distance[1],
+                            linear_velocity[1] + angular_velocity * distance[0],
+                            index as f32,
+                            0.0,
+                        ]
+                    } else {
+                        [0.0, obstacle_color[0], obstacle_color[1], obstacle_color[2]]
+                    }
+                });
+            }
+            Pass::ObstaclePolygon {
+                write_velocity,
+                index,
+                position,
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color,
+                vertices,
+                obstacles,
+            } => {
+                let vertices: Vec<[f32; 2]> = vertices.data().iter().map(|v| [v[0], v[1]]).collect();
+                target.render(|frag_coord, uv| {
+                    let offset = sub(frag_coord, position);
+                    let local = [
+                        rotation[0] * offset[0] + rotation[1] * offset[1],
+                        rotation[0] * offset[1] - rotation[1] * offset[0],
+                    ];
+
+                    // Even-odd rule, so that concave polygons work as well
+                    let mut inside = false;
+                    let mut previous = vertices[vertices.len() - 1];
+                    for &current in &vertices {
+                        if (current[1] > local[1]) != (previous[1] > local[1])
+                            && local[0] < (previous[0] - current[0]) * (local[1] - current[1]) / (previous[1] - current[1]) + current[0] {
+                            inside = !inside;
+                        }
+                        previous = current;
+                    }
+
+                    if !inside {
+                        obstacles.sample(uv)
+                    } else if write_velocity {
+                        [
+                            linear_velocity[0] - angular_velocity * offset[1],
+                            linear_velocity[1] + angular_velocity * offset[0],
+                            index as f32,
+                            0.0,
+                        ]
+                    } else {
+                        [0.0, obstacle_color[0], obstacle_color[1], obstacle_color[2]]
+                    }
+                });
+            }
+            Pass::Force { pressure_scale, viscous_scale, resolution, pressure, velocity, obstacles, obstacle_velocity } => {
+                target.render(|_, uv| {
+                    // The lower half of the target holds the forces, the upper half the obstacle indices
+                    let upper = uv[1] >= 0.5;
+                    let uv = [uv[0], fract(2.0 * uv[1])];
+                    if upper {
+                        return [obstacle_velocity.sample(uv)[2], 0.0, 0.0, 0.0];
+                    }
+                    if obstacles.sample(uv)[0] >= 0.5 {
+                        return [0.0; 4];
+                    }
+
+                    let wall = obstacle_velocity.sample(uv);
+                    let mut force = [0.0; 4];
+                    for direction in [[-1.0, 0.0], [1.0, 0.0], [0.0, -1.0], [0.0, 1.0]] {
+                        let uv = [uv[0] + direction[0] / resolution[0], uv[1] + direction[1] / resolution[1]];
+                        if obstacles.sample(uv)[0] < 0.5 {
+                            continue;
+                        }
+
+                        // The pressure pushes into the obstacle, the shear drags it along with the fluid
+                        let pressure = pressure.sample(uv)[0];
+                        let tangent = [-direction[1], direction[0]];
+                        let value = velocity.sample(uv);
+                        let slip = dot(sub([value[0], value[1]], [wall[0], wall[1]]), tangent);
+                        force[0] -= direction[0] * pressure * pressure_scale;
+                        force[1] -= direction[1] * pressure * pressure_scale;
+                        force[2] += tangent[0] * slip * viscous_scale;
+                        force[3] += tangent[1] * slip * viscous_scale;
+                    }
+
+                    force
+                });
+            }
+            Pass::Particles { delta_time, lifetime, seed, period, resolution, particles, velocity, obstacles, emitters } => {
+                let velocity_at = |position: [f32; 2]| {
+                    let velocity = velocity.sample(position);
+                    [velocity[0] / resolution[0], velocity[1] / resolution[1]]
+                };
+
+                target.render(|_, uv| {
+                    let random = |salt: f32| {
+                        fract(dot([uv[0] + seed, uv[1] + salt], [12.9898, 78.233]).sin() * 43_758.547)
+                    };
+
+                    let particle = particles.sample(uv);
+
+                    // Midpoint integration along the velocity
+                    let start = velocity_at([particle[0], particle[1]]);
+                    let midpoint = [
+                        particle[0] + 0.5 * delta_time * start[0],
+                        particle[1] + 0.5 * delta_time * start[1],
+                    ];
+                    let step = scale(velocity_at(midpoint), delta_time);
+                    let mut position = [particle[0] + step[0], particle[1] + step[1]];
+                    for (position, period) in position.iter_mut().zip(period) {
+                        if period > 0.0 { *position = fract(*position); }
+                    }
+
+                    let age = particle[2] + delta_time;
+                    let outside = position.iter().any(|v| *v < 0.0 || *v > 1.0);
+                    if age < particle[3] && !outside && obstacles.sample(position)[0] >= 0.5 {
+                        return [position[0], position[1], age, particle[3]];
+                    }
+
+                    // Emit again
+                    let mut position = [random(1.0), random(2.0)];
+                    if let Some(emitters) = emitters {
+                        // A random emitter, and a point spread evenly over its area
+                        let count = emitters.width() as f32;
+                        let index = (random(3.0) * count).floor();
+                        let emitter = emitters.sample([(index + 0.5) / count, 0.5]);
+                        let angle = std::f32::consts::TAU * position[0];
+                        let distance = position[1].sqrt();
+                        position = [
+                            emitter[0] + distance * emitter[2] * angle.cos(),
+                            emitter[1] + distance * emitter[3] * angle.sin(),
+                        ];
+                    }
+
+                    [position[0], position[1], 0.0, lifetime * (0.5 + random(4.0))]
+                });
+            }
+            Pass::DrawParticles { count, size, streak, color_mode, color, max_speed, resolution, particles, velocity } => {
+                let (width, height) = (target.width() as f32, target.height() as f32);
+                for particle in particles.data().iter().take(count as usize) {
+                    let velocity = velocity.sample([particle[0], particle[1]]);
+                    let velocity = [velocity[0], velocity[1]];
+
+                    // Particles fade in after they are emitted and out before they die
+                    let life = particle[2] / particle[3].max(0.000001);
+                    let alpha = smoothstep(0.0, 0.1, life) * (1.0 - smoothstep(0.8, 1.0, life));
+                    let color = match color_mode {
+                        ParticleColor::SOLID => color,
+                        ParticleColor::SPEED => {
+                            let speed = (length(velocity) / max_speed).clamp(0.0, 1.0);
+                            [3.0, 2.0, 1.0].map(|offset: f32| (1.5 - (4.0 * speed - offset).abs()).clamp(0.0, 1.0))
+                        }
+                    };
+
+                    let head = [particle[0] * width, particle[1] * height];
+                    if streak > 0.0 {
+                        // A line towards the end of the streak, fading out along the way
+                        let tail = [
+                            head[0] - streak * velocity[0] / resolution[0] * width,
+                            head[1] - streak * velocity[1] / resolution[1] * height,
+                        ];
+                        draw_line(
+                            target,
+                            [head, tail],
+                            [color, color],
+                            [alpha, 0.0],
+                        );
+                    } else {
+                        // Points cover every texel whose center they contain
+                        let min = [(head[0] - 0.5 * size).round() as i64, (head[1] - 0.5 * size).round() as i64];
+                        let max = [(head[0] + 0.5 * size).round() as i64, (head[1] + 0.5 * size).round() as i64];
+                        for y in min[1]..max[1] {
+                            for x in min[0]..max[0] {
+                                blend(target, [x, y], color, alpha);
+                            }
+                        }
+                    }
+                }
+            }
+            Pass::Streamlines { step_size, grid, period, resolution, seeds, velocity, obstacles } => {
+                // Direction of the flow with a length of one step, zero where the fluid stands still
+                let direction = |position: [f32; 2]| {
+                    let velocity = velocity.sample(position);
+                    let speed = length([velocity[0], velocity[1]]);
+                    if speed < 0.001 {
+                        return [0.0; 2];
+                    }
+
+                    [
+                        step_size * velocity[0] / speed / resolution[0],
+                        step_size * velocity[1] / speed / resolution[1],
+                    ]
+                };
+                let add = |a: [f32; 2], b: [f32; 2], s: f32| [a[0] + b[0] * s, a[1] + b[1] * s];
+
+                target.render(|frag_coord, _| {
+                    let index = frag_coord[1].floor();
+                    let mut position = match seeds {
+                        Some(seeds) => {
+                            let seed = seeds.sample([(index + 0.5) / seeds.width() as f32, 0.5]);
+                            [seed[0], seed[1]]
+                        }
+                        None => [
+                            (index.rem_euclid(grid[0]) + 0.5) / grid[0],
+                            ((index / grid[0]).floor() + 0.5) / grid[1],
+                        ],
+                    };
+
+                    let mut alive = 1.0;
+                    for _ in 0..frag_coord[0] as u32 {
+                        // RK4 along the direction of the flow
+                        let k1 = direction(position);
+                        let k2 = direction(add(position, k1, 0.5));
+                        let k3 = direction(add(position, k2, 0.5));
+                        let k4 = direction(add(position, k3, 1.0));
+                        let mut next = add(
+                            position,
+                            [k1[0] + 2.0 * k2[0] + 2.0 * k3[0] + k4[0], k1[1] + 2.0 * k2[1] + 2.0 * k3[1] + k4[1]],
+                            1.0 / 6.0,
+                        );
+                        for (next, period) in next.iter_mut().zip(period) {
+                            if period > 0.0 { *next = fract(*next); }
+                        }
+
+                        let outside = next.iter().any(|v| *v < 0.0 || *v > 1.0);
+                        if k1 == [0.0; 2] || outside || obstacles.sample(next)[0] < 0.5 {
+                            alive = 0.0;
+                            break;
+                        }
+                        position = next;
+                    }
+
+                    let velocity = velocity.sample(position);
+                    [position[0], position[1], length([velocity[0], velocity[1]]), alive]
+                });
+            }
+            Pass::DrawStreamlines { max_speed, lines } => {
+                let (width, height) = (target.width() as f32, target.height() as f32);
+                let color = |point: [f32; 4]| {
+                    let speed = (point[2] / max_speed).clamp(0.0, 1.0);
+                    [3.0, 2.0, 1.0].map(|offset: f32| (1.5 - (4.0 * speed - offset).abs()).clamp(0.0, 1.0))
+                };
+
+                for row in 0..lines.height() as i64 {
+                    for column in 0..lines.width() as i64 - 1 {
+                        let (start, next) = (lines.texel(column, row), lines.texel(column + 1, row));
+
+                        // Segments that stop or wrap around a periodic edge are not drawn, lines fade along the flow
+                        let wrapped = length([next[0] - start[0], next[1] - start[1]]) >= 0.5;
+                        if start[3] * next[3] == 0.0 || wrapped {
+                            continue;
+                        }
+
+                        let fade = |column: i64| 1.0 - 0.75 * (column as f32 + 0.5) / lines.width() as f32;
+                        draw_line(
+                            target,
+                            [[start[0] * width, start[1] * height], [next[0] * width, next[1] * height]],
+                            [color(start), color(next)],
+                            [fade(column), fade(column)],
+                        );
+                    }
+                }
+            }
+            Pass::Colormap { colormap, range, magnitude, texture, obstacles } => {
+                target.render(|_, uv| {
+                    if let Some(obstacle) = obstacles.map(|obstacles| obstacles.sample(uv)) {
+                        if obstacle[0] < 0.5 {
+                            return [obstacle[1], obstacle[2], obstacle[3], 1.0];
+                        }
+                    }
+
+                    let value = texture.sample(uv);
+                    let x = match magnitude {
+                        true => length([value[0], value[1]]),
+                        false => value[0],
+                    };
+                    let [r, g, b] = colormap.color((x - range[0]) / (range[1] - range[0]));
+
+                    [r, g, b, 1.0]
+                });
+            }
+            Pass::Streaklines { count, radius, length, resolution, color, texture } => {
+                let spacing = resolution[1] / count as f32;
+                target.render(|frag_coord, uv| {
+                    let value = texture.sample(uv);
+                    let line = ((frag_coord[1] / spacing).floor() + 0.5) * spacing;
+                    if frag_coord[0] < length && (frag_coord[1] - line).abs() < radius {
+                        [color[0], color[1], color[2], value[3]]
+                    } else {
+                        value
+                    }
+                });
+            }
+            Pass::ColorObstacle { intensity, obstacles, texture } => {
+                target.render(|_, uv| {
+                    let value = texture.sample(uv);
+                    let obstacle = obstacles.sample(uv);
+                    if obstacle[0] < 0.5 {
+                        [obstacle[1] * intensity, obstacle[2] * intensity, obstacle[3] * intensity, value[3]]
+                    } else {
+                        value
+                    }
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn present(
+        &mut self,
+        texture: &Grid,
+        factor: f32,
+        offset: f32,
+    ) -> Result<(), Infallible> {
+        if self.frame.width() != texture.width() || self.frame.height() != texture.height() {
+            self.frame = Grid::new(texture.width(), texture.height(), Filter::Nearest);
+        }
+
+        self.frame.render(|_, uv| {
+            texture.sample(uv).map(|v| v * factor + offset)
+        });
+
+        Ok(())
+    }
+}
+
+/// Blend `color` over the texel at `position` of `target` with `alpha`, keeping its alpha
+fn blend(target: &mut Grid, position: [i64; 2], color: [f32; 3], alpha: f32) {
+    let (width, height) = (target.width() as i64, target.height() as i64);
+    if position[0] < 0 || position[1] < 0 || position[0] >= width || position[1] >= height {
+        return;
+    }
+
+    let texel = &mut target.data_mut()[(position[1] * width + position[0]) as usize];
+    for (value, color) in texel.iter_mut().zip(color) {
+        *value = mix(*value, color, alpha);
+    }
+}
+
+/// Blend a line from `points[0]` to `points[1]` over `target`, interpolating the color and the alpha
+fn draw_line(target: &mut Grid, points: [[f32; 2]; 2], colors: [[f32; 3]; 2], alpha: [f32; 2]) {
+    let [from, to] = points;
+    let steps = (to[0] - from[0]).abs().max((to[1] - from[1]).abs()).ceil().max(1.0);
+    for step in 0..=steps as u32 {
+        let t = step as f32 / steps;
+        blend(
+            target,
+            [mix(from[0], to[0], t).floor() as i64, mix(from[1], to[1], t).floor() as i64],
+            [0, 1, 2].map(|i| mix(colors[0][i], colors[1][i], t)),
+            mix(alpha[0], alpha[1], t),
+        );
+    }
+}
+
+/// The pressure of the four neighbours of a texel and of the texel itself, with the neighbours beyond an
+/// edge or inside of an obstacle replaced like in `pressure_solver.glsl`
+fn pressure_stencil(
+    x: &Grid,
+    obstacles: &Grid,
+    boundary: [BoundaryMode; 4],
+    resolution: &[f32; 2],
+    frag_coord: [f32; 2],
+    uv: [f32; 2],
+) -> [[f32; 4]; 5] {
+    // Pressure just outside of an edge, zero at an outflow
+    let ghost = |mode: BoundaryMode, x_c: [f32; 4]| match mode {
+        BoundaryMode::OUTFLOW => x_c.map(|v| -v),
+        _ => x_c,
+    };
+    let [left, right, bottom, top] = boundary;
+    let (l, r, b, t) = neighbours(uv, resolution);
+
+    let mut x_l = x.sample(l);
+    let mut x_r = x.sample(r);
+    let mut x_b = x.sample(b);
+    let mut x_t = x.sample(t);
+    let x_c = x.sample(uv);
+
+    if frag_coord[0] < 1.0 && left != BoundaryMode::PERIODIC { x_l = ghost(left, x_c); }
+    else if obstacles.sample(l)[0] < 0.5 { x_l = x_c; }
+    else if frag_coord[0] > resolution[0] - 1.0 && right != BoundaryMode::PERIODIC { x_r = ghost(right, x_c); }
+    else if obstacles.sample(r)[0] < 0.5 { x_r = x_c; }
+
+    if frag_coord[1] < 1.0 && bottom != BoundaryMode::PERIODIC { x_b = ghost(bottom, x_c); }
+    else if obstacles.sample(b)[0] < 0.5 { x_b = x_c; }
+    else if frag_coord[1] > resolution[1] - 1.0 && top != BoundaryMode::PERIODIC { x_t = ghost(top, x_c); }
+    else if obstacles.sample(t)[0] < 0.5 { x_t = x_c; }
+
+    [x_l, x_r, x_b, x_t, x_c]
+}
+
+fn neighbours(uv: [f32; 2], resolution: &[f32; 2]) -> ([f32; 2], [f32; 2], [f32; 2], [f32; 2]) {
+    let dx = 1.0 / resolution[0];
+    let dy = 1.0 / resolution[1];
+
+    (
+        [uv[0] - dx, uv[1]],
+        [uv[0] + dx, uv[1]],
+        [uv[0], uv[1] - dy],
+        [uv[0], uv[1] + dy],
+    )
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn length(a: [f32; 2]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn fract(a: f32) -> f32 {
+    a - a.floor()
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLS: [BoundaryMode; 4] = [BoundaryMode::NO_SLIP; 4];
+
+    /// A grid of `width` x `height` texels with the values of `texel` at every column and row
+    fn grid(width: u32, height: u32, texel: impl Fn(u32, u32) -> [f32; 4]) -> Grid {
+        let mut grid = Grid::new(width, height, Filter::Linear);
+        for (i, value) in grid.data_mut().iter_mut().enumerate() {
+            *value = texel(i as u32 % width, i as u32 / width);
+        }
+
+        grid
+    }
+
+    #[test]
+    fn divergence_of_a_linear_field() {
+        let mut backend = CpuBackend::new();
+        let velocity = grid(6, 5, |x, y| [2.0 * x as f32, -3.0 * y as f32, 0.0, 0.0]);
+        let fluid = grid(6, 5, |_, _| [1.0; 4]);
+        let still = grid(6, 5, |_, _| [0.0; 4]);
+        let mut divergence = Grid::new(6, 5, Filter::Linear);
+
+        let Ok(()) = backend.dispatch(
+            Pass::Divergence {
+                boundary: WALLS,
+                inflow: [[0.0; 2]; 4],
+                r_half_texel_size: 0.5,
+                resolution: [6.0, 5.0],
+                velocity: &velocity,
+                obstacles: &fluid,
+                obstacle_velocity: &still,
+            },
+            &mut divergence,
+        );
+
+        // Inside, the central differences are 2 * 2 and -3 * 2. The walls mirror the velocity next to them,
+        // so the ghost texel of the left and bottom edges is zero and that of the right and top ones the
+        // negated velocity of the edge.
+        for (i, value) in divergence.data().iter().enumerate() {
+            let (x, y) = (i % 6, i / 6);
+            let du = match x {
+                0 => 2.0 - 0.0,
+                5 => -10.0 - 8.0,
+                _ => 4.0,
+            };
+            let dv = match y {
+                0 => -3.0 - 0.0,
+                4 => 12.0 - -9.0,
+                _ => -6.0,
+            };
+            let expected = (du + dv) * 0.5;
+            assert!((value[0] - expected).abs() < 1e-5, "texel ({}, {}): {} instead of {}", x, y, value[0], expected);
+        }
+    }
+
+    #[test]
+    fn jacobi_converges() {
+        let mut backend = CpuBackend::new();
+        let fluid = grid(8, 8, |_, _| [1.0; 4]);
+        // A source and a sink of the same strength, so that walls all around leave a solution
+        let divergence = grid(8, 8, |x, y| match (x, y) {
+            (2, 2) => [1.0; 4],
+            (5, 5) => [-1.0; 4],
+            _ => [0.0; 4],
+        });
+        let mut pressure = [Grid::new(8, 8, Filter::Linear), Grid::new(8, 8, Filter::Linear)];
+
+        let iterate = |backend: &mut CpuBackend, pressure: &mut [Grid; 2]| {
+            let [read, write] = pressure;
+            let Ok(()) = backend.dispatch(
+                Pass::PressureSolver {
+                    boundary: WALLS,
+                    alpha: -1.0,
+                    r_beta: 0.25,
+                    omega: 1.0,
+                    resolution: [8.0, 8.0],
+                    x: read,
+                    b: &divergence,
+                    obstacles: &fluid,
+                },
+                write,
+            );
+            pressure.swap(0, 1);
+        };
+
+        // The first iteration from zero only spreads the divergence over the diagonal
+        iterate(&mut backend, &mut pressure);
+        for (i, value) in pressure[0].data().iter().enumerate() {
+            let expected = match i {
+                18 => -0.25,
+                45 => 0.25,
+                _ => 0.0,
+            };
+            assert_eq!(value[0], expected, "texel {}", i);
+        }
+
+        for _ in 0..1000 {
+            iterate(&mut backend, &mut pressure);
+        }
+
+        let mut residual = Grid::new(8, 8, Filter::Linear);
+        let Ok(()) = backend.dispatch(
+            Pass::Residual {
+                boundary: WALLS,
+                alpha: -1.0,
+                resolution: [8.0, 8.0],
+                x: &pressure[0],
+                b: &divergence,
+                obstacles: &fluid,
+            },
+            &mut residual,
+        );
+        let max = residual.data().iter().map(|texel| texel[0].abs()).fold(0.0, f32::max);
+        assert!(max < 1e-4, "residual {} left after 1000 iterations", max);
+    }
+}
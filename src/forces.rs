@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// The force of the fluid on an obstacle during the last step of the [simulation](crate::Simulation)
+///
+/// Forces are in pixel units per unit of depth, with a fluid density of one: the pressure is integrated
+/// over the faces between the obstacle and the fluid, and the viscous force is the shear of the
+/// tangential velocity at those faces, using the viscosity of the
+/// [configuration](crate::SimulationConfig). Both are estimates on the grid of the obstacle texture.
+/// The torque of both is taken around the position of the obstacle, counterclockwise positive.
+pub struct ObstacleForce {
+    id: u32,
+    pressure: [f32; 2],
+    viscous: [f32; 2],
+    torque: f32,
+}
+
+impl ObstacleForce {
+    pub(crate) fn new(id: u32, pressure: [f32; 2], viscous: [f32; 2], torque: f32) -> ObstacleForce {
+        ObstacleForce {
+            id,
+            pressure,
+            viscous,
+            torque,
+        }
+    }
+
+    /// The total force, the sum of the pressure and the viscous force
+    pub fn net(&self) -> [f32; 2] {
+        [self.pressure[0] + self.viscous[0], self.pressure[1] + self.viscous[1]]
+    }
+
+    /// Split the total force into drag, along `flow`, and lift, across it
+    ///
+    /// # Returns
+    /// The drag and the lift vector, or the total force as drag if `flow` has no length.
+    pub fn drag_lift(&self, flow: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+        let net = self.net();
+        let length = (flow[0] * flow[0] + flow[1] * flow[1]).sqrt();
+        if length == 0.0 {
+            return (net, [0.0; 2]);
+        }
+
+        let direction = [flow[0] / length, flow[1] / length];
+        let along = net[0] * direction[0] + net[1] * direction[1];
+        let drag = [along * direction[0], along * direction[1]];
+
+        (drag, [net[0] - drag[0], net[1] - drag[1]])
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl ObstacleForce {
+    /// The id of the obstacle
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The force of the pressure, an x and a y value
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn pressure(&self) -> Vec<f32> {
+        self.pressure.to_vec()
+    }
+
+    /// The viscous force, an x and a y value
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn viscous(&self) -> Vec<f32> {
+        self.viscous.to_vec()
+    }
+
+    /// The total force, an x and a y value
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn total(&self) -> Vec<f32> {
+        self.net().to_vec()
+    }
+
+    /// The torque of the total force around the position of the obstacle, counterclockwise positive
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn torque(&self) -> f32 {
+        self.torque
+    }
+
+    /// The part of the total force along the direction of the flow
+    ///
+    /// # Arguments
+    /// * `flow` - A float array that should have two values, the x and y direction of the undisturbed flow
+    ///
+    /// # Panics
+    /// If `flow` contains fewer than two values.
+    pub fn drag(&self, flow: &[f32]) -> Vec<f32> {
+        self.drag_lift([flow[0], flow[1]]).0.to_vec()
+    }
+
+    /// The part of the total force across the direction of the flow
+    ///
+    /// # Arguments
+    /// * `flow` - A float array that should have two values, the x and y direction of the undisturbed flow
+    ///
+    /// # Panics
+    /// If `flow` contains fewer than two values.
+    pub fn lift(&self, flow: &[f32]) -> Vec<f32> {
+        self.drag_lift([flow[0], flow[1]]).1.to_vec()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// The forces of the last steps, oldest first, dropping the oldest once `capacity` steps are stored
+pub(crate) struct ForceHistory {
+    capacity: usize,
+    time: f32,
+    samples: VecDeque<(f32, Vec<ObstacleForce>)>,
+}
+
+impl ForceHistory {
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Store the forces of a step of length `delta_time`
+    pub fn push(&mut self, delta_time: f32, forces: &[ObstacleForce]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.time += delta_time;
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((self.time, forces.to_vec()));
+    }
+
+    /// The time and the total force of the obstacle with the id `id` for every stored step it was part of
+    pub fn series(&self, id: u32) -> Vec<(f32, [f32; 2])> {
+        self.samples
+            .iter()
+            .filter_map(|(time, forces)| {
+                let force = forces.iter().find(|force| force.id == id)?;
+                Some((*time, force.net()))
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.time = 0.0;
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_starts_its_time_when_enabled() {
+        let forces = [ObstacleForce::new(2, [1.0, 0.0], [0.5, 0.0], 0.0)];
+        let mut history = ForceHistory::default();
+        history.push(1.0, &forces);
+        history.push(1.0, &forces);
+
+        history.set_capacity(2);
+        for _ in 0..3 {
+            history.push(0.25, &forces);
+        }
+        assert_eq!(history.series(2), [(0.5, [1.5, 0.0]), (0.75, [1.5, 0.0])]);
+        assert!(history.series(3).is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::backend::BackendTexture;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Sampling filter of a [grid](Grid), mirroring the `LINEAR` and `NEAREST` texture parameters
+pub enum Filter {
+    Linear,
+    Nearest,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Wrapping of a [grid](Grid) along one axis, mirroring the `CLAMP_TO_EDGE` and `REPEAT` texture parameters
+pub enum Wrap {
+    Clamp,
+    Repeat,
+}
+
+#[derive(Clone, Debug)]
+/// CPU counterpart of a `TextureFramebuffer`
+///
+/// Stores four channels per texel, just like the `RGBA16F` textures used on the GPU, and samples with
+/// `CLAMP_TO_EDGE` wrapping unless [set_wrap](Grid::set_wrap) says otherwise.
+pub struct Grid {
+    data: Vec<[f32; 4]>,
+    width: u32,
+    height: u32,
+    filter: Filter,
+    wrap: [Wrap; 2],
+}
+
+impl Grid {
+    pub fn new(width: u32, height: u32, filter: Filter) -> Grid {
+        Grid {
+            data: vec![[0.0; 4]; (width * height) as usize],
+            width,
+            height,
+            filter,
+            wrap: [Wrap::Clamp; 2],
+        }
+    }
+
+    /// Run a "fragment shader" over every texel of the grid
+    ///
+    /// The closure receives `gl_FragCoord.xy` and `v_uv` of the texel and returns the new value.
+    pub fn render<F>(&mut self, fragment: F)
+    where
+        F: Fn([f32; 2], [f32; 2]) -> [f32; 4],
+    {
+        let (width, height) = (self.width as f32, self.height as f32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let frag_coord = [x as f32 + 0.5, y as f32 + 0.5];
+                let uv = [frag_coord[0] / width, frag_coord[1] / height];
+                self.data[(y * self.width + x) as usize] = fragment(frag_coord, uv);
+            }
+        }
+    }
+
+    /// Sample the grid at `uv` using the filter of the grid
+    pub fn sample(&self, uv: [f32; 2]) -> [f32; 4] {
+        match self.filter {
+            Filter::Linear => self.sample_linear(uv),
+            Filter::Nearest => self.sample_nearest(uv),
+        }
+    }
+
+    fn sample_nearest(&self, uv: [f32; 2]) -> [f32; 4] {
+        let x = (uv[0] * self.width as f32).floor() as i64;
+        let y = (uv[1] * self.height as f32).floor() as i64;
+        self.texel(x, y)
+    }
+
+    fn sample_linear(&self, uv: [f32; 2]) -> [f32; 4] {
+        let x = uv[0] * self.width as f32 - 0.5;
+        let y = uv[1] * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let a = self.texel(x0, y0);
+        let b = self.texel(x0 + 1, y0);
+        let c = self.texel(x0, y0 + 1);
+        let d = self.texel(x0 + 1, y0 + 1);
+
+        let mut value = [0.0; 4];
+        for i in 0..4 {
+            let bottom = a[i] + (b[i] - a[i]) * fx;
+            let top = c[i] + (d[i] - c[i]) * fx;
+            value[i] = bottom + (top - bottom) * fy;
+        }
+
+        value
+    }
+
+    /// Get the texel at `(x, y)`, clamping or wrapping the coordinates to the grid
+    pub fn texel(&self, x: i64, y: i64) -> [f32; 4] {
+        let wrap = |value: i64, size: u32, wrap: Wrap| match wrap {
+            Wrap::Clamp => value.clamp(0, size as i64 - 1) as u32,
+            Wrap::Repeat => value.rem_euclid(size as i64) as u32,
+        };
+
+        let x = wrap(x, self.width, self.wrap[0]);
+        let y = wrap(y, self.height, self.wrap[1]);
+        self.data[(y * self.width + x) as usize]
+    }
+
+    pub fn data(&self) -> &[[f32; 4]] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [[f32; 4]] {
+        &mut self.data
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn wrap(&self) -> [Wrap; 2] {
+        self.wrap
+    }
+
+    /// Set the wrapping along the x and the y axis
+    pub fn set_wrap(&mut self, wrap: [Wrap; 2]) {
+        self.wrap = wrap;
+    }
+}
+
+impl BackendTexture for Grid {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+}
@@ -1,22 +1,73 @@
 //! A fluid simulation that is compiled using `wasm-pack` and runs in the browser
+//!
+//! The solver itself is plain Rust and runs on any [backend](SimulationBackend). The WebGL2 front end
+//! used by the browser lives behind the `webgl` feature (enabled by default). Build with
+//! `--no-default-features` to use the crate as a native library with the [CPU backend](CpuBackend).
 
+#[cfg(feature = "webgl")]
 mod shaders;
+#[cfg(feature = "webgl")]
 mod textures;
+#[cfg(feature = "webgl")]
 mod renderer;
+#[cfg(feature = "webgl")]
 mod shader_program;
-
+#[cfg(feature = "webgl")]
+mod webgl_backend;
+mod config;
+mod grid;
+mod mask;
+mod obstacle;
+mod particles;
+mod bytes;
+mod backend;
+mod boundary;
+mod colormap;
+mod multigrid;
+mod conjugate_gradient;
+mod simulation;
+mod snapshot;
+mod streamlines;
+mod recording;
+mod residual;
+mod rigid_body;
+mod cpu_backend;
+mod forces;
+mod wind_tunnel;
+
+#[cfg(feature = "webgl")]
 use wasm_bindgen::prelude::*;
+#[cfg(feature = "webgl")]
 use web_sys::{ HtmlCanvasElement, WebGl2RenderingContext };
-use crate::textures::{ TextureFramebuffer, RWTextureBuffer };
-use crate::shader_program::ShaderProgram;
+#[cfg(feature = "webgl")]
+use crate::webgl_backend::WebGlBackend;
+
+pub use crate::backend::{ BackendTexture, Pass, PingPong, SimulationBackend };
+pub use crate::boundary::{ Boundaries, BoundaryMode, Edge };
+pub use crate::colormap::Colormap;
+pub use crate::config::{ ConfigError, SimulationConfig };
+pub use crate::cpu_backend::{ CpuBackend, CpuSolver };
+pub use crate::forces::ObstacleForce;
+pub use crate::grid::{ Filter, Grid, Wrap };
+pub use crate::mask::{ Mask, MaskError };
+pub use crate::obstacle::{ Obstacle, Shape, ShapeError, ShapeKind, MAX_POLYGON_VERTICES };
+pub use crate::particles::{ ParticleColor, Particles, MAX_PARTICLES };
+pub use crate::recording::{ Event, Player, Record, Recorder, Recording, RecordingError, RECORDING_VERSION };
+pub use crate::rigid_body::{ RigidBody, RigidBodyError };
+pub use crate::simulation::Simulation;
+pub use crate::snapshot::{ Field, Precision, Snapshot, SnapshotError, SNAPSHOT_VERSION };
+pub use crate::streamlines::{ Streamlines, MAX_STREAMLINE_STEPS };
+pub use crate::wind_tunnel::WindTunnel;
 
 const MIN_PRESSURE_ITERATIONS: usize = 20;
 const FPS_30: f32 = 0.0333333;
+const FPS_60: f32 = 0.0166667;
+const MAX_SUBSTEPS: usize = 8;
 
 #[repr(u8)]
-#[derive(Clone, Copy)]
-#[wasm_bindgen]
-/// Describes the scaling of a texture used by the [renderer](Renderer)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// Describes the scaling of a texture used by the [simulation](Simulation)
 pub enum Resolution {
     ONE = 1,
     TWO = 2,
@@ -25,40 +76,158 @@ pub enum Resolution {
     SIXTEEN = 16,
 }
 
-#[derive(Clone, Copy)]
-#[wasm_bindgen]
-/// Mode for the draw pass of the [renderer](Renderer)
+impl TryFrom<u8> for Resolution {
+    type Error = u8;
+
+    /// Get the resolution with the scaling `value`, or `value` itself if there is none
+    fn try_from(value: u8) -> Result<Resolution, u8> {
+        match value {
+            1 => Ok(Resolution::ONE),
+            2 => Ok(Resolution::TWO),
+            4 => Ok(Resolution::FOUR),
+            8 => Ok(Resolution::EIGHT),
+            16 => Ok(Resolution::SIXTEEN),
+            _ => Err(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Mode for the draw pass of the [simulation](Simulation)
+///
+/// The diagnostic modes from `PRESSURE` on map a scalar field onto a color map, auto-ranged every few
+/// frames, and draw the obstacles in their colors on top. Their range is the [legend](Simulation::legend).
 pub enum Mode {
     DYE,
     VELOCITY,
+    /// [Streamlines] of the velocity over the dimmed dye
+    STREAMLINES,
+    /// Pressure of the last solve, from blue for low to red for high
+    PRESSURE,
+    /// Divergence of the velocity, left over by the pressure solve, which is zero once the solve converged
+    DIVERGENCE,
+    /// Curl of the velocity, blue clockwise and red counterclockwise
+    CURL,
+    /// Speed of the fluid
+    SPEED,
+    /// The obstacles in white on black
+    OBSTACLES,
+}
+
+impl TryFrom<u8> for Mode {
+    type Error = u8;
+
+    /// Get the mode declared at the position `value`, or `value` itself if there is none
+    fn try_from(value: u8) -> Result<Mode, u8> {
+        match value {
+            0 => Ok(Mode::DYE),
+            1 => Ok(Mode::VELOCITY),
+            2 => Ok(Mode::STREAMLINES),
+            3 => Ok(Mode::PRESSURE),
+            4 => Ok(Mode::DIVERGENCE),
+            5 => Ok(Mode::CURL),
+            6 => Ok(Mode::SPEED),
+            7 => Ok(Mode::OBSTACLES),
+            _ => Err(value),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Method the [simulation](Simulation) solves the pressure equation with
+pub enum Solver {
+    /// Jacobi iterations at the full resolution of the simulation, as many as the
+    /// [iterations](SimulationConfig::iterations)
+    JACOBI,
+    /// Geometric multigrid V-cycles over a pyramid of ever coarser grids, as many as the
+    /// [cycles](SimulationConfig::cycles)
+    ///
+    /// Every cycle smooths the pressure with a few weighted Jacobi iterations on every level, so errors
+    /// spread over the whole grid vanish in a couple of cycles instead of hundreds of iterations.
+    MULTIGRID,
+    /// Red-black successive over-relaxation at the full resolution of the simulation, as many passes as the
+    /// [iterations](SimulationConfig::iterations)
+    ///
+    /// Every pass only updates every other texel in a checkerboard pattern, from the freshly updated texels
+    /// of the other color, and overshoots the update by the [omega](SimulationConfig::omega) of the
+    /// configuration. A pass costs as much as a Jacobi iteration. At the default omega a short solve needs
+    /// about a quarter fewer passes than Jacobi for the same residual. An omega close to two removes errors
+    /// spread over large parts of the grid, which Jacobi barely reduces, many times faster, but only pays
+    /// off over long solves, since it raises the residual over the first few dozen passes.
+    SOR,
+    /// Conjugate gradients preconditioned with an incomplete Cholesky factorisation, on the CPU in double
+    /// precision, until the [tolerance](SimulationConfig::tolerance) or the limit of what the pressure
+    /// texture can hold, at most [max_iterations](SimulationConfig::max_iterations)
+    ///
+    /// Meant for offline rendering with the [CPU backend](CpuBackend), where it converges to the exact
+    /// pressure in a few dozen iterations. Other backends read the pressure, the divergence and the
+    /// obstacles back and upload the pressure again every step, which is far too slow for interactive use.
+    CONJUGATE_GRADIENT,
+}
+
+impl TryFrom<u8> for Solver {
+    type Error = u8;
+
+    /// Get the solver declared at the position `value`, or `value` itself if there is none
+    fn try_from(value: u8) -> Result<Solver, u8> {
+        match value {
+            0 => Ok(Solver::JACOBI),
+            1 => Ok(Solver::MULTIGRID),
+            2 => Ok(Solver::SOR),
+            3 => Ok(Solver::CONJUGATE_GRADIENT),
+            _ => Err(value),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Scheme the [simulation](Simulation) advects the velocity and the dye with
+///
+/// The corrected schemes estimate the error of a semi-Lagrangian step by advecting its result back in
+/// time, and clamp what they correct to the texels the step interpolated from, so they do not overshoot.
+/// They take three passes instead of one.
+pub enum Advection {
+    /// A single step back along the velocity, interpolating bilinearly, which blurs a little every step
+    SEMI_LAGRANGIAN,
+    /// Corrects the result of a semi-Lagrangian step by half its error
+    MACCORMACK,
+    /// Back and forth error compensation and correction, which compensates half the error in the quantity
+    /// before the semi-Lagrangian step
+    BFECC,
 }
 
+impl TryFrom<u8> for Advection {
+    type Error = u8;
+
+    /// Get the advection scheme declared at the position `value`, or `value` itself if there is none
+    fn try_from(value: u8) -> Result<Advection, u8> {
+        match value {
+            0 => Ok(Advection::SEMI_LAGRANGIAN),
+            1 => Ok(Advection::MACCORMACK),
+            2 => Ok(Advection::BFECC),
+            _ => Err(value),
+        }
+    }
+}
+
+#[cfg(feature = "webgl")]
 #[wasm_bindgen]
 /// Renderer for the fluid simulation
 pub struct Renderer {
-    gl: WebGl2RenderingContext,
     canvas: HtmlCanvasElement,
-    sim_resolution: Resolution,
-    dye_resolution: Resolution,
-    copy_program: ShaderProgram,
-    advection_program: ShaderProgram,
-    jacobi_program: ShaderProgram,
-    divergence_program: ShaderProgram,
-    subtraction_program: ShaderProgram,
-    curl_program: ShaderProgram,
-    vorticity_program: ShaderProgram,
-    splat_program: ShaderProgram,
-    obstacle_program: ShaderProgram,
-    color_obstacle_program: ShaderProgram,
-    velocity_buffer: RWTextureBuffer,
-    pressure_buffer: RWTextureBuffer,
-    dye_buffer: RWTextureBuffer,
-    obstacle_store: TextureFramebuffer,
-    temp_store: TextureFramebuffer,
-    last_time: f32,
-    obstacle_color: [f32; 3],
+    simulation: Simulation<WebGlBackend>,
+    recorder: Option<Recorder>,
 }
 
+#[cfg(feature = "webgl")]
 #[wasm_bindgen]
 impl Renderer {
     /// Create a new renderer
@@ -104,97 +273,120 @@ impl Renderer {
 
     /// Update the renderer
     /// 
-    /// Updates the simulation according to the provided arguments.
+    /// Updates the simulation according to the provided configuration and draws it to the canvas.
     /// 
     /// # Arguments
-    /// * `pause` - Should the simulation be paused?
     /// * `time` - Current time (may be current datetime or time since the beginning of the program run but needs to be consistent)
-    /// * `mode` - Rendering [mode](Mode)
-    /// * `viscosity` - Energy loss of the fluid due to friction (>= 0) 
-    /// * `dissipation` - Colored dye fading amount (>= 0)
-    /// * `curl` - Curl amount [0, 1]
-    /// * `pressure` - Pressure coefficient for converging pressure calculation
+    /// * `config` - The [configuration](SimulationConfig) of the simulation
     /// 
     /// # Returns
     /// May return an error if something in the WebGL pipeline were to break.
     pub fn update(
         &mut self,
-        pause: bool,
         time: f32,
-        mode: Mode,
-        iterations: usize,
-        viscosity: f32,
-        dissipation: f32,
-        curl: f32,
-        pressure: f32,
+        config: &SimulationConfig,
     ) -> Result<(), JsValue> {
-        let delta_time = FPS_30.min(time - self.last_time);
-        self.last_time = time;
-
-        let (width, height) = Renderer::resolution_size(&self.canvas, self.sim_resolution);
-        let sim_resolution = [width as f32, height as f32];
-
-        // SIMULATION
-        if !pause {
-            // UPDATE VELOCITY
-            self.vorticity_confinement(
-                &sim_resolution,
-                curl,
-            )?;
-
-            Renderer::advect(
-                &self.gl,
-                &self.advection_program,
-                &sim_resolution,
-                delta_time,
-                viscosity,
-                None,
-                &mut self.velocity_buffer,
-                &self.obstacle_store,
-            )?;
-
-            self.project_velocity(
-                &sim_resolution,
-                MIN_PRESSURE_ITERATIONS.max(iterations),
-                pressure,
-            )?;
-
-            // UPDATE DYE
-            Renderer::color_obstacle(
-                &self.gl,
-                &self.color_obstacle_program,
-                &self.obstacle_store,
-                &mut self.dye_buffer,
-                &[0.0, 0.0, 0.0],
-            )?;
-
-            Renderer::advect(
-                &self.gl,
-                &self.advection_program,
-                &sim_resolution,
-                delta_time,
-                dissipation,
-                Some(&self.velocity_buffer),
-                &mut self.dye_buffer,
-                &self.obstacle_store,
-            )?;
-
-            Renderer::color_obstacle(
-                &self.gl,
-                &self.color_obstacle_program,
-                &self.obstacle_store,
-                &mut self.dye_buffer,
-                &self.obstacle_color,
-            )?;
-        }
+        self.simulation.update(
+            time,
+            config,
+        )?;
+        self.record(Event::Update {
+            time,
+            config: config.clone(),
+        });
 
         // RENDER
         // DRAW TO CANVAS
-        self.draw_pass(mode)?;
+        self.simulation.draw(config.mode())?;
 
         Ok(())
     }
 
+    /// Step the renderer
+    ///
+    /// Advances the simulation in fixed increments of the [timestep](Renderer::set_timestep), independent of
+    /// the frame rate. Nothing is drawn, call [draw](Renderer::draw) for that.
+    ///
+    /// # Arguments
+    /// * `delta_time` - Time in seconds that has passed since the last call
+    /// * `config` - The [configuration](SimulationConfig) of the simulation
+    ///
+    /// # Returns
+    /// The number of fixed steps that were taken, or an error if something in the WebGL pipeline were to break.
+    pub fn step(
+        &mut self,
+        delta_time: f32,
+        config: &SimulationConfig,
+    ) -> Result<usize, JsValue> {
+        let substeps = self.simulation.step(
+            delta_time,
+            config,
+        )?;
+
+        // The substeps depend on the accumulator, which a replay does not start out with
+        self.record(Event::Step {
+            delta_time,
+            substeps: substeps as u32,
+            config: config.clone(),
+        });
+
+        Ok(substeps)
+    }
+
+    /// Step the renderer a fixed number of times
+    ///
+    /// # Arguments
+    /// * `substeps` - Number of steps of the [timestep](Renderer::set_timestep) to take
+    /// * `config` - The [configuration](SimulationConfig) of the simulation
+    ///
+    /// # Returns
+    /// May return an error if something in the WebGL pipeline were to break.
+    pub fn step_fixed(
+        &mut self,
+        substeps: usize,
+        config: &SimulationConfig,
+    ) -> Result<(), JsValue> {
+        self.simulation.step_fixed(
+            substeps,
+            config,
+        )?;
+
+        let substeps = if config.pause() { 0 } else { substeps as u32 };
+        self.record(Event::Step {
+            delta_time: substeps as f32 * self.simulation.timestep(),
+            substeps,
+            config: config.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Set the fixed timestep
+    ///
+    /// # Arguments
+    /// * `timestep` - Length in seconds of every step taken by [step](Renderer::step) and [step_fixed](Renderer::step_fixed) (> 0)
+    pub fn set_timestep(&mut self, timestep: f32) {
+        // The simulation ignores timesteps that are not positive, so they are not replayed either
+        if timestep > 0.0 {
+            self.record(Event::SetTimestep {
+                timestep,
+            });
+        }
+
+        self.simulation.set_timestep(timestep);
+    }
+
+    /// Draw to the canvas
+    ///
+    /// # Arguments
+    /// * `mode` - Rendering [mode](Mode)
+    ///
+    /// # Returns
+    /// May return an error if something in the WebGL pipeline were to break.
+    pub fn draw(&mut self, mode: Mode) -> Result<(), JsValue> {
+        self.simulation.draw(mode)
+    }
+
     /// Resize the renderer
     /// 
     /// Resizes the textures and buffers used in the simulation.
@@ -210,56 +402,18 @@ impl Renderer {
         sim_resolution: Resolution,
         dye_resolution: Resolution,
     ) -> Result<(), JsValue> {
-        let gl = &self.gl;
-        self.sim_resolution = sim_resolution;
-        self.dye_resolution = dye_resolution;
-        
-        // SIMULATION
-        let (width, height) = Renderer::resolution_size(&self.canvas, sim_resolution);
-        self.velocity_buffer.resize(
-            gl,
-            Some(&self.copy_program),
-            width,
-            height,
+        self.simulation.resize(
+            self.canvas.width(),
+            self.canvas.height(),
+            sim_resolution,
+            dye_resolution,
         )?;
-
-        self.pressure_buffer.resize(
-            gl,
-            None,
-            width,
-            height,
-        )?;
-
-        if width != self.temp_store.width() || height != self.temp_store.height() {
-            self.temp_store.delete(gl);
-            self.temp_store = TextureFramebuffer::new(
-                gl,
-                width,
-                height,
-                WebGl2RenderingContext::LINEAR,
-            )?;
-        }
-        
-        // DYE
-        let (width, height) = Renderer::resolution_size(&self.canvas, dye_resolution);
-        self.dye_buffer.resize(
-            gl,
-            Some(&self.copy_program),
-            width,
-            height,
-        )?;
-
-        if width != self.obstacle_store.width() || height != self.obstacle_store.height() {
-            self.obstacle_store.delete(gl);
-            self.obstacle_store = TextureFramebuffer::new(
-                gl,
-                width,
-                height,
-                WebGl2RenderingContext::NEAREST,
-            )?;
-
-            self.set_obstacle(None, &[0.0, 0.0], &[0.0, 0.0, 0.0], true)?;
-        }
+        self.record(Event::Resize {
+            width: self.canvas.width(),
+            height: self.canvas.height(),
+            sim_resolution,
+            dye_resolution,
+        });
 
         Ok(())
     }
@@ -286,131 +440,452 @@ impl Renderer {
         velocity: &[f32],
         color: &[f32],
     ) -> Result<(), JsValue> {
-        let gl = &self.gl;
-        self.splat_program.bind(gl);
-
-        // APPLY FORCE
-        let resolution = self.sim_resolution as u32 as f32;
-        gl.uniform1f(
-            self.splat_program.uniforms.get(shaders::U_SCALED_RADIUS),
-            radius / (resolution * resolution),
-        );
-        gl.uniform2f(
-            self.splat_program.uniforms.get(shaders::U_POSITION),
-            position[0] / resolution,
-            position[1] / resolution,
-        );
-        gl.uniform3f(
-            self.splat_program.uniforms.get(shaders::U_COLOR),
-            velocity[0] / resolution,
-            velocity[1] / resolution,
-            0.0,
-        );
-        gl.uniform1i(
-            self.splat_program.uniforms.get(shaders::U_TEXTURE),
-            self.velocity_buffer.read().bind(gl, 0)?,
-        );
-        gl.uniform1i(
-            self.splat_program.uniforms.get(shaders::U_OBSTACLES),
-            self.obstacle_store.bind(gl, 1)?,
-        );
-
-        Renderer::blit(
-            gl,
-            Some(self.velocity_buffer.write()),
-            None,
-        );
-        self.velocity_buffer.swap();
-
-        // APPLY COLOR
-        let resolution = self.dye_resolution as u32 as f32;
-        gl.uniform1f(
-            self.splat_program.uniforms.get(shaders::U_SCALED_RADIUS),
-            radius / (resolution * resolution),
-        );
-        gl.uniform2f(
-            self.splat_program.uniforms.get(shaders::U_POSITION),
-            position[0] / resolution,
-            position[1] / resolution,
-        );
-        gl.uniform3fv_with_f32_array(
-            self.splat_program.uniforms.get(shaders::U_COLOR),
+        self.simulation.splat(
+            radius,
+            position,
+            velocity,
             color,
-        );
-        gl.uniform1i(
-            self.splat_program.uniforms.get(shaders::U_TEXTURE),
-            self.dye_buffer.read().bind(gl, 0)?,
-        );
-
-        Renderer::blit(
-            gl,
-            Some(self.dye_buffer.write()),
-            None,
-        );
-        self.dye_buffer.swap();
+        )?;
+        self.record(Event::Splat {
+            radius,
+            position: [position[0], position[1]],
+            velocity: [velocity[0], velocity[1]],
+            color: [color[0], color[1], color[2]],
+        });
 
         Ok(())
     }
 
     /// Set obstacle
     /// 
-    /// Set either a circular or square obstacle.
+    /// Set either a circular or square obstacle, replacing all other obstacles.
     /// 
     /// # Arguments
     /// * `radius` - Radius of the obstacle in pixels (in the case of a square it is half the sidelength in pixels). If this value is `undefined`, no obstacle will be set
     /// * `position` - A float array that should have two values, an x and a y position in screen coordinates
     /// * `color` - A float array that should have three values, a red, a green, and a blue color value
     /// * `is_circle` - A boolean value deciding whether the obstacle is a circle or a square
+    /// * `velocity` - A float array that should have two values, the x and y velocity of the obstacle in pixels per second. Pass the velocity of a dragged obstacle to have it push the fluid. If this value is `undefined`, the obstacle stands still
     /// 
     /// # Returns
     /// May return an error if something in the WebGL pipeline were to break.
     ///
     /// # Panics
-    /// If `position` contains fewer than two values, or if `color` contains fewer than three values.
+    /// If `position` or `velocity` contains fewer than two values, or if `color` contains fewer than three values.
     pub fn set_obstacle(
         &mut self,
         radius: Option<f32>,
         position: &[f32],
         color: &[f32],
         is_circle: bool,
+        velocity: Option<Vec<f32>>,
     ) -> Result<(), JsValue> {
-        let gl = &self.gl;
-
-        Renderer::color_obstacle(
-            gl,
-            &self.color_obstacle_program,
-            &self.obstacle_store,
-            &mut self.dye_buffer,
-            &[0.0, 0.0, 0.0],
+        self.simulation.set_obstacle(
+            radius,
+            position,
+            color,
+            is_circle,
+            velocity.as_deref(),
         )?;
+        self.record(Event::SetObstacle {
+            radius,
+            position: [position[0], position[1]],
+            color: [color[0], color[1], color[2]],
+            is_circle,
+            velocity: velocity.as_ref().map_or([0.0; 2], |velocity| [velocity[0], velocity[1]]),
+        });
+
+        Ok(())
+    }
+
+    /// Set obstacle mask
+    ///
+    /// Set a bitmap, such as a logo or text, as the obstacle. Every pixel of the mask with a coverage of
+    /// at least `threshold` is solid. Replaces all other obstacles.
+    ///
+    /// # Arguments
+    /// * `mask` - The [mask](Mask) of the obstacle
+    /// * `threshold` - Coverage from which a pixel of the mask is solid [0, 1]
+    /// * `position` - A float array that should have two values, the x and y position of the center of the mask in screen coordinates
+    /// * `size` - A float array that should have two values, the width and height of the mask in pixels
+    /// * `rotation` - Counterclockwise rotation of the mask around its center in radians
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Returns
+    /// May return an error if something in the WebGL pipeline were to break.
+    ///
+    /// # Panics
+    /// If either `position` or `size` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn set_obstacle_mask(
+        &mut self,
+        mask: &Mask,
+        threshold: f32,
+        position: &[f32],
+        size: &[f32],
+        rotation: f32,
+        color: &[f32],
+    ) -> Result<(), JsValue> {
+        self.simulation.set_obstacle_mask(
+            mask,
+            threshold,
+            position,
+            size,
+            rotation,
+            color,
+        )?;
+        self.record(Event::SetObstacleMask {
+            mask: mask.clone(),
+            threshold,
+            position: [position[0], position[1]],
+            size: [size[0], size[1]],
+            rotation,
+            color: [color[0], color[1], color[2]],
+        });
+
+        Ok(())
+    }
+
+    /// Add obstacle
+    ///
+    /// Add an obstacle on top of the obstacles that are already set.
+    ///
+    /// # Arguments
+    /// * `obstacle` - The [obstacle](Obstacle) to add
+    ///
+    /// # Returns
+    /// The id of the obstacle, or an error if something in the WebGL pipeline were to break.
+    pub fn add_obstacle(&mut self, obstacle: &Obstacle) -> Result<u32, JsValue> {
+        let id = self.simulation.add_obstacle(obstacle.clone())?;
+        self.record(Event::AddObstacle {
+            obstacle: obstacle.clone(),
+        });
+
+        Ok(id)
+    }
+
+    /// Remove obstacle
+    ///
+    /// # Arguments
+    /// * `id` - The id returned by [add_obstacle](Renderer::add_obstacle)
+    ///
+    /// # Returns
+    /// Whether an obstacle was removed, or an error if something in the WebGL pipeline were to break.
+    pub fn remove_obstacle(&mut self, id: u32) -> Result<bool, JsValue> {
+        // Removing a missing obstacle changes nothing, so it is not replayed either
+        let removed = self.simulation.remove_obstacle(id)?;
+        if removed {
+            self.record(Event::RemoveObstacle {
+                id,
+            });
+        }
+
+        Ok(removed)
+    }
+
+    /// Update obstacle
+    ///
+    /// Replace an obstacle, for example to move it, while keeping its id and its place in the drawing order.
+    ///
+    /// # Arguments
+    /// * `id` - The id returned by [add_obstacle](Renderer::add_obstacle)
+    /// * `obstacle` - The new [obstacle](Obstacle)
+    ///
+    /// # Returns
+    /// Whether an obstacle was updated, or an error if something in the WebGL pipeline were to break.
+    pub fn update_obstacle(&mut self, id: u32, obstacle: &Obstacle) -> Result<bool, JsValue> {
+        let updated = self.simulation.update_obstacle(id, obstacle.clone())?;
+        if updated {
+            self.record(Event::UpdateObstacle {
+                id,
+                obstacle: obstacle.clone(),
+            });
+        }
 
-        self.obstacle_program.bind(gl);
-
-        // SET OBSTACLE
-        let resolution = self.dye_resolution as u32 as f32;
-        gl.uniform1i(
-            self.obstacle_program.uniforms.get(shaders::U_IS_CIRCLE),
-            is_circle as i32,
-        );
-        gl.uniform1f(
-            self.obstacle_program.uniforms.get(shaders::U_SCALED_RADIUS_SQR),
-            radius.map_or(-10.0, |r| r * r / (resolution * resolution)),
-        );
-        gl.uniform2f(
-            self.obstacle_program.uniforms.get(shaders::U_POSITION),
-            position[0] / resolution,
-            position[1] / resolution,
-        );
-
-        Renderer::blit(
-            gl,
-            Some(&self.obstacle_store),
-            None,
-        );
-
-        // SET COLOR
-        self.obstacle_color = [color[0], color[1], color[2]];
+        Ok(updated)
+    }
+
+    /// Remove every obstacle
+    ///
+    /// # Returns
+    /// May return an error if something in the WebGL pipeline were to break.
+    pub fn clear_obstacles(&mut self) -> Result<(), JsValue> {
+        self.simulation.clear_obstacles()?;
+        self.record(Event::ClearObstacles);
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Let an obstacle float in the fluid as a rigid body
+    ///
+    /// The fluid pushes the obstacle around from then on, and it bounces off the closed edges of the
+    /// canvas and off other rigid bodies.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the obstacle
+    /// * `body` - The [rigid body](RigidBody), or `undefined` to pin the obstacle where it is
+    ///
+    /// # Returns
+    /// Whether there was such an obstacle.
+    pub fn set_rigid_body(&mut self, id: u32, body: Option<RigidBody>) -> bool {
+        // A body for a missing obstacle never takes effect, so it is not replayed either
+        let accepted = self.simulation.set_rigid_body(id, body);
+        if accepted {
+            self.record(Event::SetRigidBody {
+                id,
+                body,
+            });
+        }
+
+        accepted
+    }
+
+    /// The [rigid body](RigidBody) of the obstacle with the id `id`, or `undefined` if it has none
+    pub fn rigid_body(&self, id: u32) -> Option<RigidBody> {
+        self.simulation.rigid_body(id).copied()
+    }
+
+    /// Set wind tunnel
+    ///
+    /// # Arguments
+    /// * `wind_tunnel` - The [wind tunnel](WindTunnel), or `undefined` to close the edges of the canvas again
+    pub fn set_wind_tunnel(&mut self, wind_tunnel: Option<WindTunnel>) {
+        self.record(Event::SetWindTunnel {
+            wind_tunnel: wind_tunnel.clone(),
+        });
+
+        self.simulation.set_wind_tunnel(wind_tunnel);
+    }
+
+    /// Set the boundary modes of the edges of the canvas
+    ///
+    /// # Arguments
+    /// * `boundaries` - The [boundaries](Boundaries), they replace those of a [wind tunnel](Renderer::set_wind_tunnel)
+    pub fn set_boundaries(&mut self, boundaries: &Boundaries) {
+        self.record(Event::SetBoundaries {
+            boundaries: boundaries.clone(),
+        });
+
+        self.simulation.set_boundaries(boundaries.clone());
+    }
+
+    /// The current [wind tunnel](WindTunnel), if any
+    pub fn wind_tunnel(&self) -> Option<WindTunnel> {
+        self.simulation.wind_tunnel().cloned()
+    }
+
+    /// Set up a wind tunnel around an airfoil
+    ///
+    /// Replaces every obstacle with a NACA airfoil a third as long as the canvas is wide, and lets the
+    /// fluid flow past it from the left with the default streaklines.
+    ///
+    /// # Arguments
+    /// * `code` - The four-digit NACA code of the airfoil, see [Obstacle::naca](Obstacle::naca)
+    /// * `angle_of_attack` - Angle of attack of the airfoil in radians
+    /// * `inflow` - Velocity of the fluid entering through the left edge in pixels per second
+    ///
+    /// # Returns
+    /// The id of the airfoil, or an error if `code` is not a valid NACA code or if something in the WebGL
+    /// pipeline were to break.
+    pub fn wind_tunnel_preset(
+        &mut self,
+        code: u16,
+        angle_of_attack: f32,
+        inflow: f32,
+    ) -> Result<u32, JsValue> {
+        let width = self.simulation.width() as f32;
+        let height = self.simulation.height() as f32;
+        let airfoil = Obstacle::naca(
+            code,
+            width / 3.0,
+            &[0.35 * width, 0.5 * height],
+            angle_of_attack,
+            &[0.6, 0.6, 0.6],
+        )?;
+
+        let mut wind_tunnel = WindTunnel::default();
+        wind_tunnel.set_inflow(inflow);
+
+        self.clear_obstacles()?;
+        let id = self.add_obstacle(&airfoil)?;
+        self.set_wind_tunnel(Some(wind_tunnel));
+
+        Ok(id)
+    }
+
+    /// Turn the measurement of the forces on the obstacles on or off
+    ///
+    /// While it is on, every step ends with integrating the pressure and the viscous force over the
+    /// boundary of every obstacle, which costs a read back from the GPU per step.
+    pub fn set_measure_forces(&mut self, measure_forces: bool) {
+        self.simulation.set_measure_forces(measure_forces);
+    }
+
+    /// The [forces](ObstacleForce) on every obstacle during the last step, in drawing order
+    pub fn forces(&self) -> Vec<ObstacleForce> {
+        self.simulation.forces().to_vec()
+    }
+
+    /// The [force](ObstacleForce) on the obstacle with the id `id` during the last step, or `undefined` if there is none
+    pub fn obstacle_force(&self, id: u32) -> Option<ObstacleForce> {
+        self.simulation.obstacle_force(id).copied()
+    }
+
+    /// Keep the measured forces of the last `capacity` steps, see [force_history](Renderer::force_history)
+    pub fn set_force_history(&mut self, capacity: usize) {
+        self.simulation.set_force_history(capacity);
+    }
+
+    /// The time series of the total force on the obstacle with the id `id`
+    ///
+    /// # Returns
+    /// A float array with three values per stored step: the simulated time in seconds, and the x and y force.
+    pub fn force_history(&self, id: u32) -> Vec<f32> {
+        self.simulation
+            .force_history(id)
+            .into_iter()
+            .flat_map(|(time, force)| [time, force[0], force[1]])
+            .collect()
+    }
+
+    /// Set the tracer particles, or remove them with `undefined`
+    ///
+    /// [Particles] only show the flow, so they are neither recorded nor saved with the state.
+    ///
+    /// # Returns
+    /// May return an error if something in the WebGL pipeline were to break.
+    pub fn set_particles(&mut self, particles: Option<Particles>) -> Result<(), JsValue> {
+        self.simulation.set_particles(particles)
+    }
+
+    /// The current [particles](Particles), if any
+    pub fn particles(&self) -> Option<Particles> {
+        self.simulation.particles().cloned()
+    }
+
+    /// The range of the field drawn by the last frame in a diagnostic [mode](Mode)
+    ///
+    /// # Returns
+    /// A float array with the value at the low and the value at the high end of the color map, or an empty
+    /// array if the mode is not a diagnostic one. See [Simulation::legend] for the units.
+    pub fn legend(&self) -> Vec<f32> {
+        self.simulation.legend().map_or_else(Vec::new, |legend| legend.to_vec())
+    }
+
+    /// Number of iterations, or V-cycles of the [multigrid](Solver::MULTIGRID) solver, of the pressure solve of
+    /// the last step
+    pub fn pressure_iterations(&self) -> usize {
+        self.simulation.pressure_iterations()
+    }
+
+    /// Residual left over by the pressure solve of the last step, or `undefined` unless the solve ran to a
+    /// [tolerance](SimulationConfig::tolerance)
+    pub fn pressure_residual(&self) -> Option<f32> {
+        self.simulation.pressure_residual()
+    }
+
+    /// Set how the [streamlines](Mode::STREAMLINES) mode traces the flow
+    ///
+    /// # Returns
+    /// May return an error if something in the WebGL pipeline were to break.
+    pub fn set_streamlines(&mut self, streamlines: &Streamlines) -> Result<(), JsValue> {
+        self.simulation.set_streamlines(streamlines.clone())
+    }
+
+    /// The current [streamlines](Streamlines)
+    pub fn streamlines(&self) -> Streamlines {
+        self.simulation.streamlines().clone()
+    }
+
+    /// Save the state of the simulation
+    ///
+    /// Reads back the velocity, pressure, dye and obstacle textures so that the current flow can be
+    /// restored later on with [load_state](Renderer::load_state).
+    ///
+    /// # Arguments
+    /// * `precision` - [Precision] of the saved textures
+    /// * `compress` - A boolean value deciding whether the saved textures are compressed
+    ///
+    /// # Returns
+    /// The state in the snapshot format, or an error if something in the WebGL pipeline were to break.
+    pub fn save_state(
+        &mut self,
+        precision: Precision,
+        compress: bool,
+    ) -> Result<Vec<u8>, JsValue> {
+        Ok(self.simulation.snapshot()?.to_bytes(precision, compress))
+    }
+
+    /// Load a state saved with [save_state](Renderer::save_state)
+    ///
+    /// A state saved at another canvas size is resampled to fit the canvas.
+    ///
+    /// # Arguments
+    /// * `bytes` - The state in the snapshot format
+    ///
+    /// # Returns
+    /// An error if the bytes are not a valid state, or if something in the WebGL pipeline were to break.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        let snapshot = Snapshot::from_bytes(bytes)?;
+        self.simulation.restore(&snapshot)
+    }
+
+    /// Start recording
+    ///
+    /// Every following [update](Renderer::update), [resize](Renderer::resize), [splat](Renderer::splat),
+    /// [set_obstacle](Renderer::set_obstacle), [set_obstacle_mask](Renderer::set_obstacle_mask),
+    /// obstacle list, rigid body, wind tunnel and boundary call is logged, starting with the current size
+    /// of the simulation. A recording that is already running is discarded.
+    pub fn start_recording(&mut self) {
+        let mut recorder = Recorder::new();
+        recorder.record(Event::Resize {
+            width: self.simulation.width(),
+            height: self.simulation.height(),
+            sim_resolution: self.simulation.sim_resolution(),
+            dye_resolution: self.simulation.dye_resolution(),
+        });
+
+        self.recorder = Some(recorder);
+    }
+
+    /// Stop recording
+    ///
+    /// # Returns
+    /// The recording in the recording format, or `undefined` if nothing was being recorded.
+    pub fn stop_recording(&mut self) -> Option<Vec<u8>> {
+        self.recorder.take().map(|recorder| recorder.finish().to_bytes())
+    }
+
+    /// Whether a recording was [started](Renderer::start_recording) and not stopped yet
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Play the next frame of a recording
+    ///
+    /// Drives the simulation with the events of `player` up to and including the next update, and draws
+    /// the result to the canvas. Played events are not recorded.
+    ///
+    /// # Arguments
+    /// * `player` - The [player](Player) of the recording
+    ///
+    /// # Returns
+    /// Whether a frame was played, `false` once the recording has ended, or an error if something in the WebGL pipeline were to break.
+    pub fn play(&mut self, player: &mut Player) -> Result<bool, JsValue> {
+        match player.play_frame(&mut self.simulation)? {
+            Some(config) => {
+                self.simulation.draw(config.mode())?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(feature = "webgl")]
+impl Renderer {
+    fn record(&mut self, event: Event) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(event);
+        }
+    }
+}
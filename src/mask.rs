@@ -0,0 +1,185 @@
+use std::fmt;
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+use crate::bytes::Reader;
+use crate::obstacle::DecodeError;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Error returned when the data of a [mask](Mask) does not match its size
+pub enum MaskError {
+    /// A mask without pixels
+    Empty,
+    /// Data with this many values, not one (or four, for `RGBA`) per pixel
+    DataLength(usize),
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskError::Empty => write!(f, "a mask needs at least one pixel"),
+            MaskError::DataLength(len) => write!(f, "{} values do not match the size of the mask", len),
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
+#[cfg(feature = "webgl")]
+impl From<MaskError> for JsValue {
+    fn from(error: MaskError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// A bitmap that can be placed in the [simulation](crate::Simulation) as an obstacle
+///
+/// Stores one coverage value per pixel, row by row from the top, just like the images it is made from.
+pub struct Mask {
+    width: u32,
+    height: u32,
+    coverage: Vec<u8>,
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl Mask {
+    /// Create a mask from an alpha mask
+    ///
+    /// # Arguments
+    /// * `width` - Width of the mask in pixels
+    /// * `height` - Height of the mask in pixels
+    /// * `alpha` - One coverage value per pixel, row by row from the top
+    ///
+    /// # Errors
+    /// If the mask is empty, or if `alpha` does not contain exactly one value per pixel.
+    pub fn from_alpha(width: u32, height: u32, alpha: &[u8]) -> Result<Mask, MaskError> {
+        Mask::check(width, height, 1, alpha.len())?;
+
+        Ok(Mask {
+            width,
+            height,
+            coverage: alpha.to_vec(),
+        })
+    }
+
+    /// Create a mask from the alpha channel of an `RGBA` image
+    ///
+    /// # Arguments
+    /// * `width` - Width of the image in pixels
+    /// * `height` - Height of the image in pixels
+    /// * `rgba` - Four values per pixel, row by row from the top
+    ///
+    /// # Errors
+    /// If the image is empty, or if `rgba` does not contain exactly four values per pixel.
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Mask, MaskError> {
+        Mask::check(width, height, 4, rgba.len())?;
+        let alpha: Vec<u8> = rgba.chunks_exact(4).map(|pixel| pixel[3]).collect();
+
+        Mask::from_alpha(width, height, &alpha)
+    }
+
+    /// Create a mask from the alpha channel of an `ImageData`
+    ///
+    /// # Errors
+    /// If the image is empty.
+    #[cfg(feature = "webgl")]
+    pub fn from_image(image: &web_sys::ImageData) -> Result<Mask, MaskError> {
+        Mask::from_rgba(image.width(), image.height(), &image.data().0)
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl Mask {
+    /// Check that `len` values are `channels` values for every pixel of a non-empty mask
+    fn check(width: u32, height: u32, channels: usize, len: usize) -> Result<(), MaskError> {
+        if width == 0 || height == 0 {
+            return Err(MaskError::Empty);
+        }
+
+        match (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(channels)) {
+            Some(expected) if expected == len => Ok(()),
+            _ => Err(MaskError::DataLength(len)),
+        }
+    }
+
+    /// Coverage values of the mask, one per pixel, row by row from the top
+    pub fn coverage(&self) -> &[u8] {
+        &self.coverage
+    }
+
+    /// `RGBA` texels of the mask with the coverage in `[0, 1]` in the red channel, row by row from the bottom
+    pub(crate) fn texels(&self) -> Vec<f32> {
+        self.coverage
+            .chunks_exact(self.width as usize)
+            .rev()
+            .flatten()
+            .flat_map(|&value| [value as f32 / 255.0, 0.0, 0.0, 0.0])
+            .collect()
+    }
+
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.width.to_le_bytes());
+        bytes.extend(self.height.to_le_bytes());
+        bytes.extend(&self.coverage);
+    }
+
+    pub(crate) fn read(reader: &mut Reader) -> Result<Mask, DecodeError> {
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|&len| len > 0)
+            .ok_or(DecodeError::InvalidObstacle)?;
+        if reader.remaining().len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+
+        let mask = Mask {
+            width,
+            height,
+            coverage: reader.remaining()[..len].to_vec(),
+        };
+        reader.skip(len);
+
+        Ok(mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_data_that_does_not_match_the_size() {
+        assert_eq!(Mask::from_alpha(4, 4, &[0; 3]), Err(MaskError::DataLength(3)));
+        assert_eq!(Mask::from_alpha(0, 4, &[]), Err(MaskError::Empty));
+        assert_eq!(Mask::from_rgba(2, 2, &[0; 15]), Err(MaskError::DataLength(15)));
+        assert_eq!(Mask::from_rgba(u32::MAX, u32::MAX, &[0; 4]), Err(MaskError::DataLength(4)));
+
+        let mask = Mask::from_rgba(2, 1, &[0, 0, 0, 7, 0, 0, 0, 9]).unwrap();
+        assert_eq!(mask.coverage(), [7, 9]);
+    }
+
+    #[test]
+    fn read_rejects_sizes_without_pixels() {
+        for (width, height) in [(0, 3), (3, 0), (u32::MAX, u32::MAX)] {
+            let mut bytes = Vec::new();
+            bytes.extend(u32::to_le_bytes(width));
+            bytes.extend(u32::to_le_bytes(height));
+            bytes.extend([0; 16]);
+
+            let result = Mask::read(&mut Reader::new(&bytes));
+            assert!(result.is_err(), "{} x {} was read", width, height);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Geometric multigrid solve of the pressure equation, see [Solver::MULTIGRID](crate::Solver::MULTIGRID)
+//!
+//! The pressure equation is solved on a pyramid of grids, every one half the size of the one below it. A
+//! V-cycle smooths the pressure with a few weighted Jacobi iterations, restricts what is left of the
+//! divergence (the residual) onto the next coarser level, solves for a correction there the same way,
+//! and adds the interpolated correction back before smoothing again. Errors that are smooth on one level
+//! alternate from texel to texel on a coarser one, where Jacobi removes them quickly.
+
+use crate::backend::{ BackendTexture, Pass, PingPong, SimulationBackend };
+use crate::boundary::BoundaryMode;
+use crate::grid::{ Filter, Wrap };
+
+/// Levels stop halving once their shorter side is at most this many texels
+const COARSEST_SIZE: u32 = 8;
+/// Weighted Jacobi iterations on every level before and after the correction from the coarser level
+const SMOOTHING_ITERATIONS: usize = 2;
+/// Weighted Jacobi iterations on the coarsest level, which stand in for solving it exactly
+const COARSEST_ITERATIONS: usize = 32;
+/// Weight of the smoothing iterations, which damps the errors that alternate from texel to texel
+const SMOOTHING_WEIGHT: f32 = 0.8;
+
+/// A level of the pyramid, coarser than the grid of the simulation
+struct Level<T> {
+    /// Correction of the next finer level
+    x: PingPong<T>,
+    /// Residual of the next finer level, restricted onto this one
+    b: T,
+    /// Obstacles of the next finer level, restricted onto this one
+    obstacles: T,
+}
+
+/// Texture pyramid of a multigrid solve on a grid of a fixed size
+pub(crate) struct Multigrid<T> {
+    size: [u32; 2],
+    /// Residual of every level but the coarsest, starting with the grid of the simulation
+    residuals: Vec<T>,
+    levels: Vec<Level<T>>,
+}
+
+impl<T: BackendTexture> Multigrid<T> {
+    /// Create the pyramid below a grid of `width` x `height` texels
+    pub fn new<B: SimulationBackend<Texture = T>>(
+        backend: &mut B,
+        width: u32,
+        height: u32,
+        wrap: [Wrap; 2],
+    ) -> Result<Multigrid<T>, B::Error> {
+        let mut multigrid = Multigrid {
+            size: [width, height],
+            residuals: Vec::new(),
+            levels: Vec::new(),
+        };
+
+        let (mut width, mut height) = (width, height);
+        while width.min(height) > COARSEST_SIZE {
+            multigrid.residuals.push(backend.create_texture(width, height, Filter::Linear)?);
+
+            width = width.div_ceil(2);
+            height = height.div_ceil(2);
+            multigrid.levels.push(Level {
+                x: PingPong::new(backend, width, height, Some(Filter::Linear))?,
+                b: backend.create_texture(width, height, Filter::Linear)?,
+                obstacles: backend.create_texture(width, height, Filter::Nearest)?,
+            });
+        }
+        multigrid.set_wrap(backend, wrap);
+
+        Ok(multigrid)
+    }
+
+    /// Size of the grid the pyramid was created for
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Set the wrapping of every texture along the x and the y axis
+    pub fn set_wrap<B: SimulationBackend<Texture = T>>(&mut self, backend: &mut B, wrap: [Wrap; 2]) {
+        for residual in &mut self.residuals {
+            backend.set_wrap(residual, wrap);
+        }
+        for level in &mut self.levels {
+            level.x.set_wrap(backend, wrap);
+            backend.set_wrap(&mut level.b, wrap);
+            backend.set_wrap(&mut level.obstacles, wrap);
+        }
+    }
+
+    /// Delete every texture
+    pub fn delete<B: SimulationBackend<Texture = T>>(self, backend: &mut B) {
+        for residual in self.residuals {
+            backend.delete_texture(residual);
+        }
+        for level in self.levels {
+            level.x.delete(backend);
+            backend.delete_texture(level.b);
+            backend.delete_texture(level.obstacles);
+        }
+    }
+
+    /// Improve the pressure `x` with `cycles` V-cycles
+    ///
+    /// # Arguments
+    /// * `boundary` - Boundary mode of every edge, in the order left, right, bottom, top
+    /// * `alpha` - The `alpha` of the [pressure solver](Pass::PressureSolver) on the grid of the simulation
+    /// * `x` - The pressure, which is also the initial guess
+    /// * `b` - The divergence of the velocity
+    /// * `obstacles` - The obstacles, of any size
+    #[allow(clippy::too_many_arguments)]
+    pub fn solve<B: SimulationBackend<Texture = T>>(
+        &mut self,
+        backend: &mut B,
+        cycles: usize,
+        boundary: [BoundaryMode; 4],
+        alpha: f32,
+        x: &mut PingPong<T>,
+        b: &T,
+        obstacles: &T,
+    ) -> Result<(), B::Error> {
+        // OBSTACLES
+        // A coarse texel is solid where most of the finer texels it covers are
+        let mut finer = obstacles;
+        for level in &mut self.levels {
+            backend.dispatch(
+                Pass::Restrict {
+                    resolution: [level.obstacles.width() as f32, level.obstacles.height() as f32],
+                    texture: finer,
+                },
+                &mut level.obstacles,
+            )?;
+            finer = &level.obstacles;
+        }
+
+        // CYCLES
+        for _ in 0..cycles {
+            v_cycle(
+                backend,
+                &mut self.levels,
+                &mut self.residuals,
+                boundary,
+                alpha,
+                self.size.map(|size| size as f32),
+                x,
+                b,
+                obstacles,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Solve a level for `x`, recursing into the coarser `levels` below it
+#[allow(clippy::too_many_arguments)]
+fn v_cycle<B: SimulationBackend>(
+    backend: &mut B,
+    levels: &mut [Level<B::Texture>],
+    residuals: &mut [B::Texture],
+    boundary: [BoundaryMode; 4],
+    alpha: f32,
+    resolution: [f32; 2],
+    x: &mut PingPong<B::Texture>,
+    b: &B::Texture,
+    obstacles: &B::Texture,
+) -> Result<(), B::Error> {
+    let (Some((level, coarser)), Some((residual, coarser_residuals))) = (levels.split_first_mut(), residuals.split_first_mut()) else {
+        return smooth(backend, COARSEST_ITERATIONS, boundary, alpha, resolution, x, b, obstacles);
+    };
+
+    smooth(backend, SMOOTHING_ITERATIONS, boundary, alpha, resolution, x, b, obstacles)?;
+
+    // RESTRICTION
+    backend.dispatch(
+        Pass::Residual {
+            boundary,
+            alpha,
+            resolution,
+            x: x.read(),
+            b,
+            obstacles,
+        },
+        residual,
+    )?;
+
+    let coarse_resolution = [level.b.width() as f32, level.b.height() as f32];
+    backend.dispatch(
+        Pass::Restrict {
+            resolution: coarse_resolution,
+            texture: residual,
+        },
+        &mut level.b,
+    )?;
+
+    // CORRECTION
+    // Starts from zero, and the texels are twice as large, which makes `alpha` four times as large
+    let (read, write) = level.x.split();
+    backend.dispatch(
+        Pass::Copy {
+            factor: 0.0,
+            offset: 0.0,
+            texture: read,
+        },
+        write,
+    )?;
+    level.x.swap();
+
+    v_cycle(
+        backend,
+        coarser,
+        coarser_residuals,
+        boundary,
+        4.0 * alpha,
+        coarse_resolution,
+        &mut level.x,
+        &level.b,
+        &level.obstacles,
+    )?;
+
+    // PROLONGATION
+    let (read, write) = x.split();
+    backend.dispatch(
+        Pass::Prolong {
+            x: read,
+            correction: level.x.read(),
+        },
+        write,
+    )?;
+    x.swap();
+
+    smooth(backend, SMOOTHING_ITERATIONS, boundary, alpha, resolution, x, b, obstacles)
+}
+
+/// Run weighted Jacobi iterations on `x`
+#[allow(clippy::too_many_arguments)]
+fn smooth<B: SimulationBackend>(
+    backend: &mut B,
+    iterations: usize,
+    boundary: [BoundaryMode; 4],
+    alpha: f32,
+    resolution: [f32; 2],
+    x: &mut PingPong<B::Texture>,
+    b: &B::Texture,
+    obstacles: &B::Texture,
+) -> Result<(), B::Error> {
+    for _ in 0..iterations {
+        let (read, write) = x.split();
+        backend.dispatch(
+            Pass::PressureSolver {
+                boundary,
+                alpha,
+                r_beta: 0.25,
+                omega: SMOOTHING_WEIGHT,
+                resolution,
+                x: read,
+                b,
+                obstacles,
+            },
+            write,
+        )?;
+        x.swap();
+    }
+
+    Ok(())
+}
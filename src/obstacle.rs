@@ -0,0 +1,680 @@
+use std::fmt;
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+use crate::bytes::{ Reader, UnexpectedEnd };
+use crate::mask::Mask;
+
+/// Largest number of vertices of a [polygon](Shape::Polygon)
+pub const MAX_POLYGON_VERTICES: usize = 1024;
+/// Number of segments along each side of a [NACA airfoil](Obstacle::naca)
+const NACA_SAMPLES: usize = 80;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Shape of an [obstacle](Obstacle), in pixels of the domain
+///
+/// Every shape is centered on the position of the obstacle and turns with its rotation, so a rotated
+/// box is a [rectangle](Shape::Rectangle) with a rotation.
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+    /// A square with sides of twice `half_size`
+    Square {
+        half_size: f32,
+    },
+    /// A bitmap, solid where the coverage of the mask is at least `threshold`
+    Mask {
+        mask: Mask,
+        threshold: f32,
+        size: [f32; 2],
+    },
+    /// A box of `size[0]` by `size[1]`
+    Rectangle {
+        size: [f32; 2],
+    },
+    /// A line of `length` along the x axis, widened by `radius` to every side
+    Capsule {
+        length: f32,
+        radius: f32,
+    },
+    /// A ring between `inner_radius` and `outer_radius`
+    Annulus {
+        inner_radius: f32,
+        outer_radius: f32,
+    },
+    /// A closed polygon through `vertices`, relative to the position of the obstacle
+    ///
+    /// The polygon may be concave and is filled with the even-odd rule. Polygons with fewer than three
+    /// or more than [MAX_POLYGON_VERTICES] vertices are not drawn.
+    Polygon {
+        vertices: Vec<[f32; 2]>,
+    },
+}
+
+impl Shape {
+    pub fn kind(&self) -> ShapeKind {
+        match self {
+            Shape::Circle { .. } => ShapeKind::CIRCLE,
+            Shape::Square { .. } => ShapeKind::SQUARE,
+            Shape::Mask { .. } => ShapeKind::MASK,
+            Shape::Rectangle { .. } => ShapeKind::RECTANGLE,
+            Shape::Capsule { .. } => ShapeKind::CAPSULE,
+            Shape::Annulus { .. } => ShapeKind::ANNULUS,
+            Shape::Polygon { .. } => ShapeKind::POLYGON,
+        }
+    }
+
+    /// Area of the shape and its polar moment of area around the position of the obstacle
+    pub(crate) fn area_moment(&self) -> (f32, f32) {
+        use std::f32::consts::PI;
+
+        match self {
+            Shape::Circle { radius } => (PI * radius.powi(2), 0.5 * PI * radius.powi(4)),
+            Shape::Square { half_size } => {
+                let side = 2.0 * half_size;
+                (side * side, side.powi(4) / 6.0)
+            }
+            Shape::Rectangle { size } => {
+                let area = size[0] * size[1];
+                (area, area * (size[0] * size[0] + size[1] * size[1]) / 12.0)
+            }
+            Shape::Capsule { length, radius } => {
+                // The two caps make up a circle, counted as if its halves sat at the ends of the line
+                let (box_area, box_moment) = Shape::Rectangle { size: [*length, 2.0 * radius] }.area_moment();
+                let (cap_area, cap_moment) = Shape::Circle { radius: *radius }.area_moment();
+                (box_area + cap_area, box_moment + cap_moment + cap_area * (0.5 * length).powi(2))
+            }
+            Shape::Annulus { inner_radius, outer_radius } => (
+                PI * (outer_radius.powi(2) - inner_radius.powi(2)),
+                0.5 * PI * (outer_radius.powi(4) - inner_radius.powi(4)),
+            ),
+            Shape::Polygon { vertices } => {
+                let mut area = 0.0;
+                let mut moment = 0.0;
+                for (a, b) in vertices.iter().zip(vertices.iter().cycle().skip(1)) {
+                    let cross = a[0] * b[1] - b[0] * a[1];
+                    area += 0.5 * cross;
+                    moment += cross * (a[0] * a[0] + a[0] * b[0] + b[0] * b[0] + a[1] * a[1] + a[1] * b[1] + b[1] * b[1]) / 12.0;
+                }
+
+                (area.abs(), moment.abs())
+            }
+            Shape::Mask { mask, threshold, size } => {
+                let texel = [size[0] / mask.width() as f32, size[1] / mask.height() as f32];
+                let texel_area = texel[0] * texel[1];
+                let mut area = 0.0;
+                let mut moment = 0.0;
+                for (i, coverage) in mask.coverage().iter().enumerate() {
+                    if (*coverage as f32 / 255.0) < *threshold {
+                        continue;
+                    }
+
+                    let x = ((i as u32 % mask.width()) as f32 + 0.5) * texel[0] - 0.5 * size[0];
+                    let y = ((i as u32 / mask.width()) as f32 + 0.5) * texel[1] - 0.5 * size[1];
+                    area += texel_area;
+                    moment += texel_area * (x * x + y * y + (texel[0] * texel[0] + texel[1] * texel[1]) / 12.0);
+                }
+
+                (area, moment)
+            }
+        }
+    }
+
+    /// Points on the outline of the shape, relative to the position of the obstacle and before rotation
+    ///
+    /// Every point of the shape lies inside of the convex hull of the outline, which is what collisions
+    /// are checked against.
+    pub(crate) fn outline(&self) -> Vec<[f32; 2]> {
+        use std::f32::consts::{ FRAC_PI_2, PI, TAU };
+        const ARC_POINTS: usize = 16;
+        let arc = |center: [f32; 2], radius: f32, start: f32, span: f32| {
+            (0..=ARC_POINTS).map(move |i| {
+                let angle = start + span * i as f32 / ARC_POINTS as f32;
+                [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+            })
+        };
+
+        let corners = |half: [f32; 2]| vec![
+            [-half[0], -half[1]],
+            [half[0], -half[1]],
+            [half[0], half[1]],
+            [-half[0], half[1]],
+        ];
+
+        match self {
+            Shape::Circle { radius } | Shape::Annulus { outer_radius: radius, .. } => arc([0.0; 2], *radius, 0.0, TAU).collect(),
+            Shape::Square { half_size } => corners([*half_size; 2]),
+            Shape::Rectangle { size } => corners([0.5 * size[0], 0.5 * size[1]]),
+            Shape::Mask { size, .. } => corners([0.5 * size[0], 0.5 * size[1]]),
+            Shape::Capsule { length, radius } => arc([0.5 * length, 0.0], *radius, -FRAC_PI_2, PI)
+                .chain(arc([-0.5 * length, 0.0], *radius, FRAC_PI_2, PI))
+                .collect(),
+            Shape::Polygon { vertices } => vertices.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Error returned when the arguments of an [obstacle](Obstacle) do not describe a shape
+pub enum ShapeError {
+    /// A polygon with this many vertices, fewer than three or more than [MAX_POLYGON_VERTICES]
+    PolygonVertices(usize),
+    /// A NACA code with more than four digits or a thickness of zero
+    NacaCode(u16),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::PolygonVertices(count) => write!(
+                f,
+                "a polygon needs between 3 and {} vertices, got {}",
+                MAX_POLYGON_VERTICES,
+                count,
+            ),
+            ShapeError::NacaCode(code) => write!(f, "`{}` is not a four-digit NACA code with a thickness", code),
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+#[cfg(feature = "webgl")]
+impl From<ShapeError> for JsValue {
+    fn from(error: ShapeError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// The kind of [shape](Shape) of an [obstacle](Obstacle)
+pub enum ShapeKind {
+    CIRCLE,
+    SQUARE,
+    RECTANGLE,
+    CAPSULE,
+    ANNULUS,
+    POLYGON,
+    MASK,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// A solid in the [simulation](crate::Simulation)
+///
+/// An obstacle is a [shape](Shape) placed in the domain by its position and rotation. Fluid does not
+/// enter it and dye inside of it takes on the color of the obstacle.
+///
+/// The fluid along the boundary of an obstacle moves with its linear and angular velocity, so that a
+/// moving obstacle pushes the fluid around. The velocity does not move the obstacle itself, callers move
+/// it by [updating](crate::Simulation::update_obstacle) its position and rotation.
+///
+/// # Format
+/// [Snapshots](crate::Snapshot) and [recordings](crate::Recording) encode an obstacle as a shape tag
+/// (`u8`) and the parameters of the shape, followed by the position (`f32` x 2), the rotation (`f32`),
+/// the color (`f32` x 3), the linear velocity (`f32` x 2) and the angular velocity (`f32`). All values
+/// are little endian.
+///
+/// | Tag | Shape  | Parameters                                                                          |
+/// |-----|--------|-------------------------------------------------------------------------------------|
+/// | 0   | Circle | `radius` (`f32`)                                                                    |
+/// | 1   | Square | `half_size` (`f32`)                                                                 |
+/// | 2   | Mask   | `width`, `height` (`u32`), coverage (`u8` per pixel), `threshold`, `size` x 2 (`f32`) |
+/// | 3   | Rectangle | `size` x 2 (`f32`)                                                               |
+/// | 4   | Capsule | `length`, `radius` (`f32`)                                                         |
+/// | 5   | Annulus | `inner_radius`, `outer_radius` (`f32`)                                             |
+/// | 6   | Polygon | vertex count (`u32`), `vertices` x 2 (`f32`)                                       |
+pub struct Obstacle {
+    pub(crate) shape: Shape,
+    pub(crate) position: [f32; 2],
+    pub(crate) rotation: f32,
+    pub(crate) color: [f32; 3],
+    pub(crate) linear_velocity: [f32; 2],
+    pub(crate) angular_velocity: f32,
+}
+
+impl Obstacle {
+    /// Create a new obstacle that stands still
+    ///
+    /// # Arguments
+    /// * `shape` - The [shape](Shape) of the obstacle
+    /// * `position` - The x and y position of the center of the obstacle in domain coordinates
+    /// * `rotation` - Counterclockwise rotation of the obstacle around its center in radians
+    /// * `color` - A red, a green, and a blue color value
+    pub fn new(
+        shape: Shape,
+        position: [f32; 2],
+        rotation: f32,
+        color: [f32; 3],
+    ) -> Obstacle {
+        Obstacle {
+            shape,
+            position,
+            rotation,
+            color,
+            linear_velocity: [0.0; 2],
+            angular_velocity: 0.0,
+        }
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+    }
+
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        let floats: Vec<f32> = match &self.shape {
+            Shape::Circle { radius } => {
+                bytes.push(0);
+                vec![*radius]
+            }
+            Shape::Square { half_size } => {
+                bytes.push(1);
+                vec![*half_size]
+            }
+            Shape::Mask { mask, threshold, size } => {
+                bytes.push(2);
+                mask.write(bytes);
+                vec![*threshold, size[0], size[1]]
+            }
+            Shape::Rectangle { size } => {
+                bytes.push(3);
+                size.to_vec()
+            }
+            Shape::Capsule { length, radius } => {
+                bytes.push(4);
+                vec![*length, *radius]
+            }
+            Shape::Annulus { inner_radius, outer_radius } => {
+                bytes.push(5);
+                vec![*inner_radius, *outer_radius]
+            }
+            Shape::Polygon { vertices } => {
+                bytes.push(6);
+                bytes.extend((vertices.len() as u32).to_le_bytes());
+                vertices.concat()
+            }
+        };
+
+        let values = floats.into_iter()
+            .chain(self.position)
+            .chain([self.rotation])
+            .chain(self.color)
+            .chain(self.linear_velocity)
+            .chain([self.angular_velocity]);
+
+        for value in values {
+            bytes.extend(value.to_le_bytes());
+        }
+    }
+
+    /// Read an obstacle written by [write](Obstacle::write)
+    pub(crate) fn read(reader: &mut Reader) -> Result<Obstacle, DecodeError> {
+        let shape = match reader.u8()? {
+            0 => Shape::Circle {
+                radius: reader.f32()?,
+            },
+            1 => Shape::Square {
+                half_size: reader.f32()?,
+            },
+            2 => Shape::Mask {
+                mask: Mask::read(reader)?,
+                threshold: reader.f32()?,
+                size: [reader.f32()?, reader.f32()?],
+            },
+            3 => Shape::Rectangle {
+                size: [reader.f32()?, reader.f32()?],
+            },
+            4 => Shape::Capsule {
+                length: reader.f32()?,
+                radius: reader.f32()?,
+            },
+            5 => Shape::Annulus {
+                inner_radius: reader.f32()?,
+                outer_radius: reader.f32()?,
+            },
+            6 => {
+                let count = reader.u32()? as usize;
+                if !(3..=MAX_POLYGON_VERTICES).contains(&count) {
+                    return Err(DecodeError::InvalidObstacle);
+                }
+
+                let mut vertices = Vec::with_capacity(count);
+                for _ in 0..count {
+                    vertices.push([reader.f32()?, reader.f32()?]);
+                }
+
+                Shape::Polygon {
+                    vertices,
+                }
+            }
+            _ => return Err(DecodeError::InvalidObstacle),
+        };
+
+        Ok(Obstacle {
+            shape,
+            position: [reader.f32()?, reader.f32()?],
+            rotation: reader.f32()?,
+            color: [reader.f32()?, reader.f32()?, reader.f32()?],
+            linear_velocity: [reader.f32()?, reader.f32()?],
+            angular_velocity: reader.f32()?,
+        })
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl Obstacle {
+    /// Create a circular obstacle
+    ///
+    /// # Arguments
+    /// * `radius` - Radius of the obstacle in pixels
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn circle(radius: f32, position: &[f32], color: &[f32]) -> Obstacle {
+        Obstacle::new(
+            Shape::Circle { radius },
+            [position[0], position[1]],
+            0.0,
+            [color[0], color[1], color[2]],
+        )
+    }
+
+    /// Create a square obstacle
+    ///
+    /// # Arguments
+    /// * `half_size` - Half the sidelength of the obstacle in pixels
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `rotation` - Counterclockwise rotation of the obstacle around its center in radians
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn square(half_size: f32, position: &[f32], rotation: f32, color: &[f32]) -> Obstacle {
+        Obstacle::new(
+            Shape::Square { half_size },
+            [position[0], position[1]],
+            rotation,
+            [color[0], color[1], color[2]],
+        )
+    }
+
+    /// Create a rectangular obstacle
+    ///
+    /// # Arguments
+    /// * `size` - A float array that should have two values, the width and height of the obstacle in pixels
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `rotation` - Counterclockwise rotation of the obstacle around its center in radians, 0 for an axis-aligned box
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If either `size` or `position` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn rectangle(size: &[f32], position: &[f32], rotation: f32, color: &[f32]) -> Obstacle {
+        Obstacle::new(
+            Shape::Rectangle {
+                size: [size[0], size[1]],
+            },
+            [position[0], position[1]],
+            rotation,
+            [color[0], color[1], color[2]],
+        )
+    }
+
+    /// Create a capsule shaped obstacle, a rectangle with rounded ends
+    ///
+    /// # Arguments
+    /// * `length` - Distance between the centers of the two rounded ends in pixels
+    /// * `radius` - Radius of the rounded ends, and half the width of the capsule, in pixels
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `rotation` - Counterclockwise rotation of the obstacle around its center in radians, 0 for a horizontal capsule
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn capsule(length: f32, radius: f32, position: &[f32], rotation: f32, color: &[f32]) -> Obstacle {
+        Obstacle::new(
+            Shape::Capsule { length, radius },
+            [position[0], position[1]],
+            rotation,
+            [color[0], color[1], color[2]],
+        )
+    }
+
+    /// Create a ring shaped obstacle
+    ///
+    /// # Arguments
+    /// * `inner_radius` - Radius of the hole in the ring in pixels
+    /// * `outer_radius` - Radius of the ring in pixels
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn annulus(inner_radius: f32, outer_radius: f32, position: &[f32], color: &[f32]) -> Obstacle {
+        Obstacle::new(
+            Shape::Annulus { inner_radius, outer_radius },
+            [position[0], position[1]],
+            0.0,
+            [color[0], color[1], color[2]],
+        )
+    }
+
+    /// Create a polygonal obstacle
+    ///
+    /// The polygon may be convex or concave, it is filled with the even-odd rule.
+    ///
+    /// # Arguments
+    /// * `vertices` - A float array of x and y pairs, the vertices of the polygon in pixels relative to `position`
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `rotation` - Counterclockwise rotation of the obstacle around `position` in radians
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Returns
+    /// The obstacle, or an error if `vertices` holds fewer than three or more than `MAX_POLYGON_VERTICES`
+    /// vertices.
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn polygon(vertices: &[f32], position: &[f32], rotation: f32, color: &[f32]) -> Result<Obstacle, ShapeError> {
+        let vertices: Vec<[f32; 2]> = vertices
+            .chunks_exact(2)
+            .map(|vertex| [vertex[0], vertex[1]])
+            .collect();
+        if !(3..=MAX_POLYGON_VERTICES).contains(&vertices.len()) {
+            return Err(ShapeError::PolygonVertices(vertices.len()));
+        }
+
+        Ok(Obstacle::new(
+            Shape::Polygon { vertices },
+            [position[0], position[1]],
+            rotation,
+            [color[0], color[1], color[2]],
+        ))
+    }
+
+    /// Create an airfoil shaped obstacle from a four-digit NACA code
+    ///
+    /// The first digit of the code is the maximum camber in percent of the chord, the second one the
+    /// position of the maximum camber in tenths of the chord, and the last two the maximum thickness in
+    /// percent of the chord. A code of 2412 makes a NACA 2412 airfoil, 12 a symmetric NACA 0012 one.
+    /// The leading edge points to the left.
+    ///
+    /// # Arguments
+    /// * `code` - The four-digit NACA code
+    /// * `chord` - Length of the airfoil from the leading to the trailing edge in pixels
+    /// * `position` - A float array that should have two values, the x and y position of the middle of the chord in domain coordinates
+    /// * `angle_of_attack` - Angle between the chord and a flow from the left in radians, positive values raise the leading edge
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Returns
+    /// The obstacle, or an error if `code` has more than four digits or a thickness of zero.
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn naca(
+        code: u16,
+        chord: f32,
+        position: &[f32],
+        angle_of_attack: f32,
+        color: &[f32],
+    ) -> Result<Obstacle, ShapeError> {
+        if code > 9999 || code.is_multiple_of(100) {
+            return Err(ShapeError::NacaCode(code));
+        }
+        let camber = (code / 1000) as f32 / 100.0;
+        let camber_position = (code / 100 % 10) as f32 / 10.0;
+        let thickness = (code % 100) as f32 / 100.0;
+
+        // SURFACE
+        // Cosine spacing puts more vertices around the strongly curved leading edge
+        let samples = NACA_SAMPLES;
+        let mut upper = Vec::with_capacity(samples + 1);
+        let mut lower = Vec::with_capacity(samples + 1);
+        for i in 0..=samples {
+            let x = 0.5 * (1.0 - (std::f32::consts::PI * i as f32 / samples as f32).cos());
+            let half_thickness = 5.0 * thickness * (
+                0.2969 * x.sqrt() - 0.1260 * x - 0.3516 * x.powi(2) + 0.2843 * x.powi(3) - 0.1036 * x.powi(4)
+            );
+
+            let (camber_line, slope) = if camber == 0.0 || camber_position == 0.0 {
+                (0.0, 0.0)
+            } else if x < camber_position {
+                let scale = camber / camber_position.powi(2);
+                (scale * (2.0 * camber_position * x - x * x), 2.0 * scale * (camber_position - x))
+            } else {
+                let scale = camber / (1.0 - camber_position).powi(2);
+                (scale * (1.0 - 2.0 * camber_position + 2.0 * camber_position * x - x * x), 2.0 * scale * (camber_position - x))
+            };
+
+            let (sin, cos) = slope.atan().sin_cos();
+            upper.push([x - half_thickness * sin, camber_line + half_thickness * cos]);
+            lower.push([x + half_thickness * sin, camber_line - half_thickness * cos]);
+        }
+
+        // From the trailing edge over the top to the leading edge and back along the bottom
+        let vertices: Vec<[f32; 2]> = upper.iter()
+            .rev()
+            .chain(&lower[1..samples])
+            .map(|vertex| [(vertex[0] - 0.5) * chord, vertex[1] * chord])
+            .collect();
+
+        Ok(Obstacle::new(
+            Shape::Polygon { vertices },
+            [position[0], position[1]],
+            -angle_of_attack,
+            [color[0], color[1], color[2]],
+        ))
+    }
+
+    /// Create an obstacle from a bitmap
+    ///
+    /// # Arguments
+    /// * `mask` - The [mask](Mask) of the obstacle
+    /// * `threshold` - Coverage from which a pixel of the mask is solid [0, 1]
+    /// * `position` - A float array that should have two values, the x and y position of the center of the mask in domain coordinates
+    /// * `size` - A float array that should have two values, the width and height of the mask in pixels
+    /// * `rotation` - Counterclockwise rotation of the mask around its center in radians
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If either `position` or `size` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn mask(
+        mask: &Mask,
+        threshold: f32,
+        position: &[f32],
+        size: &[f32],
+        rotation: f32,
+        color: &[f32],
+    ) -> Obstacle {
+        Obstacle::new(
+            Shape::Mask {
+                mask: mask.clone(),
+                threshold,
+                size: [size[0], size[1]],
+            },
+            [position[0], position[1]],
+            rotation,
+            [color[0], color[1], color[2]],
+        )
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn kind(&self) -> ShapeKind {
+        self.shape.kind()
+    }
+
+    /// Position of the center of the obstacle in domain coordinates
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn position(&self) -> Vec<f32> {
+        self.position.to_vec()
+    }
+
+    /// # Panics
+    /// If `position` contains fewer than two values.
+    pub fn set_position(&mut self, position: &[f32]) {
+        self.position = [position[0], position[1]];
+    }
+
+    /// Counterclockwise rotation of the obstacle in radians
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn color(&self) -> Vec<f32> {
+        self.color.to_vec()
+    }
+
+    /// # Panics
+    /// If `color` contains fewer than three values.
+    pub fn set_color(&mut self, color: &[f32]) {
+        self.color = [color[0], color[1], color[2]];
+    }
+
+    /// Velocity of the center of the obstacle in pixels per second
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn linear_velocity(&self) -> Vec<f32> {
+        self.linear_velocity.to_vec()
+    }
+
+    /// # Panics
+    /// If `velocity` contains fewer than two values.
+    pub fn set_linear_velocity(&mut self, velocity: &[f32]) {
+        self.linear_velocity = [velocity[0], velocity[1]];
+    }
+
+    /// Counterclockwise angular velocity of the obstacle around its center in radians per second
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn angular_velocity(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    pub fn set_angular_velocity(&mut self, velocity: f32) {
+        self.angular_velocity = velocity;
+    }
+}
+
+/// Error returned when an encoded [obstacle](Obstacle), [mask](Mask) or [rigid body](crate::RigidBody) can
+/// not be read
+pub(crate) enum DecodeError {
+    UnexpectedEnd,
+    InvalidObstacle,
+    InvalidRigidBody,
+}
+
+impl From<UnexpectedEnd> for DecodeError {
+    fn from(_: UnexpectedEnd) -> DecodeError {
+        DecodeError::UnexpectedEnd
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+
+/// Largest number of [particles](Particles)
+pub const MAX_PARTICLES: u32 = 1 << 22;
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// How [particles](Particles) are colored
+pub enum ParticleColor {
+    /// Every particle has the color of the particles
+    SOLID,
+    /// Particles go from blue over green and yellow to red as they speed up to the maximum speed
+    SPEED,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// Tracer particles that are carried along by the fluid and drawn over the dye
+///
+/// Particles follow the velocity of the fluid and show fine details of the flow that the dye blurs at a
+/// low dye resolution. Every particle lives for a random time around the lifetime and is then emitted
+/// again, inside of a random emitter or anywhere in the domain if there are no emitters. Particles that
+/// leave the domain or enter an obstacle are emitted again right away.
+///
+/// Particles only show the flow and do not change it, so they are neither part of
+/// [snapshots](crate::Snapshot) nor of [recordings](crate::Recording).
+pub struct Particles {
+    count: u32,
+    lifetime: f32,
+    size: f32,
+    streak: f32,
+    color_mode: ParticleColor,
+    color: [f32; 3],
+    max_speed: f32,
+    emitters: Vec<[f32; 3]>,
+}
+
+impl Default for Particles {
+    fn default() -> Particles {
+        Particles {
+            count: 1 << 16,
+            lifetime: 4.0,
+            size: 1.0,
+            streak: 0.0,
+            color_mode: ParticleColor::SPEED,
+            color: [1.0, 1.0, 1.0],
+            max_speed: 500.0,
+            emitters: Vec::new(),
+        }
+    }
+}
+
+impl Particles {
+    /// Width and height of the texture holding the particles, at least as many texels as particles
+    pub(crate) fn texture_size(&self) -> (u32, u32) {
+        let width = (self.count as f32).sqrt().ceil().max(1.0) as u32;
+        (width, self.count.div_ceil(width).max(1))
+    }
+
+    /// Every emitter as its x and y position and its radius, in pixels
+    pub fn emitters(&self) -> &[[f32; 3]] {
+        &self.emitters
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl Particles {
+    /// Create particles with a lifetime of 4 seconds, colored by speed and emitted anywhere in the domain
+    ///
+    /// # Arguments
+    /// * `count` - Number of particles, at most [MAX_PARTICLES]
+    #[cfg_attr(feature = "webgl", wasm_bindgen(constructor))]
+    pub fn new(count: u32) -> Particles {
+        let mut particles = Particles::default();
+        particles.set_count(count);
+
+        particles
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Set the number of particles, at most [MAX_PARTICLES]
+    pub fn set_count(&mut self, count: u32) {
+        self.count = count.min(MAX_PARTICLES);
+    }
+
+    /// Average time a particle lives before it is emitted again, in seconds
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn lifetime(&self) -> f32 {
+        self.lifetime
+    }
+
+    pub fn set_lifetime(&mut self, lifetime: f32) {
+        self.lifetime = lifetime.max(0.0);
+    }
+
+    /// Size of a particle in pixels
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size.max(0.0);
+    }
+
+    /// Length of the streak behind every particle, as the time in seconds the particle takes to cover it,
+    /// 0 to draw particles as points
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn streak(&self) -> f32 {
+        self.streak
+    }
+
+    pub fn set_streak(&mut self, streak: f32) {
+        self.streak = streak.max(0.0);
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn color_mode(&self) -> ParticleColor {
+        self.color_mode
+    }
+
+    pub fn set_color_mode(&mut self, color_mode: ParticleColor) {
+        self.color_mode = color_mode;
+    }
+
+    /// Color of the particles when they are [solid](ParticleColor::SOLID)
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn color(&self) -> Vec<f32> {
+        self.color.to_vec()
+    }
+
+    /// # Panics
+    /// If `color` contains fewer than three values.
+    pub fn set_color(&mut self, color: &[f32]) {
+        self.color = [color[0], color[1], color[2]];
+    }
+
+    /// Speed in pixels per second at which particles colored [by speed](ParticleColor::SPEED) turn red
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    pub fn set_max_speed(&mut self, max_speed: f32) {
+        self.max_speed = max_speed.max(f32::EPSILON);
+    }
+
+    /// Add a round area that particles are emitted from
+    ///
+    /// # Arguments
+    /// * `position` - A float array that should have two values, the x and y position of the center in pixels
+    /// * `radius` - Radius of the area in pixels
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values.
+    pub fn add_emitter(&mut self, position: &[f32], radius: f32) {
+        self.emitters.push([position[0], position[1], radius.max(0.0)]);
+    }
+
+    /// Remove every emitter, so that particles are emitted anywhere in the domain
+    pub fn clear_emitters(&mut self) {
+        self.emitters.clear();
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn emitter_count(&self) -> u32 {
+        self.emitters.len() as u32
+    }
+}
@@ -0,0 +1,862 @@
+//! Recording and replay of the input of a [simulation](crate::Simulation)
+//!
+//! A [recorder](Recorder) logs every update, step, resize, splat and change of the timestep, the
+//! obstacles, the rigid bodies, the wind tunnel or the boundaries, and a [player](Player) drives a
+//! simulation from the resulting [recording](Recording) again.
+//!
+//! Steps are replayed with the number of fixed steps they took when they were recorded, so a session
+//! driven by [step](crate::Simulation::step) and [step_fixed](crate::Simulation::step_fixed) is
+//! reproduced exactly on the same backend, given that the simulation starts from the same state (a new
+//! simulation, or a restored [snapshot](crate::Snapshot)). The length of an
+//! [update](crate::Simulation::update) depends on the frame rate: replayed with its recorded time it
+//! takes a step of the same length, replayed with the fixed timestep it takes one step of the timestep
+//! instead, which only approximates the session. Calls that are no [events](Event), like changing the
+//! particles or restoring a snapshot, are not recorded.
+
+use std::fmt;
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+use crate::backend::SimulationBackend;
+use crate::boundary::Boundaries;
+use crate::bytes::{ Reader, UnexpectedEnd };
+use crate::config::{ ConfigError, SimulationConfig };
+use crate::mask::Mask;
+use crate::obstacle::{ DecodeError, Obstacle };
+use crate::rigid_body::RigidBody;
+use crate::simulation::Simulation;
+use crate::wind_tunnel::WindTunnel;
+use crate::{ Advection, Mode, Resolution, Solver };
+
+/// Magic bytes at the start of every recording
+const MAGIC: [u8; 8] = *b"FLUIDREC";
+/// Version of the format written by [to_bytes](Recording::to_bytes)
+pub const RECORDING_VERSION: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Error returned when bytes can not be read as a [recording](Recording)
+pub enum RecordingError {
+    /// The bytes do not start with the magic bytes of a recording
+    InvalidMagic,
+    /// The recording was written by another version of the format
+    UnsupportedVersion(u16),
+    /// The bytes end in the middle of an event
+    UnexpectedEnd,
+    /// An event with an unknown tag
+    InvalidEvent(u8),
+    /// A scaling that is not a valid [Resolution](Resolution)
+    InvalidResolution(u8),
+    /// A value that is not a valid [Mode](Mode)
+    InvalidMode(u8),
+    /// A value that is not a valid [Solver](Solver)
+    InvalidSolver(u8),
+    /// A value that is not a valid [Advection](Advection)
+    InvalidAdvection(u8),
+    /// An update with an invalid [configuration](SimulationConfig)
+    InvalidConfig(ConfigError),
+    /// An obstacle that can not be read
+    InvalidObstacle,
+    /// A rigid body without a positive mass and inertia, or with a restitution outside of [0, 1]
+    InvalidRigidBody,
+    /// A value that is not a valid [BoundaryMode](crate::BoundaryMode)
+    InvalidBoundary(u8),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::InvalidMagic => write!(f, "not a recording"),
+            RecordingError::UnsupportedVersion(version) => write!(
+                f,
+                "recording version {} is not supported, expected {}",
+                version,
+                RECORDING_VERSION,
+            ),
+            RecordingError::UnexpectedEnd => write!(f, "recording ends unexpectedly"),
+            RecordingError::InvalidEvent(tag) => write!(f, "invalid event {} in recording", tag),
+            RecordingError::InvalidResolution(value) => write!(f, "invalid resolution {} in recording", value),
+            RecordingError::InvalidMode(value) => write!(f, "invalid mode {} in recording", value),
+            RecordingError::InvalidSolver(value) => write!(f, "invalid solver {} in recording", value),
+            RecordingError::InvalidAdvection(value) => write!(f, "invalid advection {} in recording", value),
+            RecordingError::InvalidConfig(error) => write!(f, "invalid configuration in recording: {}", error),
+            RecordingError::InvalidObstacle => write!(f, "invalid obstacle in recording"),
+            RecordingError::InvalidRigidBody => write!(f, "invalid rigid body in recording"),
+            RecordingError::InvalidBoundary(value) => write!(f, "invalid boundary mode {} in recording", value),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl From<UnexpectedEnd> for RecordingError {
+    fn from(_: UnexpectedEnd) -> RecordingError {
+        RecordingError::UnexpectedEnd
+    }
+}
+
+impl From<ConfigError> for RecordingError {
+    fn from(error: ConfigError) -> RecordingError {
+        RecordingError::InvalidConfig(error)
+    }
+}
+
+impl From<DecodeError> for RecordingError {
+    fn from(error: DecodeError) -> RecordingError {
+        match error {
+            DecodeError::UnexpectedEnd => RecordingError::UnexpectedEnd,
+            DecodeError::InvalidObstacle => RecordingError::InvalidObstacle,
+            DecodeError::InvalidRigidBody => RecordingError::InvalidRigidBody,
+        }
+    }
+}
+
+#[cfg(feature = "webgl")]
+impl From<RecordingError> for JsValue {
+    fn from(error: RecordingError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A call on the [simulation](Simulation), together with its arguments
+pub enum Event {
+    Update {
+        time: f32,
+        config: SimulationConfig,
+    },
+    /// A call of [step](Simulation::step) with its `delta_time`, or of [step_fixed](Simulation::step_fixed)
+    /// with the time its steps took, and the number of fixed steps that were taken
+    Step {
+        delta_time: f32,
+        substeps: u32,
+        config: SimulationConfig,
+    },
+    SetTimestep {
+        timestep: f32,
+    },
+    Resize {
+        width: u32,
+        height: u32,
+        sim_resolution: Resolution,
+        dye_resolution: Resolution,
+    },
+    Splat {
+        radius: f32,
+        position: [f32; 2],
+        velocity: [f32; 2],
+        color: [f32; 3],
+    },
+    SetObstacle {
+        radius: Option<f32>,
+        position: [f32; 2],
+        color: [f32; 3],
+        is_circle: bool,
+        velocity: [f32; 2],
+    },
+    SetObstacleMask {
+        mask: Mask,
+        threshold: f32,
+        position: [f32; 2],
+        size: [f32; 2],
+        rotation: f32,
+        color: [f32; 3],
+    },
+    AddObstacle {
+        obstacle: Obstacle,
+    },
+    RemoveObstacle {
+        id: u32,
+    },
+    UpdateObstacle {
+        id: u32,
+        obstacle: Obstacle,
+    },
+    ClearObstacles,
+    SetWindTunnel {
+        wind_tunnel: Option<WindTunnel>,
+    },
+    SetBoundaries {
+        boundaries: Boundaries,
+    },
+    SetRigidBody {
+        id: u32,
+        body: Option<RigidBody>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An [event](Event) of a [recording](Recording)
+pub struct Record {
+    /// Time of the update, or of the last update plus the time of the steps since for every other event
+    pub timestamp: f32,
+    pub event: Event,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Events logged by a [recorder](Recorder), in the order they happened
+///
+/// # Format
+/// Encoded recordings are little-endian and start with the magic bytes `FLUIDREC` and the format
+/// version (`u16`, currently 1). Then follow the events up to the end of the bytes, every one as a tag
+/// (`u8`), its timestamp (`f32`) and its arguments:
+///
+/// | Tag | Event        | Arguments                                                                      |
+/// |-----|--------------|--------------------------------------------------------------------------------|
+/// | 0   | Update       | `pause` (`u8`), `mode` (`u8`, the [mode](Mode) in the order it is declared), `solver` (`u8`, the [solver](Solver) in the order it is declared), `iterations`, `cycles` (`u32`), `omega`, `tolerance` (`f32`), `max_iterations` (`u32`), `advection` (`u8`, the [advection](Advection) in the order it is declared), `viscosity`, `dissipation`, `curl`, `pressure` (`f32`) |
+/// | 1   | Resize       | `width`, `height` (`u32`), `sim_resolution`, `dye_resolution` (`u8`)            |
+/// | 2   | Splat        | `radius`, `position` x 2, `velocity` x 2, `color` x 3 (`f32`)                   |
+/// | 3   | SetObstacle  | flags (`u8`, 1 = has radius, 2 = is circle), `radius`, `position` x 2, `color` x 3, `velocity` x 2 (`f32`) |
+/// | 4   | SetObstacleMask | `width`, `height` (`u32`), coverage (`u8` per pixel), `threshold`, `position` x 2, `size` x 2, `rotation`, `color` x 3 (`f32`) |
+/// | 5   | AddObstacle  | the [encoded obstacle](Obstacle#format)                                          |
+/// | 6   | RemoveObstacle | `id` (`u32`)                                                                 |
+/// | 7   | UpdateObstacle | `id` (`u32`), the [encoded obstacle](Obstacle#format)                        |
+/// | 8   | ClearObstacles | none                                                                         |
+/// | 9   | SetWindTunnel | flag (`u8`, 1 = has wind tunnel), the [encoded wind tunnel](WindTunnel#format) if set |
+/// | 10  | SetBoundaries | the [encoded boundaries](Boundaries#format)                                   |
+/// | 11  | SetRigidBody | `id` (`u32`), flag (`u8`, 1 = has body), the [encoded rigid body](RigidBody#format) if set |
+/// | 12  | Step         | `delta_time` (`f32`), `substeps` (`u32`), the configuration as in Update        |
+/// | 13  | SetTimestep  | `timestep` (`f32`)                                                             |
+///
+/// The timestamp of an update is its time, every other event carries the time of the last update plus
+/// the `delta_time` of every step since.
+/// Readers reject every version but their own.
+pub struct Recording {
+    records: Vec<Record>,
+}
+
+impl Recording {
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Time between the first and the last update of the recording
+    pub fn duration(&self) -> f32 {
+        let mut times = self.records.iter().filter_map(|record| match record.event {
+            Event::Update { time, .. } => Some(time),
+            _ => None,
+        });
+
+        match times.next() {
+            Some(first) => times.next_back().map_or(0.0, |last| last - first),
+            None => 0.0,
+        }
+    }
+
+    /// Encode the recording in the [recording format](Recording#format)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::from(MAGIC);
+        bytes.extend(RECORDING_VERSION.to_le_bytes());
+
+        for record in &self.records {
+            let floats: Vec<f32> = match &record.event {
+                Event::Update { config, .. } => {
+                    bytes.push(0);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    write_config(config, &mut bytes);
+                    vec![]
+                }
+                Event::Step { delta_time, substeps, config } => {
+                    bytes.push(12);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    bytes.extend(delta_time.to_le_bytes());
+                    bytes.extend(substeps.to_le_bytes());
+                    write_config(config, &mut bytes);
+                    vec![]
+                }
+                Event::SetTimestep { timestep } => {
+                    bytes.push(13);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    vec![*timestep]
+                }
+                Event::Resize { width, height, sim_resolution, dye_resolution } => {
+                    bytes.push(1);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    bytes.extend(width.to_le_bytes());
+                    bytes.extend(height.to_le_bytes());
+                    bytes.extend([*sim_resolution as u8, *dye_resolution as u8]);
+                    vec![]
+                }
+                Event::Splat { radius, position, velocity, color } => {
+                    bytes.push(2);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    [&[*radius][..], position, velocity, color].concat()
+                }
+                Event::SetObstacle { radius, position, color, is_circle, velocity } => {
+                    bytes.push(3);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    bytes.push(radius.is_some() as u8 | (*is_circle as u8) << 1);
+                    [&[radius.unwrap_or(0.0)][..], position, color, velocity].concat()
+                }
+                Event::SetObstacleMask { mask, threshold, position, size, rotation, color } => {
+                    bytes.push(4);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    mask.write(&mut bytes);
+                    [&[*threshold][..], position, size, &[*rotation], color].concat()
+                }
+                Event::AddObstacle { obstacle } => {
+                    bytes.push(5);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    obstacle.write(&mut bytes);
+                    vec![]
+                }
+                Event::RemoveObstacle { id } => {
+                    bytes.push(6);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    bytes.extend(id.to_le_bytes());
+                    vec![]
+                }
+                Event::UpdateObstacle { id, obstacle } => {
+                    bytes.push(7);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    bytes.extend(id.to_le_bytes());
+                    obstacle.write(&mut bytes);
+                    vec![]
+                }
+                Event::ClearObstacles => {
+                    bytes.push(8);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    vec![]
+                }
+                Event::SetWindTunnel { wind_tunnel } => {
+                    bytes.push(9);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    bytes.push(wind_tunnel.is_some() as u8);
+                    if let Some(wind_tunnel) = wind_tunnel {
+                        wind_tunnel.write(&mut bytes);
+                    }
+                    vec![]
+                }
+                Event::SetBoundaries { boundaries } => {
+                    bytes.push(10);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    boundaries.write(&mut bytes);
+                    vec![]
+                }
+                Event::SetRigidBody { id, body } => {
+                    bytes.push(11);
+                    bytes.extend(record.timestamp.to_le_bytes());
+                    bytes.extend(id.to_le_bytes());
+                    bytes.push(body.is_some() as u8);
+                    if let Some(body) = body {
+                        body.write(&mut bytes);
+                    }
+                    vec![]
+                }
+            };
+
+            for value in floats {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Decode a recording in the [recording format](Recording#format)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Recording, RecordingError> {
+        let mut reader = Reader::new(bytes);
+        if reader.take::<8>().ok() != Some(MAGIC) {
+            return Err(RecordingError::InvalidMagic);
+        }
+
+        let version = reader.u16()?;
+        if version != RECORDING_VERSION {
+            return Err(RecordingError::UnsupportedVersion(version));
+        }
+
+        let mut records = Vec::new();
+        while !reader.remaining().is_empty() {
+            let tag = reader.u8()?;
+            let timestamp = reader.f32()?;
+            let event = match tag {
+                0 => Event::Update {
+                    time: timestamp,
+                    config: read_config(&mut reader)?,
+                },
+                1 => Event::Resize {
+                    width: reader.u32()?,
+                    height: reader.u32()?,
+                    sim_resolution: Resolution::try_from(reader.u8()?).map_err(RecordingError::InvalidResolution)?,
+                    dye_resolution: Resolution::try_from(reader.u8()?).map_err(RecordingError::InvalidResolution)?,
+                },
+                2 => Event::Splat {
+                    radius: reader.f32()?,
+                    position: [reader.f32()?, reader.f32()?],
+                    velocity: [reader.f32()?, reader.f32()?],
+                    color: [reader.f32()?, reader.f32()?, reader.f32()?],
+                },
+                3 => {
+                    let flags = reader.u8()?;
+                    let radius = reader.f32()?;
+
+                    Event::SetObstacle {
+                        radius: if flags & 1 != 0 { Some(radius) } else { None },
+                        position: [reader.f32()?, reader.f32()?],
+                        color: [reader.f32()?, reader.f32()?, reader.f32()?],
+                        is_circle: flags & 2 != 0,
+                        velocity: [reader.f32()?, reader.f32()?],
+                    }
+                }
+                4 => Event::SetObstacleMask {
+                    mask: Mask::read(&mut reader)?,
+                    threshold: reader.f32()?,
+                    position: [reader.f32()?, reader.f32()?],
+                    size: [reader.f32()?, reader.f32()?],
+                    rotation: reader.f32()?,
+                    color: [reader.f32()?, reader.f32()?, reader.f32()?],
+                },
+                5 => Event::AddObstacle {
+                    obstacle: Obstacle::read(&mut reader)?,
+                },
+                6 => Event::RemoveObstacle {
+                    id: reader.u32()?,
+                },
+                7 => Event::UpdateObstacle {
+                    id: reader.u32()?,
+                    obstacle: Obstacle::read(&mut reader)?,
+                },
+                8 => Event::ClearObstacles,
+                9 => Event::SetWindTunnel {
+                    wind_tunnel: match reader.u8()? != 0 {
+                        true => Some(WindTunnel::read(&mut reader)?),
+                        false => None,
+                    },
+                },
+                10 => Event::SetBoundaries {
+                    boundaries: Boundaries::read(&mut reader, RecordingError::InvalidBoundary)?,
+                },
+                11 => Event::SetRigidBody {
+                    id: reader.u32()?,
+                    body: match reader.u8()? != 0 {
+                        true => Some(RigidBody::read(&mut reader)?),
+                        false => None,
+                    },
+                },
+                12 => Event::Step {
+                    delta_time: reader.f32()?,
+                    substeps: reader.u32()?,
+                    config: read_config(&mut reader)?,
+                },
+                13 => Event::SetTimestep {
+                    timestep: reader.f32()?,
+                },
+                _ => return Err(RecordingError::InvalidEvent(tag)),
+            };
+
+            records.push(Record {
+                timestamp,
+                event,
+            });
+        }
+
+        Ok(Recording {
+            records,
+        })
+    }
+}
+
+/// Write `config` as the arguments of an update in the [recording format](Recording#format)
+fn write_config(config: &SimulationConfig, bytes: &mut Vec<u8>) {
+    bytes.push(config.pause() as u8);
+    bytes.push(config.mode() as u8);
+    bytes.push(config.solver() as u8);
+    bytes.extend((config.iterations() as u32).to_le_bytes());
+    bytes.extend((config.cycles() as u32).to_le_bytes());
+    bytes.extend(config.omega().to_le_bytes());
+    bytes.extend(config.tolerance().to_le_bytes());
+    bytes.extend((config.max_iterations() as u32).to_le_bytes());
+    bytes.push(config.advection() as u8);
+    for value in [config.viscosity(), config.dissipation(), config.curl(), config.pressure()] {
+        bytes.extend(value.to_le_bytes());
+    }
+}
+
+/// Read a configuration written by [write_config]
+fn read_config(reader: &mut Reader) -> Result<SimulationConfig, RecordingError> {
+    let mut config = SimulationConfig::default();
+    config.set_pause(reader.u8()? != 0);
+    config.set_mode(Mode::try_from(reader.u8()?).map_err(RecordingError::InvalidMode)?);
+    config.set_solver(Solver::try_from(reader.u8()?).map_err(RecordingError::InvalidSolver)?);
+    config.set_iterations(reader.u32()? as usize);
+    config.set_cycles(reader.u32()? as usize);
+    config.set_omega(reader.f32()?)?;
+    config.set_tolerance(reader.f32()?)?;
+    config.set_max_iterations(reader.u32()? as usize);
+    config.set_advection(Advection::try_from(reader.u8()?).map_err(RecordingError::InvalidAdvection)?);
+    config.set_viscosity(reader.f32()?)?;
+    config.set_dissipation(reader.f32()?)?;
+    config.set_curl(reader.f32()?)?;
+    config.set_pressure(reader.f32()?)?;
+
+    Ok(config)
+}
+
+#[derive(Clone, Debug, Default)]
+/// Logs the [events](Event) of a session into a [recording](Recording)
+pub struct Recorder {
+    recording: Recording,
+    time: f32,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    /// Log `event`
+    ///
+    /// Updates are timestamped with their own time, every other event with the time of the last update
+    /// plus the `delta_time` of the steps since, including their own.
+    pub fn record(&mut self, event: Event) {
+        match event {
+            Event::Update { time, .. } => self.time = time,
+            Event::Step { delta_time, .. } => self.time += delta_time,
+            _ => {}
+        }
+
+        self.recording.records.push(Record {
+            timestamp: self.time,
+            event,
+        });
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Stop recording and get the logged events
+    pub fn finish(self) -> Recording {
+        self.recording
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// Drives a [simulation](Simulation) from a [recording](Recording), one update or step at a time
+pub struct Player {
+    recording: Recording,
+    position: usize,
+    fixed_timestep: bool,
+    looping: bool,
+    time_offset: f32,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Player {
+        Player {
+            recording,
+            position: 0,
+            fixed_timestep: true,
+            looping: false,
+            time_offset: 0.0,
+        }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Play the next frame of the recording
+    ///
+    /// Applies every event up to and including the next update or step. Steps take as many
+    /// [fixed steps](Simulation::step_fixed) as they took when they were recorded. With the fixed
+    /// timestep, updates step the simulation once, otherwise they are replayed with their recorded time.
+    ///
+    /// # Returns
+    /// The [configuration](SimulationConfig) of the played update or step, or `None` once the recording has
+    /// ended.
+    pub fn play_frame<B: SimulationBackend>(
+        &mut self,
+        simulation: &mut Simulation<B>,
+    ) -> Result<Option<SimulationConfig>, B::Error> {
+        if self.looping && self.is_finished() {
+            self.rewind();
+        }
+
+        while let Some(record) = self.recording.records.get(self.position) {
+            self.position += 1;
+            match &record.event {
+                Event::Update { time, config } => {
+                    if self.fixed_timestep {
+                        simulation.step_fixed(1, config)?;
+                    } else {
+                        simulation.update(time + self.time_offset, config)?;
+                    }
+
+                    return Ok(Some(config.clone()));
+                }
+                Event::Step { substeps, config, .. } => {
+                    simulation.step_fixed(*substeps as usize, config)?;
+
+                    return Ok(Some(config.clone()));
+                }
+                Event::SetTimestep { timestep } => simulation.set_timestep(*timestep),
+                Event::Resize { width, height, sim_resolution, dye_resolution } => simulation.resize(
+                    *width,
+                    *height,
+                    *sim_resolution,
+                    *dye_resolution,
+                )?,
+                Event::Splat { radius, position, velocity, color } => simulation.splat(
+                    *radius,
+                    position,
+                    velocity,
+                    color,
+                )?,
+                Event::SetObstacle { radius, position, color, is_circle, velocity } => simulation.set_obstacle(
+                    *radius,
+                    position,
+                    color,
+                    *is_circle,
+                    Some(velocity),
+                )?,
+                Event::SetObstacleMask { mask, threshold, position, size, rotation, color } => simulation.set_obstacle_mask(
+                    mask,
+                    *threshold,
+                    position,
+                    size,
+                    *rotation,
+                    color,
+                )?,
+                Event::AddObstacle { obstacle } => {
+                    simulation.add_obstacle(obstacle.clone())?;
+                }
+                Event::RemoveObstacle { id } => {
+                    simulation.remove_obstacle(*id)?;
+                }
+                Event::UpdateObstacle { id, obstacle } => {
+                    simulation.update_obstacle(*id, obstacle.clone())?;
+                }
+                Event::ClearObstacles => simulation.clear_obstacles()?,
+                Event::SetWindTunnel { wind_tunnel } => simulation.set_wind_tunnel(wind_tunnel.clone()),
+                Event::SetBoundaries { boundaries } => simulation.set_boundaries(boundaries.clone()),
+                Event::SetRigidBody { id, body } => {
+                    simulation.set_rigid_body(*id, *body);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl Player {
+    /// Create a player for a recording in the [recording format](Recording#format)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Player, RecordingError> {
+        Ok(Player::new(Recording::from_bytes(bytes)?))
+    }
+
+    /// Should updates step the simulation with its fixed timestep (the default), rather than with their recorded time?
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: bool) {
+        self.fixed_timestep = fixed_timestep;
+    }
+
+    /// Should the recording start over once it has ended?
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Start the recording over
+    ///
+    /// Recorded times keep increasing, so that a replay with recorded times does not step backwards.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+        self.time_offset += self.recording.duration();
+    }
+
+    /// Have all events of the recording been played?
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.recording.records.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::{ BoundaryMode, Edge };
+    use crate::cpu_backend::{ CpuBackend, CpuSolver };
+
+    fn recording() -> Recording {
+        let mut config = SimulationConfig::new();
+        config.set_mode(Mode::CURL);
+        config.set_solver(Solver::CONJUGATE_GRADIENT);
+        config.set_cycles(3);
+        config.set_omega(1.5).unwrap();
+        config.set_tolerance(0.01).unwrap();
+        config.set_advection(Advection::BFECC);
+        config.set_curl(0.75).unwrap();
+
+        let mut boundaries = Boundaries::new();
+        boundaries.set_mode(Edge::TOP, BoundaryMode::PERIODIC);
+        boundaries.set_mode(Edge::BOTTOM, BoundaryMode::PERIODIC);
+
+        let mut recorder = Recorder::new();
+        for event in [
+            Event::Update {
+                time: 0.5,
+                config: config.clone(),
+            },
+            Event::Resize {
+                width: 320,
+                height: 240,
+                sim_resolution: Resolution::EIGHT,
+                dye_resolution: Resolution::TWO,
+            },
+            Event::Splat {
+                radius: 0.25,
+                position: [10.0, 20.0],
+                velocity: [-3.0, 4.0],
+                color: [0.1, 0.2, 0.3],
+            },
+            Event::SetObstacle {
+                radius: Some(8.0),
+                position: [100.0, 50.0],
+                color: [1.0, 0.0, 0.0],
+                is_circle: true,
+                velocity: [1.0, -1.0],
+            },
+            Event::SetObstacleMask {
+                mask: Mask::from_alpha(2, 2, &[0, 255, 128, 64]).unwrap(),
+                threshold: 0.5,
+                position: [30.0, 40.0],
+                size: [20.0, 10.0],
+                rotation: 0.25,
+                color: [0.0, 1.0, 0.0],
+            },
+            Event::AddObstacle {
+                obstacle: Obstacle::circle(4.0, &[60.0, 60.0], &[0.5, 0.5, 0.5]),
+            },
+            Event::RemoveObstacle {
+                id: 2,
+            },
+            Event::UpdateObstacle {
+                id: 1,
+                obstacle: Obstacle::rectangle(&[6.0, 2.0], &[80.0, 30.0], 1.0, &[0.0, 0.0, 1.0]),
+            },
+            Event::ClearObstacles,
+            Event::SetWindTunnel {
+                wind_tunnel: Some(WindTunnel::new(40.0, 4, &[1.0, 1.0, 1.0])),
+            },
+            Event::SetWindTunnel {
+                wind_tunnel: None,
+            },
+            Event::SetBoundaries {
+                boundaries,
+            },
+            Event::SetRigidBody {
+                id: 1,
+                body: Some(RigidBody::new(2.0, 3.0).unwrap()),
+            },
+            Event::SetRigidBody {
+                id: 1,
+                body: None,
+            },
+            Event::SetTimestep {
+                timestep: 0.02,
+            },
+            Event::Step {
+                delta_time: 0.05,
+                substeps: 2,
+                config: config.clone(),
+            },
+            Event::Update {
+                time: 0.75,
+                config: SimulationConfig::new(),
+            },
+        ] {
+            recorder.record(event);
+        }
+
+        recorder.finish()
+    }
+
+    #[test]
+    fn round_trip() {
+        let recording = recording();
+        assert_eq!(Recording::from_bytes(&recording.to_bytes()), Ok(recording));
+    }
+
+    #[test]
+    fn steps_are_timestamped_with_their_time() {
+        let timestamps: Vec<f32> = recording().records().iter().map(|record| record.timestamp).collect();
+        assert_eq!(timestamps[..2], [0.5, 0.5]);
+        assert_eq!(timestamps[timestamps.len() - 3..], [0.5, 0.55, 0.75]);
+    }
+
+    #[test]
+    fn replays_steps_exactly() {
+        let config = SimulationConfig::new();
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 48, Resolution::FOUR, Resolution::TWO);
+        let mut recorder = Recorder::new();
+
+        // Time left over in the accumulator before the recording starts, which a replay does not have
+        simulation.set_timestep(0.02);
+        assert_eq!(simulation.step(0.015, &config), Ok(0));
+        recorder.record(Event::SetTimestep {
+            timestep: 0.02,
+        });
+        for (i, delta_time) in [0.013, 0.031, 0.007, 0.05, 0.019, 0.026].into_iter().enumerate() {
+            let position = [8.0 + 8.0 * i as f32, 24.0];
+            let Ok(()) = simulation.splat(4.0, &position, &[200.0, 50.0], &[1.0, 0.5, 0.0]);
+            recorder.record(Event::Splat {
+                radius: 4.0,
+                position,
+                velocity: [200.0, 50.0],
+                color: [1.0, 0.5, 0.0],
+            });
+
+            let Ok(substeps) = simulation.step(delta_time, &config);
+            recorder.record(Event::Step {
+                delta_time,
+                substeps: substeps as u32,
+                config: config.clone(),
+            });
+        }
+
+        let Ok(mut replay) = CpuSolver::new(CpuBackend::new(), 64, 48, Resolution::FOUR, Resolution::TWO);
+        let mut player = Player::new(Recording::from_bytes(&recorder.finish().to_bytes()).unwrap());
+        let mut frames = 0;
+        while let Ok(Some(_)) = player.play_frame(&mut replay) {
+            frames += 1;
+        }
+
+        assert_eq!(frames, 6);
+        let (Ok(expected), Ok(replayed)) = (simulation.snapshot(), replay.snapshot());
+        assert_eq!(replayed, expected);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let mut bytes = recording().to_bytes();
+        bytes[8..10].copy_from_slice(&(RECORDING_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            Recording::from_bytes(&bytes),
+            Err(RecordingError::UnsupportedVersion(RECORDING_VERSION + 1)),
+        );
+
+        bytes[0] = b'X';
+        assert_eq!(Recording::from_bytes(&bytes), Err(RecordingError::InvalidMagic));
+    }
+
+    #[test]
+    fn rejects_truncated_events() {
+        let bytes = recording().to_bytes();
+        let end = bytes.len() - 1;
+        assert_eq!(Recording::from_bytes(&bytes[..end]), Err(RecordingError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn rejects_unknown_values() {
+        // The first update starts after the magic bytes, the version, its tag and its timestamp
+        let bytes = recording().to_bytes();
+        for (offset, error) in [
+            (10, RecordingError::InvalidEvent(255)),
+            (16, RecordingError::InvalidMode(255)),
+            (17, RecordingError::InvalidSolver(255)),
+            (38, RecordingError::InvalidAdvection(255)),
+        ] {
+            let mut bytes = bytes.clone();
+            bytes[offset] = 255;
+            assert_eq!(Recording::from_bytes(&bytes), Err(error));
+        }
+    }
+}
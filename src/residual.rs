@@ -0,0 +1,144 @@
+//! Norm of the residual of the pressure equation, measured on the backend
+//!
+//! The residual of every texel is written to a texture, which is summed up by repeatedly halving it with
+//! [Reduce](Pass::Reduce) passes, so that only a single texel has to be read back.
+
+use crate::backend::{ BackendTexture, Pass, PingPong, SimulationBackend };
+use crate::boundary::BoundaryMode;
+use crate::grid::Filter;
+
+/// Textures that measure the residual of a grid of a fixed size
+pub(crate) struct ResidualNorm<T> {
+    /// Residual of every texel
+    residual: T,
+    /// Sums of ever larger blocks of squared residuals, down to a single texel
+    sums: Vec<T>,
+}
+
+impl<T: BackendTexture> ResidualNorm<T> {
+    /// Create the textures for a grid of `width` x `height` texels
+    pub fn new<B: SimulationBackend<Texture = T>>(
+        backend: &mut B,
+        width: u32,
+        height: u32,
+    ) -> Result<ResidualNorm<T>, B::Error> {
+        let residual = backend.create_texture(width, height, Filter::Nearest)?;
+
+        let mut sums = Vec::new();
+        let (mut width, mut height) = (width, height);
+        loop {
+            width = width.div_ceil(2);
+            height = height.div_ceil(2);
+            sums.push(backend.create_data_texture(width, height)?);
+            if width == 1 && height == 1 {
+                break;
+            }
+        }
+
+        Ok(ResidualNorm { residual, sums })
+    }
+
+    /// Size of the grid the textures were created for
+    pub fn size(&self) -> [u32; 2] {
+        [self.residual.width(), self.residual.height()]
+    }
+
+    /// Delete every texture
+    pub fn delete<B: SimulationBackend<Texture = T>>(self, backend: &mut B) {
+        backend.delete_texture(self.residual);
+        for sum in self.sums {
+            backend.delete_texture(sum);
+        }
+    }
+
+    /// Measure the root mean square of the residual of the pressure `x` over the whole grid, in the units of
+    /// the divergence `b`
+    ///
+    /// Solid texels count as zero. The arguments are the ones of the [pressure solver](Pass::PressureSolver).
+    #[allow(clippy::too_many_arguments)]
+    pub fn measure<B: SimulationBackend<Texture = T>>(
+        &mut self,
+        backend: &mut B,
+        boundary: [BoundaryMode; 4],
+        alpha: f32,
+        resolution: [f32; 2],
+        x: &PingPong<T>,
+        b: &T,
+        obstacles: &T,
+    ) -> Result<f32, B::Error> {
+        backend.dispatch(
+            Pass::Residual {
+                boundary,
+                alpha,
+                resolution,
+                x: x.read(),
+                b,
+                obstacles,
+            },
+            &mut self.residual,
+        )?;
+
+        // REDUCTION
+        let mut square = true;
+        let mut texture = &self.residual;
+        for sum in &mut self.sums {
+            backend.dispatch(
+                Pass::Reduce {
+                    square,
+                    resolution: [texture.width() as f32, texture.height() as f32],
+                    texture,
+                },
+                sum,
+            )?;
+            square = false;
+            texture = sum;
+        }
+
+        let sum = backend.read_texture(texture)?[0];
+        Ok((sum / (resolution[0] * resolution[1])).max(0.0).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_backend::CpuBackend;
+    use crate::grid::Grid;
+
+    #[test]
+    fn measures_the_root_mean_square_of_an_odd_grid() {
+        let mut backend = CpuBackend::new();
+        let Ok(mut norm) = ResidualNorm::new(&mut backend, 7, 5);
+        let sizes: Vec<[u32; 2]> = norm.sums.iter().map(|sum| [sum.width(), sum.height()]).collect();
+        assert_eq!(sizes, [[4, 3], [2, 2], [1, 1]]);
+
+        // With a zero pressure, the residual is the divergence itself on every fluid texel. The last column
+        // and row, which the halving leaves without a partner, carry the largest values.
+        let Ok(x) = PingPong::new_data(&mut backend, 7, 5);
+        let mut b = Grid::new(7, 5, Filter::Nearest);
+        let mut obstacles = Grid::new(7, 5, Filter::Nearest);
+        for (i, (b, obstacle)) in b.data_mut().iter_mut().zip(obstacles.data_mut()).enumerate() {
+            let (column, row) = (i % 7, i / 7);
+            *b = [(column + 2 * row) as f32 - 3.0; 4];
+            *obstacle = [if i == 17 { 0.0 } else { 1.0 }; 4];
+        }
+
+        let sum: f32 = b.data().iter()
+            .zip(obstacles.data())
+            .filter(|(_, obstacle)| obstacle[0] >= 0.5)
+            .map(|(b, _)| b[0] * b[0])
+            .sum();
+        let expected = (sum / 35.0).sqrt();
+
+        let Ok(measured) = norm.measure(
+            &mut backend,
+            [BoundaryMode::NO_SLIP; 4],
+            -1.0,
+            [7.0, 5.0],
+            &x,
+            &b,
+            &obstacles,
+        );
+        assert!((measured - expected).abs() < 1e-5 * expected, "measured {}, expected {}", measured, expected);
+    }
+}
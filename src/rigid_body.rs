@@ -0,0 +1,271 @@
+use std::fmt;
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+use crate::bytes::Reader;
+use crate::obstacle::{ DecodeError, Obstacle };
+
+#[derive(Clone, Debug, PartialEq)]
+/// Error returned when the arguments of a [rigid body](RigidBody) do not describe one that can move
+pub enum RigidBodyError {
+    /// A mass that is not positive and finite
+    Mass(f32),
+    /// A moment of inertia that is not positive and finite
+    Inertia(f32),
+    /// A density that is not positive and finite
+    Density(f32),
+    /// A shape without an area
+    NoArea,
+}
+
+impl fmt::Display for RigidBodyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RigidBodyError::Mass(mass) => write!(f, "a rigid body needs a positive mass, got {}", mass),
+            RigidBodyError::Inertia(inertia) => write!(f, "a rigid body needs a positive inertia, got {}", inertia),
+            RigidBodyError::Density(density) => write!(f, "a rigid body needs a positive density, got {}", density),
+            RigidBodyError::NoArea => write!(f, "a rigid body needs a shape with an area"),
+        }
+    }
+}
+
+impl std::error::Error for RigidBodyError {}
+
+#[cfg(feature = "webgl")]
+impl From<RigidBodyError> for JsValue {
+    fn from(error: RigidBodyError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// The mass properties that let an [obstacle](Obstacle) float in the fluid
+///
+/// A rigid body is attached to an obstacle of the [simulation](crate::Simulation), which then no longer
+/// stands still: every step the force and torque of the fluid on the obstacle accelerate it, and its
+/// position, rotation and velocities are updated. The obstacle turns around its position, which is
+/// taken as the center of mass. Bodies bounce off closed edges of the domain and off each other, the
+/// latter as if they were round.
+///
+/// Masses are relative to the fluid, which has a density of one, so a body with the mass of its
+/// [area](RigidBody::from_density) floats like a part of the fluid. Much lighter bodies are unstable.
+///
+/// # Format
+/// [Snapshots](crate::Snapshot) and [recordings](crate::Recording) encode a rigid body as the `mass`,
+/// the `inertia` and the `restitution` (`f32` each), little endian.
+pub struct RigidBody {
+    mass: f32,
+    inertia: f32,
+    restitution: f32,
+}
+
+impl RigidBody {
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        for value in [self.mass, self.inertia, self.restitution] {
+            bytes.extend(value.to_le_bytes());
+        }
+    }
+
+    pub(crate) fn read(reader: &mut Reader) -> Result<RigidBody, DecodeError> {
+        let (mass, inertia, restitution) = (reader.f32()?, reader.f32()?, reader.f32()?);
+        if !(0.0..=1.0).contains(&restitution) {
+            return Err(DecodeError::InvalidRigidBody);
+        }
+
+        let mut body = RigidBody::new(mass, inertia).map_err(|_| DecodeError::InvalidRigidBody)?;
+        body.restitution = restitution;
+        Ok(body)
+    }
+
+    /// Move `obstacle` by the `force` and `torque` of the fluid over a step of `delta_time` seconds
+    pub(crate) fn integrate(
+        &self,
+        obstacle: &mut Obstacle,
+        force: [f32; 2],
+        torque: f32,
+        delta_time: f32,
+    ) {
+        // Semi-implicit Euler, the new velocities move the obstacle
+        for (i, force) in force.iter().enumerate() {
+            obstacle.linear_velocity[i] += force / self.mass * delta_time;
+            obstacle.position[i] += obstacle.linear_velocity[i] * delta_time;
+        }
+
+        obstacle.angular_velocity += torque / self.inertia * delta_time;
+        obstacle.rotation += obstacle.angular_velocity * delta_time;
+    }
+
+    /// Keep `obstacle` inside of a domain of `size` pixels, wrapping it around along the `periodic` axes
+    /// and bouncing it off the edges of the others
+    pub(crate) fn collide_edges(
+        &self,
+        obstacle: &mut Obstacle,
+        size: [f32; 2],
+        periodic: [bool; 2],
+    ) {
+        let (sin, cos) = obstacle.rotation.sin_cos();
+        let outline: Vec<[f32; 2]> = obstacle
+            .shape
+            .outline()
+            .iter()
+            .map(|point| [cos * point[0] - sin * point[1], sin * point[0] + cos * point[1]])
+            .collect();
+
+        for axis in 0..2 {
+            if periodic[axis] {
+                obstacle.position[axis] = obstacle.position[axis].rem_euclid(size[axis]);
+                continue;
+            }
+
+            for (normal, limit) in [(1.0, 0.0), (-1.0, size[axis])] {
+                // The point of the outline furthest beyond the edge is the contact
+                let Some(contact) = outline
+                    .iter()
+                    .copied()
+                    .max_by(|a, b| (-normal * a[axis]).total_cmp(&(-normal * b[axis])))
+                else {
+                    continue;
+                };
+
+                let depth = normal * (limit - obstacle.position[axis] - contact[axis]);
+                if depth <= 0.0 {
+                    continue;
+                }
+
+                obstacle.position[axis] += normal * depth;
+                let mut direction = [0.0; 2];
+                direction[axis] = normal;
+                self.bounce(obstacle, contact, direction);
+            }
+        }
+    }
+
+    /// Apply an impulse at `contact`, relative to the position of `obstacle`, that reflects the velocity
+    /// of that point towards `normal`
+    fn bounce(&self, obstacle: &mut Obstacle, contact: [f32; 2], normal: [f32; 2]) {
+        let velocity = [
+            obstacle.linear_velocity[0] - obstacle.angular_velocity * contact[1],
+            obstacle.linear_velocity[1] + obstacle.angular_velocity * contact[0],
+        ];
+        let approach = velocity[0] * normal[0] + velocity[1] * normal[1];
+        if approach >= 0.0 {
+            return;
+        }
+
+        let arm = contact[0] * normal[1] - contact[1] * normal[0];
+        let impulse = -(1.0 + self.restitution) * approach / (1.0 / self.mass + arm * arm / self.inertia);
+        obstacle.linear_velocity[0] += impulse * normal[0] / self.mass;
+        obstacle.linear_velocity[1] += impulse * normal[1] / self.mass;
+        obstacle.angular_velocity += impulse * arm / self.inertia;
+    }
+
+    /// Push two bodies apart that overlap, treating both as circles around their positions
+    pub(crate) fn collide(
+        (a, body_a): (&mut Obstacle, &RigidBody),
+        (b, body_b): (&mut Obstacle, &RigidBody),
+    ) {
+        let radius = |obstacle: &Obstacle| {
+            obstacle
+                .shape
+                .outline()
+                .iter()
+                .map(|point| point[0].hypot(point[1]))
+                .fold(0.0, f32::max)
+        };
+
+        let offset = [b.position[0] - a.position[0], b.position[1] - a.position[1]];
+        let distance = offset[0].hypot(offset[1]);
+        let depth = radius(a) + radius(b) - distance;
+        if depth <= 0.0 || distance == 0.0 {
+            return;
+        }
+
+        // Both bodies move out of each other in inverse proportion to their masses
+        let normal = [offset[0] / distance, offset[1] / distance];
+        let inverse_mass = 1.0 / body_a.mass + 1.0 / body_b.mass;
+        for (i, normal) in normal.iter().enumerate() {
+            a.position[i] -= normal * depth / body_a.mass / inverse_mass;
+            b.position[i] += normal * depth / body_b.mass / inverse_mass;
+        }
+
+        let approach = (b.linear_velocity[0] - a.linear_velocity[0]) * normal[0]
+            + (b.linear_velocity[1] - a.linear_velocity[1]) * normal[1];
+        if approach >= 0.0 {
+            return;
+        }
+
+        let restitution = body_a.restitution.min(body_b.restitution);
+        let impulse = -(1.0 + restitution) * approach / inverse_mass;
+        for (i, normal) in normal.iter().enumerate() {
+            a.linear_velocity[i] -= impulse * normal / body_a.mass;
+            b.linear_velocity[i] += impulse * normal / body_b.mass;
+        }
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl RigidBody {
+    /// Create a rigid body
+    ///
+    /// # Arguments
+    /// * `mass` - Mass of the body, a body of the fluid has the mass of its area in square pixels (> 0)
+    /// * `inertia` - Moment of inertia of the body around the position of its obstacle (> 0)
+    ///
+    /// # Errors
+    /// If `mass` or `inertia` is not positive and finite.
+    #[cfg_attr(feature = "webgl", wasm_bindgen(constructor))]
+    pub fn new(mass: f32, inertia: f32) -> Result<RigidBody, RigidBodyError> {
+        if !(mass > 0.0 && mass.is_finite()) {
+            return Err(RigidBodyError::Mass(mass));
+        }
+        if !(inertia > 0.0 && inertia.is_finite()) {
+            return Err(RigidBodyError::Inertia(inertia));
+        }
+
+        Ok(RigidBody {
+            mass,
+            inertia,
+            restitution: 0.5,
+        })
+    }
+
+    /// Create a rigid body with the mass and inertia of the shape of `obstacle`
+    ///
+    /// # Arguments
+    /// * `obstacle` - The [obstacle](Obstacle) the body is made for
+    /// * `density` - Density of the body relative to the fluid, 1 for a body that is as heavy as the fluid it displaces
+    ///
+    /// # Errors
+    /// If `density` is not positive and finite or the shape of `obstacle` has no area.
+    pub fn from_density(obstacle: &Obstacle, density: f32) -> Result<RigidBody, RigidBodyError> {
+        if !(density > 0.0 && density.is_finite()) {
+            return Err(RigidBodyError::Density(density));
+        }
+
+        let (area, moment) = obstacle.shape.area_moment();
+        if !(area > 0.0 && moment > 0.0) {
+            return Err(RigidBodyError::NoArea);
+        }
+        RigidBody::new(density * area, density * moment)
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn inertia(&self) -> f32 {
+        self.inertia
+    }
+
+    /// How much of the velocity towards an edge or another body is kept after a collision [0, 1], 0.5 by default
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn restitution(&self) -> f32 {
+        self.restitution
+    }
+
+    pub fn set_restitution(&mut self, restitution: f32) {
+        self.restitution = restitution.clamp(0.0, 1.0);
+    }
+}
@@ -51,7 +51,7 @@ impl ShaderProgram {
         let fragment_shader = ShaderProgram::create_shader(
             gl,
             WebGl2RenderingContext::FRAGMENT_SHADER,
-            &fragment_shader
+            fragment_shader
         )?;
     
         let shader_program = gl.create_program()
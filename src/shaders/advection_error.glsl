@@ -0,0 +1,18 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform float u_delta_time;
+uniform vec2 u_resolution;
+uniform sampler2D u_velocity;
+uniform sampler2D u_quantity;
+uniform sampler2D u_advected;
+
+// Half the difference between the quantity and the advected one advected back, which would be the same
+// if the advection was exact
+void main() {
+    vec2 velocity = texture2D(u_velocity, v_uv).xy / u_resolution;
+    vec2 position = v_uv + velocity * u_delta_time;
+    gl_FragColor = 0.5 * (texture2D(u_quantity, v_uv) - texture2D(u_advected, position));
+}
\ No newline at end of file
@@ -3,13 +3,13 @@ precision highp sampler2D;
 
 varying vec2 v_uv;
 
-uniform vec3 u_obstacle_color;
+uniform float u_intensity;
 uniform sampler2D u_obstacles;
 uniform sampler2D u_texture;
 
 void main() {
     vec4 value = texture2D(u_texture, v_uv);
-    float obstacle = texture2D(u_obstacles, v_uv).x;
-    if (obstacle < 0.5) value = vec4(u_obstacle_color, value.w);
+    vec4 obstacle = texture2D(u_obstacles, v_uv);
+    if (obstacle.x < 0.5) value = vec4(obstacle.yzw * u_intensity, value.w);
     gl_FragColor = value;
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform int u_colormap;
+uniform vec2 u_range;
+uniform bool u_magnitude;
+uniform bool u_color_obstacles;
+uniform sampler2D u_texture;
+uniform sampler2D u_obstacles;
+
+// Blue through a light gray to red, for values with a sign
+vec3 diverging(float t) {
+    vec3 blue = vec3(0.230, 0.299, 0.754);
+    vec3 gray = vec3(0.865, 0.865, 0.865);
+    vec3 red = vec3(0.706, 0.016, 0.150);
+    if (t < 0.5) {
+        return mix(blue, gray, 2.0 * t);
+    }
+
+    return mix(gray, red, 2.0 * t - 1.0);
+}
+
+// A polynomial fit of viridis, for magnitudes
+vec3 sequential(float t) {
+    vec3 c0 = vec3(0.2777273, 0.005407345, 0.3340998);
+    vec3 c1 = vec3(0.1050930, 1.404614, 1.384590);
+    vec3 c2 = vec3(-0.3308618, 0.2148476, 0.09509516);
+    vec3 c3 = vec3(-4.634230, -5.799101, -19.33244);
+    vec3 c4 = vec3(6.228270, 14.17993, 56.69055);
+    vec3 c5 = vec3(4.776385, -13.74515, -65.35303);
+    vec3 c6 = vec3(-5.435456, 4.645853, 26.31244);
+
+    return clamp(c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6))))), 0.0, 1.0);
+}
+
+void main() {
+    vec4 obstacle = texture2D(u_obstacles, v_uv);
+    if (u_color_obstacles && obstacle.x < 0.5) {
+        gl_FragColor = vec4(obstacle.yzw, 1.0);
+        return;
+    }
+
+    vec4 value = texture2D(u_texture, v_uv);
+    float x = u_magnitude ? length(value.xy) : value.x;
+    float t = clamp((x - u_range.x) / (u_range.y - u_range.x), 0.0, 1.0);
+
+    vec3 color = vec3(t);
+    if (u_colormap == 0) {
+        color = diverging(t);
+    } else if (u_colormap == 1) {
+        color = sequential(t);
+    }
+
+    gl_FragColor = vec4(color, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,35 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform bool u_bfecc;
+uniform float u_dissipation;
+uniform float u_delta_time;
+uniform vec2 u_resolution;
+uniform vec2 u_quantity_resolution;
+uniform sampler2D u_velocity;
+uniform sampler2D u_quantity;
+uniform sampler2D u_error;
+uniform sampler2D u_obstacles;
+
+void main() {
+    vec2 velocity = texture2D(u_velocity, v_uv).xy / u_resolution;
+    vec2 position = v_uv - velocity * u_delta_time;
+
+    // BFECC advects the quantity with its error compensated, MacCormack corrects the advected quantity
+    vec4 error = texture2D(u_error, u_bfecc ? position : v_uv);
+    vec4 value = texture2D(u_quantity, position) + error;
+
+    // The correction may not leave the range of the texels the quantity is interpolated from
+    vec2 texel_size = 1.0 / u_quantity_resolution;
+    vec2 corner = (floor(position * u_quantity_resolution - 0.5) + 0.5) * texel_size;
+    vec4 a = texture2D(u_quantity, corner);
+    vec4 b = texture2D(u_quantity, corner + vec2(texel_size.x, 0.0));
+    vec4 c = texture2D(u_quantity, corner + vec2(0.0, texel_size.y));
+    vec4 d = texture2D(u_quantity, corner + texel_size);
+    value = clamp(value, min(min(a, b), min(c, d)), max(max(a, b), max(c, d)));
+
+    float obstacle = texture2D(u_obstacles, v_uv).x;
+    gl_FragColor = value * u_dissipation * obstacle;
+}
\ No newline at end of file
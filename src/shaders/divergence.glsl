@@ -3,10 +3,27 @@ precision highp sampler2D;
 
 varying vec2 v_uv;
 
+const int PERIODIC = 2;
+const int OUTFLOW = 3;
+const int INFLOW = 4;
+
+uniform ivec4 u_boundary;
+uniform vec2 u_inflow_left;
+uniform vec2 u_inflow_right;
+uniform vec2 u_inflow_bottom;
+uniform vec2 u_inflow_top;
 uniform float u_r_half_texel_size;
 uniform vec2 u_resolution;
 uniform sampler2D u_velocity;
 uniform sampler2D u_obstacles;
+uniform sampler2D u_obstacle_velocity;
+
+// Velocity just outside of an edge, walls stand still
+float boundary(int mode, float inflow, float x_c) {
+    if (mode == OUTFLOW) return x_c;
+    if (mode == INFLOW) return 2.0 * inflow - x_c;
+    return -x_c;
+}
 
 void main() {
     vec2 l = v_uv - vec2(1.0, 0.0) / u_resolution;
@@ -24,12 +41,18 @@ void main() {
     float o_r = texture2D(u_obstacles, r).x;
     float o_b = texture2D(u_obstacles, b).x;
     float o_t = texture2D(u_obstacles, t).x;
-    
-    if (gl_FragCoord.x < 1.0 || o_l < 0.5) { x_l = -x_c.x; }
-    else if (gl_FragCoord.x > u_resolution.x - 1.0 || o_r < 0.5) { x_r = -x_c.x; }
 
-    if (gl_FragCoord.y < 1.0 || o_b < 0.5) { x_b = -x_c.y; }
-    else if (gl_FragCoord.y > u_resolution.y - 1.0 || o_t < 0.5) { x_t = -x_c.y; }
+    // Solid neighbours move with the velocity of their obstacle, the edges of the domain follow their
+    // boundary mode, periodic edges sample the opposite side through the wrapping of the texture
+    if (gl_FragCoord.x < 1.0 && u_boundary.x != PERIODIC) { x_l = boundary(u_boundary.x, u_inflow_left.x, x_c.x); }
+    else if (o_l < 0.5) { x_l = 2.0 * texture2D(u_obstacle_velocity, l).x - x_c.x; }
+    else if (gl_FragCoord.x > u_resolution.x - 1.0 && u_boundary.y != PERIODIC) { x_r = boundary(u_boundary.y, u_inflow_right.x, x_c.x); }
+    else if (o_r < 0.5) { x_r = 2.0 * texture2D(u_obstacle_velocity, r).x - x_c.x; }
+
+    if (gl_FragCoord.y < 1.0 && u_boundary.z != PERIODIC) { x_b = boundary(u_boundary.z, u_inflow_bottom.y, x_c.y); }
+    else if (o_b < 0.5) { x_b = 2.0 * texture2D(u_obstacle_velocity, b).y - x_c.y; }
+    else if (gl_FragCoord.y > u_resolution.y - 1.0 && u_boundary.w != PERIODIC) { x_t = boundary(u_boundary.w, u_inflow_top.y, x_c.y); }
+    else if (o_t < 0.5) { x_t = 2.0 * texture2D(u_obstacle_velocity, t).y - x_c.y; }
 
     float divergence = (x_r - x_l + x_t - x_b) * u_r_half_texel_size;
     gl_FragColor = vec4(divergence, 0.0, 0.0, 0.0);
@@ -0,0 +1,42 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform float u_pressure_scale;
+uniform float u_viscous_scale;
+uniform vec2 u_resolution;
+uniform sampler2D u_pressure;
+uniform sampler2D u_velocity;
+uniform sampler2D u_obstacles;
+uniform sampler2D u_obstacle_velocity;
+
+// Pressure and viscous force of the fluid on the face of the texel at `center` towards `direction`, if
+// the neighbour in that direction is fluid
+vec4 face(vec2 center, vec2 direction, vec2 wall) {
+    vec2 uv = center + direction / u_resolution;
+    if (texture2D(u_obstacles, uv).x < 0.5) return vec4(0.0);
+
+    // The pressure pushes into the obstacle, the shear drags it along with the fluid
+    float pressure = texture2D(u_pressure, uv).x;
+    vec2 tangent = vec2(-direction.y, direction.x);
+    vec2 slip = texture2D(u_velocity, uv).xy - wall;
+    return vec4(-direction * pressure * u_pressure_scale, tangent * dot(slip, tangent) * u_viscous_scale);
+}
+
+void main() {
+    // The lower half of the target holds the forces, the upper half the index of the obstacle of every texel
+    vec2 uv = vec2(v_uv.x, fract(2.0 * v_uv.y));
+    if (v_uv.y >= 0.5) {
+        gl_FragColor = vec4(texture2D(u_obstacle_velocity, uv).z, 0.0, 0.0, 0.0);
+        return;
+    }
+    if (texture2D(u_obstacles, uv).x >= 0.5) {
+        gl_FragColor = vec4(0.0);
+        return;
+    }
+
+    vec2 wall = texture2D(u_obstacle_velocity, uv).xy;
+    gl_FragColor = face(uv, vec2(-1.0, 0.0), wall) + face(uv, vec2(1.0, 0.0), wall)
+        + face(uv, vec2(0.0, -1.0), wall) + face(uv, vec2(0.0, 1.0), wall);
+}
\ No newline at end of file
@@ -3,11 +3,21 @@ precision highp sampler2D;
 
 varying vec2 v_uv;
 
+const int NO_SLIP = 0;
+const int OUTFLOW = 3;
+const int INFLOW = 4;
+
+uniform ivec4 u_boundary;
+uniform vec2 u_inflow_left;
+uniform vec2 u_inflow_right;
+uniform vec2 u_inflow_bottom;
+uniform vec2 u_inflow_top;
 uniform float u_r_half_texel_size;
 uniform vec2 u_resolution;
 uniform sampler2D u_velocity;
 uniform sampler2D u_pressure;
 uniform sampler2D u_obstacles;
+uniform sampler2D u_obstacle_velocity;
 
 void main() {
     float x_l = texture2D(u_pressure, (gl_FragCoord.xy - vec2(1.0, 0.0)) / u_resolution).x;
@@ -15,8 +25,30 @@ void main() {
     float x_b = texture2D(u_pressure, (gl_FragCoord.xy - vec2(0.0, 1.0)) / u_resolution).x;
     float x_t = texture2D(u_pressure, (gl_FragCoord.xy + vec2(0.0, 1.0)) / u_resolution).x;
 
+    // The pressure outside of an outflow is zero
+    float x_c = texture2D(u_pressure, v_uv).x;
+    bool left = gl_FragCoord.x < 1.0;
+    bool right = gl_FragCoord.x > u_resolution.x - 1.0;
+    bool bottom = gl_FragCoord.y < 1.0;
+    bool top = gl_FragCoord.y > u_resolution.y - 1.0;
+    if (left && u_boundary.x == OUTFLOW) { x_l = -x_c; }
+    if (right && u_boundary.y == OUTFLOW) { x_r = -x_c; }
+    if (bottom && u_boundary.z == OUTFLOW) { x_b = -x_c; }
+    if (top && u_boundary.w == OUTFLOW) { x_t = -x_c; }
+
     vec2 velocity = texture2D(u_velocity, v_uv).xy;
     velocity -= vec2(x_r - x_l, x_t - x_b) * u_r_half_texel_size;
     float obstacle = texture2D(u_obstacles, v_uv).x;
-    gl_FragColor = vec4(velocity * obstacle, 0.0, 0.0);
+    vec2 obstacle_velocity = texture2D(u_obstacle_velocity, v_uv).xy;
+    velocity = mix(obstacle_velocity, velocity, obstacle);
+
+    // The fluid sticks to no-slip walls and enters through inflows with their velocity
+    if ((left && u_boundary.x == NO_SLIP) || (right && u_boundary.y == NO_SLIP)) { velocity.y = 0.0; }
+    if ((bottom && u_boundary.z == NO_SLIP) || (top && u_boundary.w == NO_SLIP)) { velocity.x = 0.0; }
+    if (left && u_boundary.x == INFLOW) { velocity = u_inflow_left; }
+    if (right && u_boundary.y == INFLOW) { velocity = u_inflow_right; }
+    if (bottom && u_boundary.z == INFLOW) { velocity = u_inflow_bottom; }
+    if (top && u_boundary.w == INFLOW) { velocity = u_inflow_top; }
+
+    gl_FragColor = vec4(velocity, 0.0, 0.0);
 } 
\ No newline at end of file
@@ -3,17 +3,41 @@ precision highp sampler2D;
 
 varying vec2 v_uv;
 
-uniform bool u_is_circle;
-uniform float u_scaled_radius_sqr;
+uniform int u_shape;
+uniform bool u_write_velocity;
+uniform float u_index;
+uniform vec2 u_size;
 uniform vec2 u_position;
+uniform vec2 u_rotation;
+uniform vec2 u_linear_velocity;
+uniform float u_angular_velocity;
+uniform vec3 u_obstacle_color;
+uniform sampler2D u_obstacles;
 
 void main() {
-    vec2 distance = gl_FragCoord.xy - u_position;
-    float obstacle = 1.0;
-    if (u_is_circle)
-        obstacle = step(u_scaled_radius_sqr, dot(distance, distance));
-    else if (distance.x * distance.x < u_scaled_radius_sqr && distance.y * distance.y < u_scaled_radius_sqr)
-        obstacle = 0.0;
+    vec2 offset = gl_FragCoord.xy - u_position;
+    vec2 distance = vec2(
+        u_rotation.x * offset.x + u_rotation.y * offset.y,
+        u_rotation.x * offset.y - u_rotation.y * offset.x
+    );
 
-    gl_FragColor = vec4(obstacle, 0.0, 0.0, 0.0);
+    // SHAPES: 0 = circle, 1 = square, 2 = rectangle, 3 = capsule, 4 = annulus
+    bool inside = false;
+    if (u_shape == 0) {
+        inside = dot(distance, distance) < u_size.x * u_size.x;
+    } else if (u_shape == 1 || u_shape == 2) {
+        inside = distance.x * distance.x < u_size.x * u_size.x && distance.y * distance.y < u_size.y * u_size.y;
+    } else if (u_shape == 3) {
+        vec2 segment = vec2(max(abs(distance.x) - u_size.x, 0.0), distance.y);
+        inside = dot(segment, segment) < u_size.y * u_size.y;
+    } else if (u_shape == 4) {
+        float distance_sqr = dot(distance, distance);
+        inside = distance_sqr >= u_size.x * u_size.x && distance_sqr < u_size.y * u_size.y;
+    }
+
+    vec4 solid = u_write_velocity
+        ? vec4(u_linear_velocity + u_angular_velocity * vec2(-offset.y, offset.x), u_index, 0.0)
+        : vec4(0.0, u_obstacle_color);
+
+    gl_FragColor = inside ? solid : texture2D(u_obstacles, v_uv);
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform bool u_write_velocity;
+uniform float u_index;
+uniform float u_threshold;
+uniform vec2 u_position;
+uniform vec2 u_half_size;
+uniform vec2 u_rotation;
+uniform vec2 u_linear_velocity;
+uniform float u_angular_velocity;
+uniform vec3 u_obstacle_color;
+uniform sampler2D u_mask;
+uniform sampler2D u_obstacles;
+
+void main() {
+    vec2 distance = gl_FragCoord.xy - u_position;
+    vec2 local = vec2(
+        u_rotation.x * distance.x + u_rotation.y * distance.y,
+        u_rotation.x * distance.y - u_rotation.y * distance.x
+    );
+    vec2 mask_uv = 0.5 + 0.5 * local / u_half_size;
+
+    bool inside = all(greaterThanEqual(mask_uv, vec2(0.0))) && all(lessThanEqual(mask_uv, vec2(1.0)))
+        && texture2D(u_mask, mask_uv).x >= u_threshold;
+
+    vec4 solid = u_write_velocity
+        ? vec4(u_linear_velocity + u_angular_velocity * vec2(-distance.y, distance.x), u_index, 0.0)
+        : vec4(0.0, u_obstacle_color);
+
+    gl_FragColor = inside ? solid : texture2D(u_obstacles, v_uv);
+}
\ No newline at end of file
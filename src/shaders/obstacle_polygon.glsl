@@ -0,0 +1,49 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+const int MAX_VERTICES = 1024;
+
+uniform bool u_write_velocity;
+uniform float u_index;
+uniform int u_vertex_count;
+uniform vec2 u_position;
+uniform vec2 u_rotation;
+uniform vec2 u_linear_velocity;
+uniform float u_angular_velocity;
+uniform vec3 u_obstacle_color;
+uniform sampler2D u_vertices;
+uniform sampler2D u_obstacles;
+
+vec2 vertex(int i) {
+    return texture2D(u_vertices, vec2((float(i) + 0.5) / float(u_vertex_count), 0.5)).xy;
+}
+
+void main() {
+    vec2 offset = gl_FragCoord.xy - u_position;
+    vec2 local = vec2(
+        u_rotation.x * offset.x + u_rotation.y * offset.y,
+        u_rotation.x * offset.y - u_rotation.y * offset.x
+    );
+
+    // Even-odd rule, so that concave polygons work as well
+    bool inside = false;
+    vec2 previous = vertex(u_vertex_count - 1);
+    for (int i = 0; i < MAX_VERTICES; i++) {
+        if (i >= u_vertex_count) break;
+
+        vec2 current = vertex(i);
+        if ((current.y > local.y) != (previous.y > local.y)
+            && local.x < (previous.x - current.x) * (local.y - current.y) / (previous.y - current.y) + current.x) {
+            inside = !inside;
+        }
+        previous = current;
+    }
+
+    vec4 solid = u_write_velocity
+        ? vec4(u_linear_velocity + u_angular_velocity * vec2(-offset.y, offset.x), u_index, 0.0)
+        : vec4(0.0, u_obstacle_color);
+
+    gl_FragColor = inside ? solid : texture2D(u_obstacles, v_uv);
+}
\ No newline at end of file
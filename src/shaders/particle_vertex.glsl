@@ -0,0 +1,37 @@
+precision highp float;
+precision highp sampler2D;
+
+attribute vec2 a_coordinates;
+varying vec4 v_color;
+
+uniform float u_size;
+uniform float u_streak;
+uniform int u_color_mode;
+uniform vec3 u_color;
+uniform float u_max_speed;
+uniform vec2 u_resolution;
+uniform sampler2D u_particles;
+uniform sampler2D u_velocity;
+
+// Every particle has two vertices at its texel of `u_particles`, the one at the end of the streak is shifted
+// by two along x
+void main() {
+    float tail = step(1.5, a_coordinates.x);
+    vec4 particle = texture2D(u_particles, a_coordinates - vec2(2.0 * tail, 0.0));
+    vec2 velocity = texture2D(u_velocity, particle.xy).xy;
+    vec2 position = particle.xy - tail * u_streak * velocity / u_resolution;
+
+    // Particles fade in after they are emitted and out before they die, streaks fade towards their end
+    float life = particle.z / max(particle.w, 0.000001);
+    float alpha = smoothstep(0.0, 0.1, life) * (1.0 - smoothstep(0.8, 1.0, life)) * (1.0 - tail);
+
+    vec3 color = u_color;
+    if (u_color_mode == 1) {
+        float speed = clamp(length(velocity) / u_max_speed, 0.0, 1.0);
+        color = clamp(vec3(1.5) - abs(4.0 * speed - vec3(3.0, 2.0, 1.0)), 0.0, 1.0);
+    }
+
+    v_color = vec4(color, alpha);
+    gl_Position = vec4(2.0 * position - 1.0, 0.0, 1.0);
+    gl_PointSize = u_size;
+}
\ No newline at end of file
@@ -0,0 +1,55 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform float u_delta_time;
+uniform float u_lifetime;
+uniform float u_seed;
+uniform int u_emitter_count;
+uniform vec2 u_period;
+uniform vec2 u_resolution;
+uniform sampler2D u_particles;
+uniform sampler2D u_velocity;
+uniform sampler2D u_obstacles;
+uniform sampler2D u_emitters;
+
+float random(float salt) {
+    return fract(sin(dot(v_uv + vec2(u_seed, salt), vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+vec2 velocity(vec2 position) {
+    return texture2D(u_velocity, position).xy / u_resolution;
+}
+
+vec4 emit() {
+    vec2 position = vec2(random(1.0), random(2.0));
+    if (u_emitter_count > 0) {
+        // A random emitter, and a point spread evenly over its area
+        float index = floor(random(3.0) * float(u_emitter_count));
+        vec4 emitter = texture2D(u_emitters, vec2((index + 0.5) / float(u_emitter_count), 0.5));
+        float angle = 6.2831853 * position.x;
+        position = emitter.xy + sqrt(position.y) * emitter.zw * vec2(cos(angle), sin(angle));
+    }
+
+    return vec4(position, 0.0, u_lifetime * (0.5 + random(4.0)));
+}
+
+void main() {
+    vec4 particle = texture2D(u_particles, v_uv);
+
+    // Midpoint integration along the velocity
+    vec2 midpoint = particle.xy + 0.5 * u_delta_time * velocity(particle.xy);
+    vec2 position = particle.xy + u_delta_time * velocity(midpoint);
+    if (u_period.x > 0.0) { position.x = fract(position.x); }
+    if (u_period.y > 0.0) { position.y = fract(position.y); }
+
+    float age = particle.z + u_delta_time;
+    bool outside = any(lessThan(position, vec2(0.0))) || any(greaterThan(position, vec2(1.0)));
+    if (age >= particle.w || outside || texture2D(u_obstacles, position).x < 0.5) {
+        gl_FragColor = emit();
+        return;
+    }
+
+    gl_FragColor = vec4(position, age, particle.w);
+}
\ No newline at end of file
@@ -3,13 +3,23 @@ precision highp sampler2D;
 
 varying vec2 v_uv;
 
+const int PERIODIC = 2;
+const int OUTFLOW = 3;
+
+uniform ivec4 u_boundary;
 uniform float u_alpha;
 uniform float u_r_beta;
+uniform float u_omega;
 uniform vec2 u_resolution;
 uniform sampler2D u_x;
 uniform sampler2D u_b;
 uniform sampler2D u_obstacles;
 
+// Pressure just outside of an edge, zero at an outflow
+vec4 boundary(int mode, vec4 x_c) {
+    return mode == OUTFLOW ? -x_c : x_c;
+}
+
 void main() {
     vec2 l = v_uv - vec2(1.0, 0.0) / u_resolution;
     vec2 r = v_uv + vec2(1.0, 0.0) / u_resolution;
@@ -27,12 +37,19 @@ void main() {
     float o_b = texture2D(u_obstacles, b).x;
     float o_t = texture2D(u_obstacles, t).x;
 
-    if (gl_FragCoord.x < 1.0 || o_l < 0.5) { x_l = x_c; }
-    else if (gl_FragCoord.x > u_resolution.x - 1.0 || o_r < 0.5) { x_r = x_c; }
+    if (gl_FragCoord.x < 1.0 && u_boundary.x != PERIODIC) { x_l = boundary(u_boundary.x, x_c); }
+    else if (o_l < 0.5) { x_l = x_c; }
+    else if (gl_FragCoord.x > u_resolution.x - 1.0 && u_boundary.y != PERIODIC) { x_r = boundary(u_boundary.y, x_c); }
+    else if (o_r < 0.5) { x_r = x_c; }
 
-    if (gl_FragCoord.y < 1.0 || o_b < 0.5) { x_b = x_c; }
-    else if (gl_FragCoord.y > u_resolution.y - 1.0 || o_t < 0.5) { x_t = x_c; }
+    if (gl_FragCoord.y < 1.0 && u_boundary.z != PERIODIC) { x_b = boundary(u_boundary.z, x_c); }
+    else if (o_b < 0.5) { x_b = x_c; }
+    else if (gl_FragCoord.y > u_resolution.y - 1.0 && u_boundary.w != PERIODIC) { x_t = boundary(u_boundary.w, x_c); }
+    else if (o_t < 0.5) { x_t = x_c; }
     
     vec4 bC = texture2D(u_b, v_uv);
-    gl_FragColor = (x_l + x_r + x_b + x_t + u_alpha * bC) * u_r_beta;
+    vec4 jacobi = (x_l + x_r + x_b + x_t + u_alpha * bC) * u_r_beta;
+
+    // Weighted Jacobi, which only moves part of the way for an `u_omega` below one
+    gl_FragColor = x_c * (1.0 - u_omega) + jacobi * u_omega;
 } 
\ No newline at end of file
@@ -0,0 +1,61 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+const int PERIODIC = 2;
+const int OUTFLOW = 3;
+
+uniform ivec4 u_boundary;
+uniform float u_alpha;
+uniform float u_omega;
+uniform int u_parity;
+uniform vec2 u_resolution;
+uniform sampler2D u_x;
+uniform sampler2D u_b;
+uniform sampler2D u_obstacles;
+
+void main() {
+    vec4 x_c = texture2D(u_x, v_uv);
+
+    // Only the texels of one color are updated, from their neighbours that all have the other one
+    if (int(mod(floor(gl_FragCoord.x) + floor(gl_FragCoord.y), 2.0)) != u_parity) {
+        gl_FragColor = x_c;
+        return;
+    }
+
+    vec2 l = v_uv - vec2(1.0, 0.0) / u_resolution;
+    vec2 r = v_uv + vec2(1.0, 0.0) / u_resolution;
+    vec2 b = v_uv - vec2(0.0, 1.0) / u_resolution;
+    vec2 t = v_uv + vec2(0.0, 1.0) / u_resolution;
+
+    vec4 x_l = texture2D(u_x, l);
+    vec4 x_r = texture2D(u_x, r);
+    vec4 x_b = texture2D(u_x, b);
+    vec4 x_t = texture2D(u_x, t);
+
+    float o_l = texture2D(u_obstacles, l).x;
+    float o_r = texture2D(u_obstacles, r).x;
+    float o_b = texture2D(u_obstacles, b).x;
+    float o_t = texture2D(u_obstacles, t).x;
+
+    // The neighbours that `pressure_solver.glsl` replaces by the pressure of the texel itself (or its
+    // negation at an outflow) move to the diagonal instead, which over-relaxation needs to converge
+    float diagonal = 4.0;
+    if (gl_FragCoord.x < 1.0 && u_boundary.x != PERIODIC) { x_l = vec4(0.0); diagonal += u_boundary.x == OUTFLOW ? 1.0 : -1.0; }
+    else if (o_l < 0.5) { x_l = vec4(0.0); diagonal -= 1.0; }
+    else if (gl_FragCoord.x > u_resolution.x - 1.0 && u_boundary.y != PERIODIC) { x_r = vec4(0.0); diagonal += u_boundary.y == OUTFLOW ? 1.0 : -1.0; }
+    else if (o_r < 0.5) { x_r = vec4(0.0); diagonal -= 1.0; }
+
+    if (gl_FragCoord.y < 1.0 && u_boundary.z != PERIODIC) { x_b = vec4(0.0); diagonal += u_boundary.z == OUTFLOW ? 1.0 : -1.0; }
+    else if (o_b < 0.5) { x_b = vec4(0.0); diagonal -= 1.0; }
+    else if (gl_FragCoord.y > u_resolution.y - 1.0 && u_boundary.w != PERIODIC) { x_t = vec4(0.0); diagonal += u_boundary.w == OUTFLOW ? 1.0 : -1.0; }
+    else if (o_t < 0.5) { x_t = vec4(0.0); diagonal -= 1.0; }
+
+    vec4 bC = texture2D(u_b, v_uv);
+    vec4 gauss_seidel = (x_l + x_r + x_b + x_t + u_alpha * bC) / diagonal;
+    // Solid texels are not over-relaxed, which would blow them up. Fluid texels do not depend on them, but
+    // the gradient along the obstacles does.
+    float omega = texture2D(u_obstacles, v_uv).x < 0.5 ? 1.0 : u_omega;
+    gl_FragColor = x_c + omega * (gauss_seidel - x_c);
+}
\ No newline at end of file
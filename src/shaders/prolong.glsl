@@ -0,0 +1,12 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform sampler2D u_x;
+uniform sampler2D u_correction;
+
+// Add the correction of the coarser level, interpolated bilinearly
+void main() {
+    gl_FragColor = texture2D(u_x, v_uv) + texture2D(u_correction, v_uv);
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+precision highp float;
+precision highp sampler2D;
+
+uniform bool u_square;
+uniform vec2 u_resolution;
+uniform sampler2D u_texture;
+
+// Sum of the (squared) texels of `u_texture` that a texel of the half as large target covers
+void main() {
+    vec2 texel = floor(gl_FragCoord.xy) * 2.0 + 0.5;
+
+    vec4 sum = vec4(0.0);
+    for (int i = 0; i < 4; i++) {
+        vec2 coord = texel + vec2(mod(float(i), 2.0), floor(float(i) / 2.0));
+        if (coord.x < u_resolution.x && coord.y < u_resolution.y) {
+            vec4 value = texture2D(u_texture, coord / u_resolution);
+            sum += u_square ? value * value : value;
+        }
+    }
+
+    gl_FragColor = sum;
+}
\ No newline at end of file
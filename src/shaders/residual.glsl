@@ -0,0 +1,56 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+const int PERIODIC = 2;
+const int OUTFLOW = 3;
+
+uniform ivec4 u_boundary;
+uniform float u_alpha;
+uniform vec2 u_resolution;
+uniform sampler2D u_x;
+uniform sampler2D u_b;
+uniform sampler2D u_obstacles;
+
+// Pressure just outside of an edge, zero at an outflow
+vec4 boundary(int mode, vec4 x_c) {
+    return mode == OUTFLOW ? -x_c : x_c;
+}
+
+void main() {
+    if (texture2D(u_obstacles, v_uv).x < 0.5) {
+        gl_FragColor = vec4(0.0);
+        return;
+    }
+
+    vec2 l = v_uv - vec2(1.0, 0.0) / u_resolution;
+    vec2 r = v_uv + vec2(1.0, 0.0) / u_resolution;
+    vec2 b = v_uv - vec2(0.0, 1.0) / u_resolution;
+    vec2 t = v_uv + vec2(0.0, 1.0) / u_resolution;
+
+    vec4 x_l = texture2D(u_x, l);
+    vec4 x_r = texture2D(u_x, r);
+    vec4 x_b = texture2D(u_x, b);
+    vec4 x_t = texture2D(u_x, t);
+    vec4 x_c = texture2D(u_x, v_uv);
+
+    float o_l = texture2D(u_obstacles, l).x;
+    float o_r = texture2D(u_obstacles, r).x;
+    float o_b = texture2D(u_obstacles, b).x;
+    float o_t = texture2D(u_obstacles, t).x;
+
+    if (gl_FragCoord.x < 1.0 && u_boundary.x != PERIODIC) { x_l = boundary(u_boundary.x, x_c); }
+    else if (o_l < 0.5) { x_l = x_c; }
+    else if (gl_FragCoord.x > u_resolution.x - 1.0 && u_boundary.y != PERIODIC) { x_r = boundary(u_boundary.y, x_c); }
+    else if (o_r < 0.5) { x_r = x_c; }
+
+    if (gl_FragCoord.y < 1.0 && u_boundary.z != PERIODIC) { x_b = boundary(u_boundary.z, x_c); }
+    else if (o_b < 0.5) { x_b = x_c; }
+    else if (gl_FragCoord.y > u_resolution.y - 1.0 && u_boundary.w != PERIODIC) { x_t = boundary(u_boundary.w, x_c); }
+    else if (o_t < 0.5) { x_t = x_c; }
+
+    // What is left of `b` once the Jacobi update of `x` would no longer change it
+    vec4 bC = texture2D(u_b, v_uv);
+    gl_FragColor = bC + (x_l + x_r + x_b + x_t - 4.0 * x_c) / u_alpha;
+}
\ No newline at end of file
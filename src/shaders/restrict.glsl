@@ -0,0 +1,18 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform vec2 u_resolution;
+uniform sampler2D u_texture;
+
+// Average of the four texels of the finer level that a texel of the coarser level covers
+void main() {
+    vec2 offset = 0.25 / u_resolution;
+    gl_FragColor = 0.25 * (
+        texture2D(u_texture, v_uv + vec2(-offset.x, -offset.y)) +
+        texture2D(u_texture, v_uv + vec2(offset.x, -offset.y)) +
+        texture2D(u_texture, v_uv + vec2(-offset.x, offset.y)) +
+        texture2D(u_texture, v_uv + vec2(offset.x, offset.y))
+    );
+}
\ No newline at end of file
@@ -5,6 +5,7 @@ varying vec2 v_uv;
 
 uniform float u_scaled_radius;
 uniform vec2 u_position;
+uniform vec2 u_period;
 uniform vec3 u_color;
 uniform sampler2D u_texture;
 uniform sampler2D u_obstacles;
@@ -12,6 +13,11 @@ uniform sampler2D u_obstacles;
 void main() {
     vec3 color = texture2D(u_texture, v_uv).xyz;
     vec2 distance = gl_FragCoord.xy - u_position;
+
+    // Splats wrap around periodic axes, which have a period larger than zero
+    if (u_period.x > 0.0) { distance.x -= u_period.x * floor(distance.x / u_period.x + 0.5); }
+    if (u_period.y > 0.0) { distance.y -= u_period.y * floor(distance.y / u_period.y + 0.5); }
+
     color += u_color * exp(-dot(distance, distance) / u_scaled_radius);
     float obstacle = texture2D(u_obstacles, v_uv).x;
     gl_FragColor = vec4(color * obstacle, 0.0);
@@ -0,0 +1,23 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform int u_count;
+uniform float u_radius;
+uniform float u_length;
+uniform vec2 u_resolution;
+uniform vec3 u_color;
+uniform sampler2D u_texture;
+
+void main() {
+    vec4 value = texture2D(u_texture, v_uv);
+
+    float spacing = u_resolution.y / float(u_count);
+    float line = (floor(gl_FragCoord.y / spacing) + 0.5) * spacing;
+    if (gl_FragCoord.x < u_length && abs(gl_FragCoord.y - line) < u_radius) {
+        value = vec4(u_color, value.w);
+    }
+
+    gl_FragColor = value;
+}
\ No newline at end of file
@@ -0,0 +1,31 @@
+precision highp float;
+precision highp sampler2D;
+
+attribute vec2 a_coordinates;
+varying vec4 v_color;
+
+uniform float u_point_spacing;
+uniform float u_max_speed;
+uniform sampler2D u_lines;
+
+// Every segment has two vertices at the texel of its first point in `u_lines`, the one at the end of the
+// segment is shifted by two along x
+void main() {
+    float end = step(1.5, a_coordinates.x);
+    vec2 coordinates = a_coordinates - vec2(2.0 * end, 0.0);
+    vec4 start = texture2D(u_lines, coordinates);
+    vec4 next = texture2D(u_lines, coordinates + vec2(u_point_spacing, 0.0));
+    vec4 point = mix(start, next, end);
+
+    // Segments that stop, wrap around a periodic edge or start at the last point are not drawn, lines fade
+    // along the flow
+    float wrapped = step(0.5, length(next.xy - start.xy));
+    float last = 1.0 - step(coordinates.x + u_point_spacing, 1.0);
+    float alpha = start.w * next.w * (1.0 - wrapped) * (1.0 - last) * (1.0 - 0.75 * coordinates.x);
+
+    float speed = clamp(point.z / u_max_speed, 0.0, 1.0);
+    vec3 color = clamp(vec3(1.5) - abs(4.0 * speed - vec3(3.0, 2.0, 1.0)), 0.0, 1.0);
+
+    v_color = vec4(color, alpha);
+    gl_Position = vec4(2.0 * point.xy - 1.0, 0.0, 1.0);
+}
\ No newline at end of file
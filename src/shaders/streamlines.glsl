@@ -0,0 +1,67 @@
+precision highp float;
+precision highp sampler2D;
+
+varying vec2 v_uv;
+
+uniform float u_step_size;
+uniform int u_seed_count;
+uniform vec2 u_grid;
+uniform vec2 u_period;
+uniform vec2 u_resolution;
+uniform sampler2D u_seeds;
+uniform sampler2D u_velocity;
+uniform sampler2D u_obstacles;
+
+const int MAX_STREAMLINE_STEPS = 128;
+
+// Direction of the flow with a length of one step, zero where the fluid stands still
+vec2 direction(vec2 position) {
+    vec2 velocity = texture2D(u_velocity, position).xy;
+    float speed = length(velocity);
+    if (speed < 0.001) {
+        return vec2(0.0);
+    }
+
+    return u_step_size * velocity / speed / u_resolution;
+}
+
+vec2 seed(float index) {
+    if (u_seed_count > 0) {
+        return texture2D(u_seeds, vec2((index + 0.5) / float(u_seed_count), 0.5)).xy;
+    }
+
+    vec2 cell = vec2(mod(index, u_grid.x), floor(index / u_grid.x));
+    return (cell + 0.5) / u_grid;
+}
+
+// Every row is a streamline, every texel of it the position after as many steps as its column
+void main() {
+    int point = int(gl_FragCoord.x);
+    vec2 position = seed(floor(gl_FragCoord.y));
+    float alive = 1.0;
+
+    for (int i = 0; i < MAX_STREAMLINE_STEPS; i++) {
+        if (i >= point) {
+            break;
+        }
+
+        // RK4 along the direction of the flow
+        vec2 k1 = direction(position);
+        vec2 k2 = direction(position + 0.5 * k1);
+        vec2 k3 = direction(position + 0.5 * k2);
+        vec2 k4 = direction(position + k3);
+        vec2 next = position + (k1 + 2.0 * k2 + 2.0 * k3 + k4) / 6.0;
+        if (u_period.x > 0.0) { next.x = fract(next.x); }
+        if (u_period.y > 0.0) { next.y = fract(next.y); }
+
+        bool outside = any(lessThan(next, vec2(0.0))) || any(greaterThan(next, vec2(1.0)));
+        if (k1 == vec2(0.0) || outside || texture2D(u_obstacles, next).x < 0.5) {
+            alive = 0.0;
+            break;
+        }
+        position = next;
+    }
+
+    float speed = length(texture2D(u_velocity, position).xy);
+    gl_FragColor = vec4(position, speed, alive);
+}
\ No newline at end of file
@@ -0,0 +1,2599 @@
+use crate::backend::{ BackendTexture, Pass, PingPong, SimulationBackend };
+use crate::boundary::{ Boundaries, BoundaryMode };
+use crate::colormap::Colormap;
+use crate::conjugate_gradient;
+use crate::config::SimulationConfig;
+use crate::forces::{ ForceHistory, ObstacleForce };
+use crate::grid::Filter;
+use crate::mask::Mask;
+use crate::multigrid::Multigrid;
+use crate::obstacle::{ Obstacle, Shape, MAX_POLYGON_VERTICES };
+use crate::particles::Particles;
+use crate::residual::ResidualNorm;
+use crate::rigid_body::RigidBody;
+use crate::snapshot::{ Field, Snapshot };
+use crate::streamlines::Streamlines;
+use crate::wind_tunnel::WindTunnel;
+use crate::{ Advection, Mode, Resolution, Solver, MIN_PRESSURE_ITERATIONS, FPS_30, FPS_60, MAX_SUBSTEPS };
+
+/// Brightness of the dye under the streamlines
+const STREAMLINE_DYE: f32 = 0.3;
+/// Jacobi or SOR iterations between two measurements of the residual when solving to a tolerance
+const RESIDUAL_INTERVAL: usize = 10;
+/// Frames between two readbacks of the field of a diagnostic mode to find its range
+const RANGE_INTERVAL: u32 = 15;
+
+/// The fluid simulation, independent of the [backend](SimulationBackend) it runs on
+pub struct Simulation<B: SimulationBackend> {
+    backend: B,
+    width: u32,
+    height: u32,
+    sim_resolution: Resolution,
+    dye_resolution: Resolution,
+    velocity_buffer: PingPong<B::Texture>,
+    pressure_buffer: PingPong<B::Texture>,
+    dye_buffer: PingPong<B::Texture>,
+    obstacle_buffer: PingPong<B::Texture>,
+    obstacle_velocity_buffer: PingPong<B::Texture>,
+    temp_store: B::Texture,
+    /// Scratch texture of the size of the dye for the corrected [advection](Advection) schemes
+    dye_store: Option<B::Texture>,
+    multigrid: Option<Multigrid<B::Texture>>,
+    residual_norm: Option<ResidualNorm<B::Texture>>,
+    pressure_iterations: usize,
+    pressure_residual: Option<f32>,
+    last_time: f32,
+    timestep: f32,
+    accumulator: f32,
+    obstacle_list: Vec<(u32, Obstacle)>,
+    next_obstacle_id: u32,
+    boundaries: Boundaries,
+    wind_tunnel: Option<WindTunnel>,
+    measure_forces: bool,
+    forces: Vec<ObstacleForce>,
+    force_history: ForceHistory,
+    force_store: Option<B::Texture>,
+    bodies: Vec<(u32, RigidBody)>,
+    particles: Option<Particles>,
+    particle_buffer: Option<PingPong<B::Texture>>,
+    particle_emitters: Option<B::Texture>,
+    particle_seed: u32,
+    streamlines: Streamlines,
+    streamline_store: Option<B::Texture>,
+    streamline_seeds: Option<B::Texture>,
+    overlay: Option<B::Texture>,
+    legend: Option<[f32; 2]>,
+    /// The diagnostic mode the range was found for, its range and the frames it has been drawn with since
+    diagnostic_range: Option<(Mode, [f32; 2], u32)>,
+}
+
+impl<B: SimulationBackend> Simulation<B> {
+    /// Create a new simulation
+    ///
+    /// # Arguments
+    /// * `backend` - The [backend](SimulationBackend) to run the simulation on
+    /// * `width` - Width of the simulated domain in pixels
+    /// * `height` - Height of the simulated domain in pixels
+    /// * `sim_resolution` - A [Resolution](Resolution) describing the scaling of the simulation in relation to the domain size
+    /// * `dye_resolution` - A [Resolution](Resolution) describing the scaling of the dye in relation to the domain size
+    pub fn new(
+        mut backend: B,
+        width: u32,
+        height: u32,
+        sim_resolution: Resolution,
+        dye_resolution: Resolution,
+    ) -> Result<Simulation<B>, B::Error> {
+        let (sim_width, sim_height) = Simulation::<B>::resolution_size(width, height, sim_resolution);
+        let velocity_buffer = PingPong::new(
+            &mut backend,
+            sim_width,
+            sim_height,
+            Some(Filter::Linear),
+        )?;
+
+        let pressure_buffer = PingPong::new(
+            &mut backend,
+            sim_width,
+            sim_height,
+            Some(Filter::Linear),
+        )?;
+
+        let temp_store = backend.create_texture(
+            sim_width,
+            sim_height,
+            Filter::Linear,
+        )?;
+
+        let (dye_width, dye_height) = Simulation::<B>::resolution_size(width, height, dye_resolution);
+        let dye_buffer = PingPong::new(
+            &mut backend,
+            dye_width,
+            dye_height,
+            Some(Filter::Linear),
+        )?;
+
+        let obstacle_buffer = PingPong::new(
+            &mut backend,
+            dye_width,
+            dye_height,
+            Some(Filter::Nearest),
+        )?;
+
+        let obstacle_velocity_buffer = PingPong::new(
+            &mut backend,
+            dye_width,
+            dye_height,
+            Some(Filter::Nearest),
+        )?;
+
+        let mut simulation = Simulation {
+            backend,
+            width,
+            height,
+            sim_resolution,
+            dye_resolution,
+            velocity_buffer,
+            pressure_buffer,
+            dye_buffer,
+            obstacle_buffer,
+            obstacle_velocity_buffer,
+            temp_store,
+            dye_store: None,
+            multigrid: None,
+            residual_norm: None,
+            pressure_iterations: 0,
+            pressure_residual: None,
+            last_time: 0.0,
+            timestep: FPS_60,
+            accumulator: 0.0,
+            obstacle_list: Vec::new(),
+            next_obstacle_id: 0,
+            boundaries: Boundaries::default(),
+            wind_tunnel: None,
+            measure_forces: false,
+            forces: Vec::new(),
+            force_history: ForceHistory::default(),
+            force_store: None,
+            bodies: Vec::new(),
+            particles: None,
+            particle_buffer: None,
+            particle_emitters: None,
+            particle_seed: 0,
+            streamlines: Streamlines::default(),
+            streamline_store: None,
+            streamline_seeds: None,
+            overlay: None,
+            legend: None,
+            diagnostic_range: None,
+        };
+
+        simulation.composite_obstacles()?;
+
+        Ok(simulation)
+    }
+
+    /// Update the simulation
+    ///
+    /// Runs one step of the simulation, but does not draw anything. The length of the step is the time
+    /// since the last update, capped at 1/30 of a second, so the result depends on the frame rate. Use
+    /// [step](Simulation::step) or [step_fixed](Simulation::step_fixed) for reproducible runs.
+    ///
+    /// # Arguments
+    /// * `time` - Current time (may be current datetime or time since the beginning of the program run but needs to be consistent)
+    /// * `config` - The [configuration](SimulationConfig) of the simulation
+    pub fn update(
+        &mut self,
+        time: f32,
+        config: &SimulationConfig,
+    ) -> Result<(), B::Error> {
+        let delta_time = FPS_30.min(time - self.last_time);
+        self.last_time = time;
+
+        if config.pause() {
+            return Ok(());
+        }
+
+        self.advance(delta_time, config)
+    }
+
+    /// Step the simulation
+    ///
+    /// Adds `delta_time` to an accumulator and advances the simulation in steps of exactly the
+    /// [timestep](Simulation::set_timestep) for as long as the accumulator allows. Physics is thereby
+    /// independent of how often this is called. At most `MAX_SUBSTEPS` steps are taken per call, any
+    /// remaining backlog is dropped. Nothing is accumulated while the simulation is paused.
+    ///
+    /// # Arguments
+    /// * `delta_time` - Time that has passed since the last call (>= 0)
+    /// * `config` - The [configuration](SimulationConfig) of the simulation
+    ///
+    /// # Returns
+    /// The number of fixed steps that were taken.
+    pub fn step(
+        &mut self,
+        delta_time: f32,
+        config: &SimulationConfig,
+    ) -> Result<usize, B::Error> {
+        if config.pause() {
+            return Ok(0);
+        }
+
+        self.accumulator += delta_time.max(0.0);
+
+        let mut substeps = 0;
+        while self.accumulator >= self.timestep && substeps < MAX_SUBSTEPS {
+            self.advance(self.timestep, config)?;
+            self.accumulator -= self.timestep;
+            substeps += 1;
+        }
+
+        if substeps == MAX_SUBSTEPS {
+            self.accumulator = self.accumulator.min(self.timestep);
+        }
+
+        Ok(substeps)
+    }
+
+    /// Step the simulation a fixed number of times
+    ///
+    /// Advances the simulation by `substeps` steps of exactly the [timestep](Simulation::set_timestep),
+    /// without touching the accumulator used by [step](Simulation::step). Nothing happens while the
+    /// simulation is paused.
+    ///
+    /// # Arguments
+    /// * `substeps` - Number of steps to take
+    /// * `config` - The [configuration](SimulationConfig) of the simulation
+    pub fn step_fixed(
+        &mut self,
+        substeps: usize,
+        config: &SimulationConfig,
+    ) -> Result<(), B::Error> {
+        if config.pause() {
+            return Ok(());
+        }
+
+        for _ in 0..substeps {
+            self.advance(self.timestep, config)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the fixed timestep used by [step](Simulation::step) and [step_fixed](Simulation::step_fixed)
+    ///
+    /// Defaults to 1/60 of a second. Values that are not positive are ignored.
+    pub fn set_timestep(&mut self, timestep: f32) {
+        if timestep > 0.0 {
+            self.timestep = timestep;
+        }
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    fn advance(
+        &mut self,
+        delta_time: f32,
+        config: &SimulationConfig,
+    ) -> Result<(), B::Error> {
+        let sim_resolution = [
+            self.velocity_buffer.read().width() as f32,
+            self.velocity_buffer.read().height() as f32,
+        ];
+
+        // UPDATE BODIES
+        // Bodies move by the forces of the previous step, so that the dye colors them where they are drawn
+        if !self.bodies.is_empty() {
+            self.move_bodies(delta_time)?;
+        }
+
+        // UPDATE VELOCITY
+        // The sign makes a positive curl amount reinforce the existing vortices
+        self.vorticity_confinement(
+            &sim_resolution,
+            -config.curl(),
+        )?;
+
+        Simulation::advect(
+            &mut self.backend,
+            config.advection(),
+            &sim_resolution,
+            delta_time,
+            config.viscosity(),
+            None,
+            &mut self.velocity_buffer,
+            Some(&mut self.temp_store),
+            self.obstacle_buffer.read(),
+        )?;
+
+        self.project_velocity(
+            &sim_resolution,
+            config,
+        )?;
+
+        // UPDATE DYE
+        let dye_size = [self.dye_buffer.read().width(), self.dye_buffer.read().height()];
+        let store_size = self.dye_store.as_ref().map(|store| [store.width(), store.height()]);
+        if config.advection() == Advection::SEMI_LAGRANGIAN || store_size != Some(dye_size) {
+            if let Some(old) = self.dye_store.take() {
+                self.backend.delete_texture(old);
+            }
+            if config.advection() != Advection::SEMI_LAGRANGIAN {
+                let mut store = self.backend.create_texture(dye_size[0], dye_size[1], Filter::Linear)?;
+                self.backend.set_wrap(&mut store, self.boundaries.wrap());
+                self.dye_store = Some(store);
+            }
+        }
+
+        Simulation::color_obstacle(
+            &mut self.backend,
+            self.obstacle_buffer.read(),
+            &mut self.dye_buffer,
+            0.0,
+        )?;
+
+        Simulation::advect(
+            &mut self.backend,
+            config.advection(),
+            &sim_resolution,
+            delta_time,
+            config.dissipation(),
+            Some(&self.velocity_buffer),
+            &mut self.dye_buffer,
+            self.dye_store.as_mut(),
+            self.obstacle_buffer.read(),
+        )?;
+
+        Simulation::color_obstacle(
+            &mut self.backend,
+            self.obstacle_buffer.read(),
+            &mut self.dye_buffer,
+            1.0,
+        )?;
+
+        if let Some(wind_tunnel) = &self.wind_tunnel {
+            Simulation::streaklines(
+                &mut self.backend,
+                wind_tunnel,
+                self.dye_resolution as u32 as f32,
+                delta_time,
+                &mut self.dye_buffer,
+            )?;
+        }
+
+        if self.measure_forces || !self.bodies.is_empty() {
+            self.measure_forces(delta_time, config)?;
+        }
+
+        if self.particles.is_some() {
+            self.advect_particles(&sim_resolution, delta_time)?;
+        }
+
+        Ok(())
+    }
+
+    /// Draw the simulation to the output of the backend
+    ///
+    /// With [streamlines](Mode::STREAMLINES), a diagnostic mode or [particles](Simulation::set_particles)
+    /// the dye or the field is drawn to a texture of the size of the dye first, and the lines and particles
+    /// are drawn over it. Diagnostic modes read the field back from the backend to find its range, once every
+    /// few frames and whenever the mode changes.
+    pub fn draw(&mut self, mode: Mode) -> Result<(), B::Error> {
+        self.legend = None;
+
+        // The texture copied under the lines and particles, the velocity if set and the dye otherwise
+        let copy = match mode {
+            Mode::DYE => Some((false, 1.0, 0.0)),
+            Mode::VELOCITY => Some((true, 0.1, 0.5)),
+            Mode::STREAMLINES => Some((false, STREAMLINE_DYE, 0.0)),
+            _ => None,
+        };
+
+        if let Some((velocity, factor, offset)) = copy {
+            if mode != Mode::STREAMLINES && self.particles.is_none() {
+                let texture = if velocity { self.velocity_buffer.read() } else { self.dye_buffer.read() };
+                return self.backend.present(texture, factor, offset);
+            }
+        }
+
+        let (width, height) = (self.dye_buffer.read().width(), self.dye_buffer.read().height());
+        let mut overlay = match self.overlay.take() {
+            Some(overlay) if overlay.width() == width && overlay.height() == height => overlay,
+            old => {
+                if let Some(old) = old {
+                    self.backend.delete_texture(old);
+                }
+                self.backend.create_texture(
+                    width,
+                    height,
+                    Filter::Linear,
+                )?
+            }
+        };
+
+        let result = match copy {
+            Some((velocity, factor, offset)) => self.backend.dispatch(
+                Pass::Copy {
+                    factor,
+                    offset,
+                    texture: if velocity { self.velocity_buffer.read() } else { self.dye_buffer.read() },
+                },
+                &mut overlay,
+            ),
+            None => self.draw_diagnostic(mode, &mut overlay),
+        }
+            .and_then(|()| match mode {
+                Mode::STREAMLINES => self.draw_streamlines(&mut overlay),
+                _ => Ok(()),
+            })
+            .and_then(|()| self.draw_particles(&mut overlay))
+            .and_then(|()| self.backend.present(&overlay, 1.0, 0.0));
+        self.overlay = Some(overlay);
+
+        result
+    }
+
+    /// The range of the field drawn by the last [draw](Simulation::draw) in a diagnostic [mode](Mode), which
+    /// maps onto the ends of the color map
+    ///
+    /// The speed is in pixels per second, the divergence and the curl per second, and the pressure in the
+    /// units of the solver. `None` for the other modes.
+    pub fn legend(&self) -> Option<[f32; 2]> {
+        self.legend
+    }
+
+    /// Number of iterations, or V-cycles of the [multigrid](Solver::MULTIGRID) solver, of the pressure solve of
+    /// the last step
+    pub fn pressure_iterations(&self) -> usize {
+        self.pressure_iterations
+    }
+
+    /// Residual left over by the pressure solve of the last step, in the units of the
+    /// [tolerance](SimulationConfig::tolerance)
+    ///
+    /// `None` unless the solve ran to a tolerance, since the residual is not measured otherwise.
+    pub fn pressure_residual(&self) -> Option<f32> {
+        self.pressure_residual
+    }
+
+    /// Resize the simulation
+    ///
+    /// # Arguments
+    /// * `width` - New width of the simulated domain in pixels
+    /// * `height` - New height of the simulated domain in pixels
+    /// * `sim_resolution` - A [Resolution](Resolution) describing the scaling of the simulation in relation to the domain size
+    /// * `dye_resolution` - A [Resolution](Resolution) describing the scaling of the dye in relation to the domain size
+    pub fn resize(
+        &mut self,
+        width: u32,
+        height: u32,
+        sim_resolution: Resolution,
+        dye_resolution: Resolution,
+    ) -> Result<(), B::Error> {
+        let backend = &mut self.backend;
+        self.width = width;
+        self.height = height;
+        self.sim_resolution = sim_resolution;
+        self.dye_resolution = dye_resolution;
+
+        // SIMULATION
+        let (width, height) = Simulation::<B>::resolution_size(self.width, self.height, sim_resolution);
+        self.velocity_buffer.resize(
+            backend,
+            true,
+            width,
+            height,
+        )?;
+
+        self.pressure_buffer.resize(
+            backend,
+            false,
+            width,
+            height,
+        )?;
+
+        if width != self.temp_store.width() || height != self.temp_store.height() {
+            let mut temp_store = backend.create_texture(
+                width,
+                height,
+                Filter::Linear,
+            )?;
+            backend.set_wrap(&mut temp_store, self.boundaries.wrap());
+            backend.delete_texture(std::mem::replace(&mut self.temp_store, temp_store));
+        }
+
+        // DYE
+        let (width, height) = Simulation::<B>::resolution_size(self.width, self.height, dye_resolution);
+        self.dye_buffer.resize(
+            backend,
+            true,
+            width,
+            height,
+        )?;
+
+        self.obstacle_buffer.resize(
+            backend,
+            false,
+            width,
+            height,
+        )?;
+
+        self.obstacle_velocity_buffer.resize(
+            backend,
+            false,
+            width,
+            height,
+        )?;
+
+        // Emitters and seeds are stored relative to the size of the domain
+        self.upload_emitters()?;
+        self.upload_streamline_seeds()?;
+
+        self.composite_obstacles()
+    }
+
+    /// Create a splat
+    ///
+    /// Adds a splat of force and color to the simulation.
+    ///
+    /// # Arguments
+    /// * `radius` - Radius of the splat in pixels
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `velocity` - A float array that should have two values, an x and a y velocity
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If either `position` or `velocity` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn splat(
+        &mut self,
+        radius: f32,
+        position: &[f32],
+        velocity: &[f32],
+        color: &[f32],
+    ) -> Result<(), B::Error> {
+        let periodic = self.boundaries.periodic();
+        let period = |texture: &B::Texture| [
+            if periodic[0] { texture.width() as f32 } else { 0.0 },
+            if periodic[1] { texture.height() as f32 } else { 0.0 },
+        ];
+
+        // APPLY FORCE
+        let resolution = self.sim_resolution as u32 as f32;
+        let (read, write) = self.velocity_buffer.split();
+        self.backend.dispatch(
+            Pass::Splat {
+                scaled_radius: radius / (resolution * resolution),
+                position: [position[0] / resolution, position[1] / resolution],
+                period: period(read),
+                color: [velocity[0] / resolution, velocity[1] / resolution, 0.0],
+                texture: read,
+                obstacles: self.obstacle_buffer.read(),
+            },
+            write,
+        )?;
+        self.velocity_buffer.swap();
+
+        // APPLY COLOR
+        let resolution = self.dye_resolution as u32 as f32;
+        let (read, write) = self.dye_buffer.split();
+        self.backend.dispatch(
+            Pass::Splat {
+                scaled_radius: radius / (resolution * resolution),
+                position: [position[0] / resolution, position[1] / resolution],
+                period: period(read),
+                color: [color[0], color[1], color[2]],
+                texture: read,
+                obstacles: self.obstacle_buffer.read(),
+            },
+            write,
+        )?;
+        self.dye_buffer.swap();
+
+        Ok(())
+    }
+
+    /// Add an obstacle
+    ///
+    /// Obstacles are drawn in the order they were added, so the color of a later obstacle covers that of
+    /// an earlier one where they overlap.
+    ///
+    /// # Returns
+    /// The id of the obstacle.
+    pub fn add_obstacle(&mut self, obstacle: Obstacle) -> Result<u32, B::Error> {
+        let id = self.next_obstacle_id;
+        self.next_obstacle_id += 1;
+        self.obstacle_list.push((id, obstacle));
+        self.composite_obstacles()?;
+
+        Ok(id)
+    }
+
+    /// Remove the obstacle with the id `id`
+    ///
+    /// # Returns
+    /// Whether there was such an obstacle.
+    pub fn remove_obstacle(&mut self, id: u32) -> Result<bool, B::Error> {
+        let len = self.obstacle_list.len();
+        self.obstacle_list.retain(|(obstacle_id, _)| *obstacle_id != id);
+        if self.obstacle_list.len() == len {
+            return Ok(false);
+        }
+
+        self.composite_obstacles()?;
+        Ok(true)
+    }
+
+    /// Replace the obstacle with the id `id`, keeping its place in the drawing order
+    ///
+    /// # Returns
+    /// Whether there was such an obstacle.
+    pub fn update_obstacle(&mut self, id: u32, obstacle: Obstacle) -> Result<bool, B::Error> {
+        match self.obstacle_list.iter_mut().find(|(obstacle_id, _)| *obstacle_id == id) {
+            Some((_, current)) => *current = obstacle,
+            None => return Ok(false),
+        }
+
+        self.composite_obstacles()?;
+        Ok(true)
+    }
+
+    /// Remove every obstacle
+    pub fn clear_obstacles(&mut self) -> Result<(), B::Error> {
+        self.obstacle_list.clear();
+        self.composite_obstacles()
+    }
+
+    /// The obstacle with the id `id`
+    pub fn obstacle(&self, id: u32) -> Option<&Obstacle> {
+        self.obstacle_list
+            .iter()
+            .find(|(obstacle_id, _)| *obstacle_id == id)
+            .map(|(_, obstacle)| obstacle)
+    }
+
+    /// Every obstacle together with its id, in drawing order
+    pub fn obstacle_list(&self) -> &[(u32, Obstacle)] {
+        &self.obstacle_list
+    }
+
+    /// Set obstacle
+    ///
+    /// Set either a circular or square obstacle, replacing all other obstacles.
+    ///
+    /// # Arguments
+    /// * `radius` - Radius of the obstacle in pixels (in the case of a square it is half the sidelength in pixels). If this value is `None`, no obstacle will be set
+    /// * `position` - A float array that should have two values, an x and a y position in domain coordinates
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    /// * `is_circle` - A boolean value deciding whether the obstacle is a circle or a square
+    /// * `velocity` - A float array that should have two values, the x and y velocity of the obstacle in pixels per second. The obstacle stands still if this value is `None`
+    ///
+    /// # Panics
+    /// If `position` or `velocity` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn set_obstacle(
+        &mut self,
+        radius: Option<f32>,
+        position: &[f32],
+        color: &[f32],
+        is_circle: bool,
+        velocity: Option<&[f32]>,
+    ) -> Result<(), B::Error> {
+        self.obstacle_list.clear();
+
+        if let Some(radius) = radius {
+            let mut obstacle = match is_circle {
+                true => Obstacle::circle(radius, position, color),
+                false => Obstacle::square(radius, position, 0.0, color),
+            };
+            if let Some(velocity) = velocity {
+                obstacle.set_linear_velocity(velocity);
+            }
+            self.obstacle_list.push((self.next_obstacle_id, obstacle));
+            self.next_obstacle_id += 1;
+        }
+
+        self.composite_obstacles()
+    }
+
+    /// Set the boundary modes of the edges of the domain
+    pub fn set_boundaries(&mut self, boundaries: Boundaries) {
+        self.boundaries = boundaries;
+        self.apply_wrap();
+    }
+
+    pub fn boundaries(&self) -> &Boundaries {
+        &self.boundaries
+    }
+
+    /// Set the wind tunnel
+    ///
+    /// With a [wind tunnel](WindTunnel) the fluid enters through the left edge and leaves through the
+    /// right edge, which replaces the [boundaries](Simulation::set_boundaries). `None` turns every edge
+    /// back into a free-slip wall.
+    pub fn set_wind_tunnel(&mut self, wind_tunnel: Option<WindTunnel>) {
+        self.set_boundaries(wind_tunnel.as_ref().map(WindTunnel::boundaries).unwrap_or_default());
+        self.wind_tunnel = wind_tunnel;
+    }
+
+    pub fn wind_tunnel(&self) -> Option<&WindTunnel> {
+        self.wind_tunnel.as_ref()
+    }
+
+    /// Let the obstacle with the id `id` float in the fluid as a [rigid body](RigidBody), or pin it again with `None`
+    ///
+    /// Rigid bodies need the forces of the fluid, so the simulation measures them while there are any.
+    /// A body is dropped together with its obstacle.
+    ///
+    /// # Returns
+    /// Whether there was such an obstacle.
+    pub fn set_rigid_body(&mut self, id: u32, body: Option<RigidBody>) -> bool {
+        if self.obstacle(id).is_none() {
+            return false;
+        }
+
+        self.bodies.retain(|(body_id, _)| *body_id != id);
+        if let Some(body) = body {
+            self.bodies.push((id, body));
+        }
+
+        true
+    }
+
+    /// The [rigid body](RigidBody) of the obstacle with the id `id`
+    pub fn rigid_body(&self, id: u32) -> Option<&RigidBody> {
+        self.bodies
+            .iter()
+            .find(|(body_id, _)| *body_id == id)
+            .map(|(_, body)| body)
+    }
+
+    /// Every rigid body together with the id of its obstacle
+    pub fn rigid_bodies(&self) -> &[(u32, RigidBody)] {
+        &self.bodies
+    }
+
+    /// Set the [tracer particles](Particles), or remove them with `None`
+    ///
+    /// Particles are kept where they are when only their look or their emitters change, and start over
+    /// when their number changes. Every particle is emitted during the next step.
+    pub fn set_particles(&mut self, particles: Option<Particles>) -> Result<(), B::Error> {
+        let Some(particles) = particles else {
+            self.particles = None;
+            if let Some(buffer) = self.particle_buffer.take() {
+                buffer.delete(&mut self.backend);
+            }
+            for texture in [self.particle_emitters.take(), self.overlay.take()].into_iter().flatten() {
+                self.backend.delete_texture(texture);
+            }
+            return Ok(());
+        };
+
+        if self.particles.as_ref().map(Particles::count) != Some(particles.count()) {
+            let (width, height) = particles.texture_size();
+            let buffer = PingPong::new_data(&mut self.backend, width, height)?;
+            if let Some(old) = self.particle_buffer.replace(buffer) {
+                old.delete(&mut self.backend);
+            }
+        }
+
+        self.particles = Some(particles);
+        self.upload_emitters()
+    }
+
+    pub fn particles(&self) -> Option<&Particles> {
+        self.particles.as_ref()
+    }
+
+    /// Set how the [streamlines](Mode::STREAMLINES) mode traces the flow
+    pub fn set_streamlines(&mut self, streamlines: Streamlines) -> Result<(), B::Error> {
+        self.streamlines = streamlines;
+        self.upload_streamline_seeds()
+    }
+
+    pub fn streamlines(&self) -> &Streamlines {
+        &self.streamlines
+    }
+
+    /// Turn the measurement of the forces on the obstacles on or off
+    ///
+    /// While it is on, every step ends with the computation of the net pressure and viscous force on every
+    /// obstacle, which reads a texture back from the backend. Turning it off clears the last forces.
+    pub fn set_measure_forces(&mut self, measure_forces: bool) {
+        self.measure_forces = measure_forces;
+        if !measure_forces {
+            self.forces.clear();
+            if let Some(texture) = self.force_store.take() {
+                self.backend.delete_texture(texture);
+            }
+        }
+    }
+
+    pub fn measure_forces_enabled(&self) -> bool {
+        self.measure_forces
+    }
+
+    /// The forces on every obstacle during the last step, in drawing order
+    ///
+    /// Empty if the [measurement](Simulation::set_measure_forces) is off and there are no
+    /// [rigid bodies](Simulation::set_rigid_body), or if no step ran since it was turned on.
+    pub fn forces(&self) -> &[ObstacleForce] {
+        &self.forces
+    }
+
+    /// The force on the obstacle with the id `id` during the last step
+    pub fn obstacle_force(&self, id: u32) -> Option<&ObstacleForce> {
+        self.forces.iter().find(|force| force.id() == id)
+    }
+
+    /// Keep the measured forces of the last `capacity` steps, zero keeps none and clears the history
+    pub fn set_force_history(&mut self, capacity: usize) {
+        self.force_history.set_capacity(capacity);
+        if capacity == 0 {
+            self.force_history.clear();
+        }
+    }
+
+    /// The time and the total force of the obstacle with the id `id` for every step in the history
+    ///
+    /// Time is the simulated time since the history was enabled, in seconds.
+    pub fn force_history(&self, id: u32) -> Vec<(f32, [f32; 2])> {
+        self.force_history.series(id)
+    }
+
+    /// Save the state of the simulation
+    ///
+    /// Reads back every texture of the simulation, together with the obstacles, the rigid bodies, the wind
+    /// tunnel and the time of the last [update](Simulation::update).
+    pub fn snapshot(&mut self) -> Result<Snapshot, B::Error> {
+        let backend = &mut self.backend;
+        let mut field = |texture: &B::Texture| -> Result<Field, B::Error> {
+            Ok(Field::new(
+                texture.width(),
+                texture.height(),
+                backend.read_texture(texture)?,
+            ))
+        };
+
+        Ok(Snapshot {
+            sim_resolution: self.sim_resolution,
+            dye_resolution: self.dye_resolution,
+            width: self.width,
+            height: self.height,
+            velocity: field(self.velocity_buffer.read())?,
+            pressure: field(self.pressure_buffer.read())?,
+            dye: field(self.dye_buffer.read())?,
+            obstacles: field(self.obstacle_buffer.read())?,
+            obstacle_list: self.obstacle_list.clone(),
+            next_obstacle_id: self.next_obstacle_id,
+            boundaries: self.boundaries.clone(),
+            wind_tunnel: self.wind_tunnel.clone(),
+            rigid_bodies: self.bodies.clone(),
+            last_time: self.last_time,
+        })
+    }
+
+    /// Restore a state saved with [snapshot](Simulation::snapshot)
+    ///
+    /// The simulation takes over the resolutions of the snapshot but keeps its current domain size. A
+    /// snapshot taken at another size is resampled to fit.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), B::Error> {
+        let backend = &mut self.backend;
+        self.sim_resolution = snapshot.sim_resolution;
+        self.dye_resolution = snapshot.dye_resolution;
+        // The restored fields have nothing to do with the range of the ones they replace
+        self.diagnostic_range = None;
+
+        // SIMULATION
+        let (width, height) = Simulation::<B>::resolution_size(self.width, self.height, self.sim_resolution);
+        for (buffer, field) in [
+            (&mut self.velocity_buffer, &snapshot.velocity),
+            (&mut self.pressure_buffer, &snapshot.pressure),
+        ] {
+            buffer.upload(
+                backend,
+                field.width(),
+                field.height(),
+                field.data(),
+            )?;
+            buffer.resize(
+                backend,
+                true,
+                width,
+                height,
+            )?;
+        }
+
+        if width != self.temp_store.width() || height != self.temp_store.height() {
+            let mut temp_store = backend.create_texture(
+                width,
+                height,
+                Filter::Linear,
+            )?;
+            backend.set_wrap(&mut temp_store, self.boundaries.wrap());
+            backend.delete_texture(std::mem::replace(&mut self.temp_store, temp_store));
+        }
+
+        // DYE
+        let (width, height) = Simulation::<B>::resolution_size(self.width, self.height, self.dye_resolution);
+        self.dye_buffer.upload(
+            backend,
+            snapshot.dye.width(),
+            snapshot.dye.height(),
+            snapshot.dye.data(),
+        )?;
+        self.dye_buffer.resize(
+            backend,
+            true,
+            width,
+            height,
+        )?;
+
+        // OBSTACLES
+        self.obstacle_buffer.upload(
+            backend,
+            snapshot.obstacles.width(),
+            snapshot.obstacles.height(),
+            snapshot.obstacles.data(),
+        )?;
+        self.obstacle_buffer.resize(
+            backend,
+            true,
+            width,
+            height,
+        )?;
+
+        self.obstacle_velocity_buffer.resize(
+            backend,
+            false,
+            width,
+            height,
+        )?;
+
+        // Snapshots without an obstacle list only carry the obstacle texture, which stands still
+        self.obstacle_list = snapshot.obstacle_list.clone();
+        self.next_obstacle_id = snapshot.next_obstacle_id;
+        self.bodies = snapshot.rigid_bodies.clone();
+        if self.obstacle_list.is_empty() {
+            Simulation::fill(&mut self.backend, &mut self.obstacle_velocity_buffer, 0.0)?;
+        } else {
+            self.composite_obstacles()?;
+        }
+
+        self.wind_tunnel = snapshot.wind_tunnel.clone();
+        self.boundaries = snapshot.boundaries.clone();
+        self.apply_wrap();
+        self.last_time = snapshot.last_time;
+        self.accumulator = 0.0;
+
+        Ok(())
+    }
+
+    /// Set obstacle mask
+    ///
+    /// Set a bitmap as the obstacle, replacing all other obstacles. Every pixel of the mask with a
+    /// coverage of at least `threshold` is solid.
+    ///
+    /// # Arguments
+    /// * `mask` - The [mask](Mask) of the obstacle
+    /// * `threshold` - Coverage from which a pixel of the mask is solid [0, 1]
+    /// * `position` - A float array that should have two values, the x and y position of the center of the mask in domain coordinates
+    /// * `size` - A float array that should have two values, the width and height of the mask in pixels
+    /// * `rotation` - Counterclockwise rotation of the mask around its center in radians
+    /// * `color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If either `position` or `size` contains fewer than two values, or if `color` contains fewer than three values.
+    pub fn set_obstacle_mask(
+        &mut self,
+        mask: &Mask,
+        threshold: f32,
+        position: &[f32],
+        size: &[f32],
+        rotation: f32,
+        color: &[f32],
+    ) -> Result<(), B::Error> {
+        self.obstacle_list.clear();
+        self.obstacle_list.push((
+            self.next_obstacle_id,
+            Obstacle::mask(
+                mask,
+                threshold,
+                position,
+                size,
+                rotation,
+                color,
+            ),
+        ));
+        self.next_obstacle_id += 1;
+
+        self.composite_obstacles()
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn velocity(&self) -> &B::Texture {
+        self.velocity_buffer.read()
+    }
+
+    pub fn pressure(&self) -> &B::Texture {
+        self.pressure_buffer.read()
+    }
+
+    pub fn dye(&self) -> &B::Texture {
+        self.dye_buffer.read()
+    }
+
+    /// The obstacle texture, with 0 in the first channel for solids and the color of the obstacle in the other three
+    pub fn obstacles(&self) -> &B::Texture {
+        self.obstacle_buffer.read()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn sim_resolution(&self) -> Resolution {
+        self.sim_resolution
+    }
+
+    pub fn dye_resolution(&self) -> Resolution {
+        self.dye_resolution
+    }
+
+    pub fn resolution_size(width: u32, height: u32, resolution: Resolution) -> (u32, u32) {
+        (width / resolution as u32, height / resolution as u32)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn pressure_solve(
+        backend: &mut B,
+        iterations: usize,
+        boundary: [BoundaryMode; 4],
+        resolution: &[f32; 2],
+        alpha: f32,
+        r_beta: f32,
+        sor: Option<f32>,
+        x: &mut PingPong<B::Texture>,
+        b: &B::Texture,
+        obstacle: &B::Texture,
+    ) -> Result<(), B::Error> {
+        for i in 0..iterations {
+            let (read, write) = x.split();
+            let pass = match sor {
+                // Over-relaxation alternates between the red and the black texels
+                Some(omega) => Pass::PressureSor {
+                    boundary,
+                    alpha,
+                    omega,
+                    parity: i as u32 % 2,
+                    resolution: *resolution,
+                    x: read,
+                    b,
+                    obstacles: obstacle,
+                },
+                None => Pass::PressureSolver {
+                    boundary,
+                    alpha,
+                    r_beta,
+                    omega: 1.0,
+                    resolution: *resolution,
+                    x: read,
+                    b,
+                    obstacles: obstacle,
+                },
+            };
+            backend.dispatch(pass, write)?;
+            x.swap();
+        }
+
+        Ok(())
+    }
+
+    /// Advect `quantity` along the velocity, or along itself without a `velocity_buffer`
+    ///
+    /// The corrected schemes need a `store` of the size of `quantity`, without one they fall back to a
+    /// semi-Lagrangian step.
+    #[allow(clippy::too_many_arguments)]
+    fn advect(
+        backend: &mut B,
+        advection: Advection,
+        sim_resolution: &[f32; 2],
+        delta_time: f32,
+        dissipation: f32,
+        velocity_buffer: Option<&PingPong<B::Texture>>,
+        quantity: &mut PingPong<B::Texture>,
+        store: Option<&mut B::Texture>,
+        obstacle: &B::Texture,
+    ) -> Result<(), B::Error> {
+        let dissipation = 1.0 / (1.0 + dissipation * delta_time);
+        let bfecc = match advection {
+            Advection::SEMI_LAGRANGIAN => None,
+            Advection::MACCORMACK => Some(false),
+            Advection::BFECC => Some(true),
+        };
+        let correction = bfecc.zip(store);
+
+        let (read, write) = quantity.split();
+        let velocity = velocity_buffer.map_or(read, |b| b.read());
+        backend.dispatch(
+            Pass::Advection {
+                // The corrected advection dissipates instead
+                dissipation: if correction.is_some() { 1.0 } else { dissipation },
+                delta_time,
+                resolution: *sim_resolution,
+                velocity,
+                quantity: read,
+                obstacles: obstacle,
+            },
+            write,
+        )?;
+
+        if let Some((bfecc, store)) = correction {
+            backend.dispatch(
+                Pass::AdvectionError {
+                    delta_time,
+                    resolution: *sim_resolution,
+                    velocity,
+                    quantity: read,
+                    advected: write,
+                },
+                store,
+            )?;
+
+            // Replaces the first advection, which is only needed for its error
+            backend.dispatch(
+                Pass::CorrectedAdvection {
+                    bfecc,
+                    dissipation,
+                    delta_time,
+                    resolution: *sim_resolution,
+                    quantity_resolution: [read.width() as f32, read.height() as f32],
+                    velocity,
+                    quantity: read,
+                    error: store,
+                    obstacles: obstacle,
+                },
+                write,
+            )?;
+        }
+        quantity.swap();
+
+        Ok(())
+    }
+
+    fn project_velocity(
+        &mut self,
+        sim_resolution: &[f32; 2],
+        config: &SimulationConfig,
+    ) -> Result<(), B::Error> {
+        let r_half_texel = 0.5 / (self.sim_resolution as u32 as f32);
+        let boundary = self.boundaries.effective_modes();
+        let resolution = self.sim_resolution as u32 as f32;
+        let inflow = self.boundaries.inflows().map(|inflow| [inflow[0] / resolution, inflow[1] / resolution]);
+
+        // DIVERGENCE
+        self.backend.dispatch(
+            Pass::Divergence {
+                boundary,
+                inflow,
+                r_half_texel_size: r_half_texel,
+                resolution: *sim_resolution,
+                velocity: self.velocity_buffer.read(),
+                obstacles: self.obstacle_buffer.read(),
+                obstacle_velocity: self.obstacle_velocity_buffer.read(),
+            },
+            &mut self.temp_store,
+        )?;
+
+        // PRESSURE
+        let (read, write) = self.pressure_buffer.split();
+        self.backend.dispatch(
+            Pass::Copy {
+                factor: config.pressure(),
+                offset: 0.0,
+                texture: read,
+            },
+            write,
+        )?;
+        self.pressure_buffer.swap();
+
+        let alpha = self.sim_resolution as u32 as f32;
+        let alpha = -alpha * alpha;
+        let r_beta = 0.25;
+        let size = [self.pressure_buffer.read().width(), self.pressure_buffer.read().height()];
+        match config.solver() {
+            Solver::JACOBI | Solver::SOR | Solver::CONJUGATE_GRADIENT => {
+                if let Some(multigrid) = self.multigrid.take() {
+                    multigrid.delete(&mut self.backend);
+                }
+            }
+            Solver::MULTIGRID => {
+                if self.multigrid.as_ref().map(Multigrid::size) != Some(size) {
+                    if let Some(old) = self.multigrid.take() {
+                        old.delete(&mut self.backend);
+                    }
+                    self.multigrid = Some(Multigrid::new(&mut self.backend, size[0], size[1], self.boundaries.wrap())?);
+                }
+            }
+        }
+
+        // The conjugate gradients measure their residual themselves
+        let tolerance = config.tolerance();
+        let measure = tolerance > 0.0 && config.solver() != Solver::CONJUGATE_GRADIENT;
+        if !measure || self.residual_norm.as_ref().map(ResidualNorm::size) != Some(size) {
+            if let Some(old) = self.residual_norm.take() {
+                old.delete(&mut self.backend);
+            }
+            if measure {
+                self.residual_norm = Some(ResidualNorm::new(&mut self.backend, size[0], size[1])?);
+            }
+        }
+
+        // Iterations (or cycles) between two measurements of the residual, and at most in total
+        let (interval, limit) = match (config.solver(), tolerance > 0.0) {
+            (Solver::JACOBI | Solver::SOR, true) => (RESIDUAL_INTERVAL, config.max_iterations()),
+            (Solver::MULTIGRID, true) => (1, config.max_iterations()),
+            (Solver::CONJUGATE_GRADIENT, _) => (config.max_iterations(), config.max_iterations()),
+            (solver, false) => {
+                let iterations = match solver {
+                    Solver::MULTIGRID => config.cycles().max(1),
+                    _ => MIN_PRESSURE_ITERATIONS.max(config.iterations()),
+                };
+                (iterations, iterations)
+            }
+        };
+
+        self.pressure_iterations = 0;
+        self.pressure_residual = None;
+        if config.solver() == Solver::CONJUGATE_GRADIENT {
+            let (iterations, residual) = conjugate_gradient::solve(
+                &mut self.backend,
+                boundary,
+                alpha,
+                tolerance / resolution,
+                limit,
+                &mut self.pressure_buffer,
+                &self.temp_store,
+                self.obstacle_buffer.read(),
+            )?;
+            self.pressure_iterations = iterations;
+            self.pressure_residual = Some(residual * resolution);
+        } else {
+            loop {
+                if let Some(residual_norm) = self.residual_norm.as_mut() {
+                    let residual = residual_norm.measure(
+                        &mut self.backend,
+                        boundary,
+                        alpha,
+                        *sim_resolution,
+                        &self.pressure_buffer,
+                        &self.temp_store,
+                        self.obstacle_buffer.read(),
+                    )? * resolution;
+
+                    self.pressure_residual = Some(residual);
+                    if residual <= tolerance {
+                        break;
+                    }
+                }
+                if self.pressure_iterations >= limit {
+                    break;
+                }
+
+                let iterations = interval.min(limit - self.pressure_iterations);
+                match self.multigrid.as_mut() {
+                    Some(multigrid) => multigrid.solve(
+                        &mut self.backend,
+                        iterations,
+                        boundary,
+                        alpha,
+                        &mut self.pressure_buffer,
+                        &self.temp_store,
+                        self.obstacle_buffer.read(),
+                    )?,
+                    None => Simulation::pressure_solve(
+                        &mut self.backend,
+                        iterations,
+                        boundary,
+                        sim_resolution,
+                        alpha,
+                        r_beta,
+                        (config.solver() == Solver::SOR).then_some(config.omega()),
+                        &mut self.pressure_buffer,
+                        &self.temp_store,
+                        self.obstacle_buffer.read(),
+                    )?,
+                }
+                self.pressure_iterations += iterations;
+            }
+        }
+
+        // SUBTRACTION
+        let (read, write) = self.velocity_buffer.split();
+        self.backend.dispatch(
+            Pass::GradientSubtract {
+                boundary,
+                inflow,
+                r_half_texel_size: r_half_texel,
+                resolution: *sim_resolution,
+                velocity: read,
+                pressure: self.pressure_buffer.read(),
+                obstacles: self.obstacle_buffer.read(),
+                obstacle_velocity: self.obstacle_velocity_buffer.read(),
+            },
+            write,
+        )?;
+        self.velocity_buffer.swap();
+
+        Ok(())
+    }
+
+    fn vorticity_confinement(
+        &mut self,
+        sim_resolution: &[f32; 2],
+        curl: f32,
+    ) -> Result<(), B::Error> {
+        let r_half_texel = 0.5 / (self.sim_resolution as u32 as f32);
+
+        // CURL
+        self.backend.dispatch(
+            Pass::Curl {
+                r_half_texel_size: r_half_texel,
+                resolution: *sim_resolution,
+                velocity: self.velocity_buffer.read(),
+            },
+            &mut self.temp_store,
+        )?;
+
+        // VORTICITY CONFINEMENT
+        let (read, write) = self.velocity_buffer.split();
+        self.backend.dispatch(
+            Pass::Vorticity {
+                curl_scale: curl,
+                r_half_texel_size: r_half_texel,
+                resolution: *sim_resolution,
+                curl: &self.temp_store,
+                velocity: read,
+            },
+            write,
+        )?;
+        self.velocity_buffer.swap();
+
+        Ok(())
+    }
+
+    /// Integrate the force of the fluid over the boundary of every obstacle
+    ///
+    /// The force pass leaves the force on every solid texel next to the fluid, which is summed per
+    /// obstacle by the index its texels carry in the obstacle velocity. The pressure of the solver is a
+    /// pressure times the step length, and both it and the velocity are in texels of the simulation.
+    fn measure_forces(
+        &mut self,
+        delta_time: f32,
+        config: &SimulationConfig,
+    ) -> Result<(), B::Error> {
+        if self.obstacle_list.is_empty() {
+            self.forces.clear();
+            self.force_history.push(delta_time, &self.forces);
+            return Ok(());
+        }
+
+        let (width, height) = (
+            self.obstacle_buffer.read().width(),
+            self.obstacle_buffer.read().height(),
+        );
+        // The forces and, above them, the obstacle index of every texel
+        if self
+            .force_store
+            .as_ref()
+            .is_some_and(|texture| texture.width() != width || texture.height() != 2 * height)
+        {
+            if let Some(texture) = self.force_store.take() {
+                self.backend.delete_texture(texture);
+            }
+        }
+
+        let mut force_store = match self.force_store.take() {
+            Some(texture) => texture,
+            None => self.backend.create_texture(
+                width,
+                2 * height,
+                Filter::Nearest,
+            )?,
+        };
+
+        let sim_resolution = self.sim_resolution as u32 as f32;
+        let dye_resolution = self.dye_resolution as u32 as f32;
+        let result = self.backend.dispatch(
+            Pass::Force {
+                pressure_scale: sim_resolution * dye_resolution / delta_time,
+                viscous_scale: config.viscosity() * sim_resolution,
+                resolution: [width as f32, height as f32],
+                pressure: self.pressure_buffer.read(),
+                velocity: self.velocity_buffer.read(),
+                obstacles: self.obstacle_buffer.read(),
+                obstacle_velocity: self.obstacle_velocity_buffer.read(),
+            },
+            &mut force_store,
+        );
+        let texels = result.and_then(|_| self.backend.read_texture(&force_store));
+        self.force_store = Some(force_store);
+        let texels = texels?;
+        let (texels, indices) = texels.split_at(texels.len() / 2);
+
+        // SUM
+        // Torques are taken around the position of every obstacle, from the centers of the texels
+        let texel_size = [self.width as f32 / width as f32, self.height as f32 / height as f32];
+        let mut sums = vec![([0.0; 4], 0.0); self.obstacle_list.len()];
+        for (i, (texel, index)) in texels.chunks_exact(4).zip(indices.chunks_exact(4)).enumerate() {
+            let Some(index) = (index[0].round() as usize).checked_sub(1).filter(|index| *index < sums.len()) else {
+                continue;
+            };
+
+            let (sum, torque) = &mut sums[index];
+            for (sum, value) in sum.iter_mut().zip(texel) {
+                *sum += value;
+            }
+
+            let position = self.obstacle_list[index].1.position;
+            let arm = [
+                ((i as u32 % width) as f32 + 0.5) * texel_size[0] - position[0],
+                ((i as u32 / width) as f32 + 0.5) * texel_size[1] - position[1],
+            ];
+            *torque += arm[0] * (texel[1] + texel[3]) - arm[1] * (texel[0] + texel[2]);
+        }
+
+        self.forces = self
+            .obstacle_list
+            .iter()
+            .zip(sums)
+            .map(|((id, _), (sum, torque))| ObstacleForce::new(*id, [sum[0], sum[1]], [sum[2], sum[3]], torque))
+            .collect();
+        self.force_history.push(delta_time, &self.forces);
+
+        Ok(())
+    }
+
+    /// Move every rigid body by the forces of the last step, let them collide and redraw the obstacles
+    fn move_bodies(&mut self, delta_time: f32) -> Result<(), B::Error> {
+        let size = [self.width as f32, self.height as f32];
+        let periodic = self.boundaries.periodic();
+        let mut moved = Vec::with_capacity(self.bodies.len());
+        for (id, body) in &self.bodies {
+            let Some(index) = self.obstacle_list.iter().position(|(obstacle_id, _)| obstacle_id == id) else {
+                continue;
+            };
+
+            let obstacle = &mut self.obstacle_list[index].1;
+            if let Some(force) = self.forces.iter().find(|force| force.id() == *id) {
+                body.integrate(
+                    obstacle,
+                    force.net(),
+                    force.torque(),
+                    delta_time,
+                );
+            }
+
+            body.collide_edges(
+                obstacle,
+                size,
+                periodic,
+            );
+            moved.push((index, body));
+        }
+
+        // COLLISIONS
+        for (i, (a, body_a)) in moved.iter().enumerate() {
+            for (b, body_b) in &moved[i + 1..] {
+                let (first, second) = self.obstacle_list.split_at_mut(*a.max(b));
+                let (a, b) = match a < b {
+                    true => (&mut first[*a].1, &mut second[0].1),
+                    false => (&mut second[0].1, &mut first[*b].1),
+                };
+                RigidBody::collide((a, body_a), (b, body_b));
+            }
+        }
+
+        self.composite_obstacles()
+    }
+
+    /// Draw every obstacle of the list into the obstacle texture and the obstacle velocity texture
+    ///
+    /// Dye that was colored by the previous obstacles is cleared first.
+    fn composite_obstacles(&mut self) -> Result<(), B::Error> {
+        let obstacle_list = &self.obstacle_list;
+        self.bodies.retain(|(id, _)| obstacle_list.iter().any(|(obstacle_id, _)| obstacle_id == id));
+
+        Simulation::color_obstacle(
+            &mut self.backend,
+            self.obstacle_buffer.read(),
+            &mut self.dye_buffer,
+            0.0,
+        )?;
+
+        // CLEAR
+        Simulation::fill(&mut self.backend, &mut self.obstacle_buffer, 1.0)?;
+        Simulation::fill(&mut self.backend, &mut self.obstacle_velocity_buffer, 0.0)?;
+
+        // DRAW OBSTACLES
+        let resolution = self.dye_resolution as u32 as f32;
+        for (index, (_, obstacle)) in self.obstacle_list.iter().enumerate() {
+            let shape_texture = match obstacle.shape() {
+                Shape::Mask { mask, .. } => {
+                    let mut texture = self.backend.create_texture(
+                        mask.width(),
+                        mask.height(),
+                        Filter::Linear,
+                    )?;
+                    self.backend.write_texture(&mut texture, &mask.texels())?;
+                    Some(texture)
+                }
+                Shape::Polygon { vertices } => {
+                    if !(3..=MAX_POLYGON_VERTICES).contains(&vertices.len()) {
+                        continue;
+                    }
+
+                    let mut texture = self.backend.create_texture(
+                        vertices.len() as u32,
+                        1,
+                        Filter::Nearest,
+                    )?;
+                    let texels: Vec<f32> = vertices
+                        .iter()
+                        .flat_map(|vertex| [vertex[0] / resolution, vertex[1] / resolution, 0.0, 0.0])
+                        .collect();
+                    self.backend.write_texture(&mut texture, &texels)?;
+                    Some(texture)
+                }
+                _ => None,
+            };
+
+            let mut result = Ok(());
+            for (buffer, write_velocity) in [
+                (&mut self.obstacle_buffer, false),
+                (&mut self.obstacle_velocity_buffer, true),
+            ] {
+                result = Simulation::draw_obstacle(
+                    &mut self.backend,
+                    self.sim_resolution,
+                    self.dye_resolution,
+                    obstacle,
+                    index as u32 + 1,
+                    shape_texture.as_ref(),
+                    write_velocity,
+                    buffer,
+                );
+                if result.is_err() {
+                    break;
+                }
+            }
+
+            if let Some(texture) = shape_texture {
+                self.backend.delete_texture(texture);
+            }
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Draw `obstacle` over `buffer`, either its color or, with `write_velocity`, its velocity and `index`
+    ///
+    /// Velocities are drawn in texels of the simulation per second, like the velocity of the fluid.
+    /// `index` lands in the third channel, so that the texels of every obstacle can be told apart.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_obstacle(
+        backend: &mut B,
+        sim_resolution: Resolution,
+        dye_resolution: Resolution,
+        obstacle: &Obstacle,
+        index: u32,
+        shape_texture: Option<&B::Texture>,
+        write_velocity: bool,
+        buffer: &mut PingPong<B::Texture>,
+    ) -> Result<(), B::Error> {
+        let sim_resolution = sim_resolution as u32 as f32;
+        let resolution = dye_resolution as u32 as f32;
+        let position = [obstacle.position[0] / resolution, obstacle.position[1] / resolution];
+        let rotation = [obstacle.rotation.cos(), obstacle.rotation.sin()];
+        let linear_velocity = [
+            obstacle.linear_velocity[0] / sim_resolution,
+            obstacle.linear_velocity[1] / sim_resolution,
+        ];
+        // Offsets in the shaders are in texels of the obstacle texture, which has the dye resolution
+        let angular_velocity = obstacle.angular_velocity * resolution / sim_resolution;
+
+        let (read, write) = buffer.split();
+        let pass = match (obstacle.shape(), shape_texture) {
+            (Shape::Mask { threshold, size, .. }, Some(mask)) => Pass::ObstacleMask {
+                write_velocity,
+                index,
+                threshold: *threshold,
+                position,
+                half_size: [0.5 * size[0] / resolution, 0.5 * size[1] / resolution],
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color: obstacle.color,
+                mask,
+                obstacles: read,
+            },
+            (Shape::Polygon { .. }, Some(vertices)) => Pass::ObstaclePolygon {
+                write_velocity,
+                index,
+                position,
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color: obstacle.color,
+                vertices,
+                obstacles: read,
+            },
+            (shape, _) => {
+                // Masks and polygons without their texture are not drawn by the obstacle pass
+                let size = match *shape {
+                    Shape::Circle { radius } => [radius, 0.0],
+                    Shape::Square { half_size } => [half_size, half_size],
+                    Shape::Rectangle { size } => [0.5 * size[0], 0.5 * size[1]],
+                    Shape::Capsule { length, radius } => [0.5 * length, radius],
+                    Shape::Annulus { inner_radius, outer_radius } => [inner_radius, outer_radius],
+                    Shape::Mask { .. } | Shape::Polygon { .. } => [0.0; 2],
+                };
+
+                Pass::Obstacle {
+                    shape: shape.kind(),
+                    write_velocity,
+                    index,
+                    size: [size[0] / resolution, size[1] / resolution],
+                    position,
+                    rotation,
+                    linear_velocity,
+                    angular_velocity,
+                    obstacle_color: obstacle.color,
+                    obstacles: read,
+                }
+            }
+        };
+        backend.dispatch(pass, write)?;
+        buffer.swap();
+
+        Ok(())
+    }
+
+    /// Set every texel of `buffer` to `value`
+    fn fill(
+        backend: &mut B,
+        buffer: &mut PingPong<B::Texture>,
+        value: f32,
+    ) -> Result<(), B::Error> {
+        let (read, write) = buffer.split();
+        backend.dispatch(
+            Pass::Copy {
+                factor: 0.0,
+                offset: value,
+                texture: read,
+            },
+            write,
+        )?;
+        buffer.swap();
+
+        Ok(())
+    }
+
+    /// Store the emitters of the particles in a texture, one per texel, as a position and a radius along
+    /// either axis in texture coordinates
+    fn upload_emitters(&mut self) -> Result<(), B::Error> {
+        if let Some(texture) = self.particle_emitters.take() {
+            self.backend.delete_texture(texture);
+        }
+
+        let Some(particles) = &self.particles else {
+            return Ok(());
+        };
+        if particles.emitters().is_empty() {
+            return Ok(());
+        }
+
+        let (width, height) = (self.width as f32, self.height as f32);
+        let data: Vec<f32> = particles
+            .emitters()
+            .iter()
+            .flat_map(|[x, y, radius]| [x / width, y / height, radius / width, radius / height])
+            .collect();
+
+        let mut texture = self.backend.create_data_texture(particles.emitters().len() as u32, 1)?;
+        self.backend.write_texture(&mut texture, &data)?;
+        self.particle_emitters = Some(texture);
+
+        Ok(())
+    }
+
+    /// Map the field of the diagnostic `mode` onto its color map in `target`
+    fn draw_diagnostic(&mut self, mode: Mode, target: &mut B::Texture) -> Result<(), B::Error> {
+        let resolution = self.sim_resolution as u32 as f32;
+        let sim_resolution = [
+            self.velocity_buffer.read().width() as f32,
+            self.velocity_buffer.read().height() as f32,
+        ];
+
+        // The divergence is what the last solve left over, both it and the curl are of the current velocity
+        match mode {
+            Mode::DIVERGENCE => self.backend.dispatch(
+                Pass::Divergence {
+                    boundary: self.boundaries.effective_modes(),
+                    inflow: self.boundaries.inflows().map(|inflow| [inflow[0] / resolution, inflow[1] / resolution]),
+                    r_half_texel_size: 0.5 / resolution,
+                    resolution: sim_resolution,
+                    velocity: self.velocity_buffer.read(),
+                    obstacles: self.obstacle_buffer.read(),
+                    obstacle_velocity: self.obstacle_velocity_buffer.read(),
+                },
+                &mut self.temp_store,
+            )?,
+            Mode::CURL => self.backend.dispatch(
+                Pass::Curl {
+                    r_half_texel_size: 0.5 / resolution,
+                    resolution: sim_resolution,
+                    velocity: self.velocity_buffer.read(),
+                },
+                &mut self.temp_store,
+            )?,
+            _ => {}
+        }
+
+        // The field, its color map, whether it is a vector and the scale from its units to those of the legend
+        let (texture, colormap, magnitude, scale) = match mode {
+            Mode::PRESSURE => (self.pressure_buffer.read(), Colormap::DIVERGING, false, 1.0),
+            Mode::DIVERGENCE | Mode::CURL => (&self.temp_store, Colormap::DIVERGING, false, resolution),
+            Mode::SPEED => (self.velocity_buffer.read(), Colormap::SEQUENTIAL, true, resolution),
+            _ => (self.obstacle_buffer.read(), Colormap::GRAY, false, 1.0),
+        };
+
+        let range = match mode {
+            // Fluid is 1 and solid 0 in the obstacle texture
+            Mode::OBSTACLES => [1.0, 0.0],
+            _ => {
+                // The readback stalls the pipeline, so the range is only found again every few frames
+                let range = match self.diagnostic_range {
+                    Some((last, range, frames)) if last == mode && frames < RANGE_INTERVAL => {
+                        self.diagnostic_range = Some((mode, range, frames + 1));
+                        range
+                    }
+                    _ => {
+                        let range = auto_range(&self.backend.read_texture(texture)?, magnitude, colormap);
+                        self.diagnostic_range = Some((mode, range, 1));
+                        range
+                    }
+                };
+                self.legend = Some(range.map(|value| value * scale));
+                range
+            }
+        };
+
+        self.backend.dispatch(
+            Pass::Colormap {
+                colormap,
+                range,
+                magnitude,
+                texture,
+                obstacles: (mode != Mode::OBSTACLES).then(|| self.obstacle_buffer.read()),
+            },
+            target,
+        )
+    }
+
+    /// Trace the streamlines through the current velocity and draw them over `target`
+    fn draw_streamlines(&mut self, target: &mut B::Texture) -> Result<(), B::Error> {
+        let grid = self.streamlines.grid(self.width, self.height);
+        let rows = match self.streamline_seeds.as_ref() {
+            Some(seeds) => seeds.width(),
+            None => grid[0] * grid[1],
+        };
+        let columns = self.streamlines.steps() + 1;
+
+        let mut store = match self.streamline_store.take() {
+            Some(store) if store.width() == columns && store.height() == rows => store,
+            old => {
+                if let Some(old) = old {
+                    self.backend.delete_texture(old);
+                }
+                self.backend.create_data_texture(columns, rows)?
+            }
+        };
+
+        let sim_resolution = self.sim_resolution as u32 as f32;
+        let periodic = self.boundaries.periodic();
+        let result = self.backend.dispatch(
+            Pass::Streamlines {
+                step_size: self.streamlines.length() / self.streamlines.steps() as f32 / sim_resolution,
+                grid: grid.map(|cells| cells as f32),
+                period: periodic.map(|periodic| if periodic { 1.0 } else { 0.0 }),
+                resolution: [
+                    self.velocity_buffer.read().width() as f32,
+                    self.velocity_buffer.read().height() as f32,
+                ],
+                seeds: self.streamline_seeds.as_ref(),
+                velocity: self.velocity_buffer.read(),
+                obstacles: self.obstacle_buffer.read(),
+            },
+            &mut store,
+        ).and_then(|()| self.backend.dispatch(
+            Pass::DrawStreamlines {
+                max_speed: self.streamlines.max_speed() / sim_resolution,
+                lines: &store,
+            },
+            target,
+        ));
+        self.streamline_store = Some(store);
+
+        result
+    }
+
+    /// Draw the particles, if any, over `target`
+    fn draw_particles(&mut self, target: &mut B::Texture) -> Result<(), B::Error> {
+        let (Some(particles), Some(buffer)) = (&self.particles, &self.particle_buffer) else {
+            return Ok(());
+        };
+
+        let dye_resolution = self.dye_resolution as u32 as f32;
+        let sim_resolution = self.sim_resolution as u32 as f32;
+        let color = particles.color();
+        self.backend.dispatch(
+            Pass::DrawParticles {
+                count: particles.count(),
+                size: (particles.size() / dye_resolution).max(1.0),
+                streak: particles.streak(),
+                color_mode: particles.color_mode(),
+                color: [color[0], color[1], color[2]],
+                max_speed: particles.max_speed() / sim_resolution,
+                resolution: [
+                    self.velocity_buffer.read().width() as f32,
+                    self.velocity_buffer.read().height() as f32,
+                ],
+                particles: buffer.read(),
+                velocity: self.velocity_buffer.read(),
+            },
+            target,
+        )
+    }
+
+    /// Store the seeds of the streamlines in a texture, one per texel, as a position in texture coordinates
+    fn upload_streamline_seeds(&mut self) -> Result<(), B::Error> {
+        if let Some(texture) = self.streamline_seeds.take() {
+            self.backend.delete_texture(texture);
+        }
+
+        if self.streamlines.seeds().is_empty() {
+            return Ok(());
+        }
+
+        let (width, height) = (self.width as f32, self.height as f32);
+        let data: Vec<f32> = self.streamlines
+            .seeds()
+            .iter()
+            .flat_map(|[x, y]| [x / width, y / height, 0.0, 0.0])
+            .collect();
+
+        let mut texture = self.backend.create_data_texture(self.streamlines.seeds().len() as u32, 1)?;
+        self.backend.write_texture(&mut texture, &data)?;
+        self.streamline_seeds = Some(texture);
+
+        Ok(())
+    }
+
+    /// Move the particles along the velocity and emit the ones that died again
+    fn advect_particles(
+        &mut self,
+        sim_resolution: &[f32; 2],
+        delta_time: f32,
+    ) -> Result<(), B::Error> {
+        let (Some(particles), Some(buffer)) = (&self.particles, &mut self.particle_buffer) else {
+            return Ok(());
+        };
+
+        // Every step draws new random numbers, spread evenly over [0, 1)
+        self.particle_seed = self.particle_seed.wrapping_add(1);
+        let seed = (self.particle_seed as f64 * 0.618034).fract() as f32;
+        let periodic = self.boundaries.periodic();
+
+        let (read, write) = buffer.split();
+        self.backend.dispatch(
+            Pass::Particles {
+                delta_time,
+                lifetime: particles.lifetime(),
+                seed,
+                period: periodic.map(|periodic| if periodic { 1.0 } else { 0.0 }),
+                resolution: *sim_resolution,
+                particles: read,
+                velocity: self.velocity_buffer.read(),
+                obstacles: self.obstacle_buffer.read(),
+                emitters: self.particle_emitters.as_ref(),
+            },
+            write,
+        )?;
+        buffer.swap();
+
+        Ok(())
+    }
+
+    /// Let every texture of the simulation wrap around the periodic axes of the boundaries
+    fn apply_wrap(&mut self) {
+        let wrap = self.boundaries.wrap();
+        for buffer in [
+            &mut self.velocity_buffer,
+            &mut self.pressure_buffer,
+            &mut self.dye_buffer,
+            &mut self.obstacle_buffer,
+            &mut self.obstacle_velocity_buffer,
+        ] {
+            buffer.set_wrap(&mut self.backend, wrap);
+        }
+        self.backend.set_wrap(&mut self.temp_store, wrap);
+        if let Some(dye_store) = self.dye_store.as_mut() {
+            self.backend.set_wrap(dye_store, wrap);
+        }
+        if let Some(multigrid) = self.multigrid.as_mut() {
+            multigrid.set_wrap(&mut self.backend, wrap);
+        }
+    }
+
+    /// Inject the lines of dye of the wind tunnel along the left edge
+    ///
+    /// The lines are as long as the inflow travels during the step, so that they join up with the dye
+    /// advected in the previous steps.
+    fn streaklines(
+        backend: &mut B,
+        wind_tunnel: &WindTunnel,
+        dye_resolution: f32,
+        delta_time: f32,
+        dye_buffer: &mut PingPong<B::Texture>,
+    ) -> Result<(), B::Error> {
+        if wind_tunnel.streaklines() == 0 {
+            return Ok(());
+        }
+
+        let resolution = [
+            dye_buffer.read().width() as f32,
+            dye_buffer.read().height() as f32,
+        ];
+        let spacing = resolution[1] / wind_tunnel.streaklines() as f32;
+        let color = wind_tunnel.streakline_color();
+
+        let (read, write) = dye_buffer.split();
+        backend.dispatch(
+            Pass::Streaklines {
+                count: wind_tunnel.streaklines(),
+                radius: (0.1 * spacing).max(1.0),
+                length: 1.0 + wind_tunnel.inflow().max(0.0) * delta_time / dye_resolution,
+                resolution,
+                color: [color[0], color[1], color[2]],
+                texture: read,
+            },
+            write,
+        )?;
+        dye_buffer.swap();
+
+        Ok(())
+    }
+
+    fn color_obstacle(
+        backend: &mut B,
+        obstacle: &B::Texture,
+        texture: &mut PingPong<B::Texture>,
+        intensity: f32,
+    ) -> Result<(), B::Error> {
+        let (read, write) = texture.split();
+        backend.dispatch(
+            Pass::ColorObstacle {
+                intensity,
+                obstacles: obstacle,
+                texture: read,
+            },
+            write,
+        )?;
+        texture.swap();
+
+        Ok(())
+    }
+}
+
+/// The range of a field of `values`, four per texel, up to the 99th percentile of its magnitude so that
+/// single spikes do not wash out the color map, centered on zero for a diverging map and from zero otherwise
+fn auto_range(values: &[f32], magnitude: bool, colormap: Colormap) -> [f32; 2] {
+    let mut magnitudes: Vec<f32> = values
+        .chunks_exact(4)
+        .map(|texel| match magnitude {
+            true => texel[0].hypot(texel[1]),
+            false => texel[0].abs(),
+        })
+        .filter(|value| value.is_finite())
+        .collect();
+
+    let max = match magnitudes.len() {
+        0 => 0.0,
+        len => *magnitudes.select_nth_unstable_by(len * 99 / 100, f32::total_cmp).1,
+    }
+        .max(0.000001);
+
+    match colormap {
+        Colormap::DIVERGING => [-max, max],
+        _ => [0.0, max],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Edge;
+    use crate::cpu_backend::{ CpuBackend, CpuSolver };
+    use crate::grid::Grid;
+
+    fn simulation() -> CpuSolver {
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 48, Resolution::FOUR, Resolution::TWO);
+        let Ok(_) = simulation.add_obstacle(Obstacle::circle(6.0, &[40.0, 24.0], &[1.0, 1.0, 1.0]));
+        simulation
+    }
+
+    /// A simulation at full resolution with a splat blowing against a circle in its middle
+    fn splashed() -> CpuSolver {
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 64, Resolution::ONE, Resolution::ONE);
+        let Ok(_) = simulation.add_obstacle(Obstacle::circle(10.0, &[32.0, 32.0], &[1.0, 1.0, 1.0]));
+        let Ok(()) = simulation.splat(6.0, &[18.0, 32.0], &[500.0, 100.0], &[1.0, 1.0, 1.0]);
+        simulation
+    }
+
+    /// Project the velocity of `simulation` with `config`, leaving its divergence in the temporary store
+    fn project(simulation: &mut CpuSolver, config: &SimulationConfig) {
+        let size = [
+            simulation.velocity_buffer.read().width() as f32,
+            simulation.velocity_buffer.read().height() as f32,
+        ];
+        let Ok(()) = simulation.project_velocity(&size, config);
+    }
+
+    /// What is left of the divergence in the temporary store once the pressure is put into the pressure
+    /// equation, per texel and per second, zero on solid texels
+    fn residuals(simulation: &mut CpuSolver) -> Vec<f32> {
+        let resolution = simulation.sim_resolution as u32 as f32;
+        let x = simulation.pressure_buffer.read();
+        let mut residual = Grid::new(x.width(), x.height(), Filter::Nearest);
+        let Ok(()) = simulation.backend.dispatch(
+            Pass::Residual {
+                boundary: simulation.boundaries.effective_modes(),
+                alpha: -resolution * resolution,
+                resolution: [x.width() as f32, x.height() as f32],
+                x,
+                b: &simulation.temp_store,
+                obstacles: simulation.obstacle_buffer.read(),
+            },
+            &mut residual,
+        );
+
+        residual.data().iter().map(|texel| texel[0] * resolution).collect()
+    }
+
+    fn rms(values: &[f32]) -> f32 {
+        (values.iter().map(|value| value * value).sum::<f32>() / values.len() as f32).sqrt()
+    }
+
+    /// Indices of the fluid texels of `simulation` with a solid neighbour
+    fn along_obstacles(simulation: &mut CpuSolver) -> Vec<usize> {
+        let width = simulation.velocity_buffer.read().width() as usize;
+        let Ok(obstacles) = simulation.backend.read_texture(simulation.obstacle_buffer.read());
+        let fluid = |i: usize| obstacles[i * 4] >= 0.5;
+
+        (width..obstacles.len() / 4 - width)
+            .filter(|&i| fluid(i) && [i - 1, i + 1, i - width, i + width].into_iter().any(|n| !fluid(n)))
+            .collect()
+    }
+
+    /// Whether the point at `x`, `y` in domain coordinates is solid in the obstacle texture of `simulation`
+    fn solid(simulation: &mut CpuSolver) -> impl Fn(f32, f32) -> bool {
+        let texture = simulation.obstacle_buffer.read();
+        let (width, height) = (texture.width() as usize, texture.height() as usize);
+        let texel = [simulation.width as f32 / width as f32, simulation.height as f32 / height as f32];
+        let Ok(obstacles) = simulation.backend.read_texture(texture);
+
+        move |x, y| {
+            let (column, row) = ((x / texel[0]) as usize, (y / texel[1]) as usize);
+            obstacles[(row * width + column) * 4] < 0.5
+        }
+    }
+
+    /// Replace the velocity of `simulation` by the one of `velocity` at the center of every texel, in texels
+    /// of the simulation and per second
+    fn set_velocity(simulation: &mut CpuSolver, velocity: impl Fn(f32, f32) -> [f32; 2]) {
+        let (width, height) = (simulation.velocity_buffer.read().width(), simulation.velocity_buffer.read().height());
+        let data: Vec<f32> = (0..width * height)
+            .flat_map(|i| {
+                let [x, y] = velocity((i % width) as f32 + 0.5, (i / width) as f32 + 0.5);
+                [x, y, 0.0, 0.0]
+            })
+            .collect();
+        let Ok(()) = simulation.velocity_buffer.upload(&mut simulation.backend, width, height, &data);
+    }
+
+    #[test]
+    fn fixed_steps_are_deterministic() {
+        let mut config = SimulationConfig::new();
+        config.set_solver(Solver::MULTIGRID);
+        config.set_advection(Advection::MACCORMACK);
+
+        let [mut first, mut second] = [simulation(), simulation()];
+        for simulation in [&mut first, &mut second] {
+            for (i, delta_time) in [0.01, 0.03, 0.02, 0.05, 0.005].into_iter().enumerate() {
+                let Ok(()) = simulation.splat(4.0, &[10.0, 10.0 + 6.0 * i as f32], &[300.0, 20.0], &[0.2, 0.8, 1.0]);
+                let Ok(_) = simulation.step(delta_time, &config);
+            }
+            let Ok(()) = simulation.step_fixed(3, &config);
+        }
+
+        let textures = |simulation: &mut CpuSolver| {
+            let backend = &mut simulation.backend;
+            [
+                simulation.velocity_buffer.read(),
+                simulation.pressure_buffer.read(),
+                simulation.dye_buffer.read(),
+            ]
+                .map(|texture| {
+                    let Ok(data) = backend.read_texture(texture);
+                    data.into_iter().map(f32::to_bits).collect::<Vec<u32>>()
+                })
+        };
+
+        let expected = textures(&mut first);
+        assert!(expected[0].iter().any(|&bits| bits != 0), "the splats did not move the fluid");
+        assert_eq!(textures(&mut second), expected);
+    }
+
+    #[test]
+    fn accumulator_is_clamped_after_max_substeps() {
+        let config = SimulationConfig::new();
+        let mut simulation = simulation();
+
+        // A long stall takes at most MAX_SUBSTEPS steps and keeps at most one step of its backlog
+        assert_eq!(simulation.step(1.0, &config), Ok(MAX_SUBSTEPS));
+        assert_eq!(simulation.accumulator, simulation.timestep());
+        assert_eq!(simulation.step(0.0, &config), Ok(1));
+        assert_eq!(simulation.step(0.0, &config), Ok(0));
+    }
+
+    #[test]
+    fn paused_steps_do_not_accumulate() {
+        let mut config = SimulationConfig::new();
+        config.set_pause(true);
+        let mut simulation = simulation();
+
+        assert_eq!(simulation.step(1.0, &config), Ok(0));
+        config.set_pause(false);
+        assert_eq!(simulation.step(0.0, &config), Ok(0));
+    }
+
+    #[test]
+    fn conjugate_gradients_leave_no_divergence() {
+        let mut config = SimulationConfig::new();
+        config.set_solver(Solver::CONJUGATE_GRADIENT);
+        config.set_tolerance(0.0).unwrap();
+        let mut simulation = splashed();
+        project(&mut simulation, &config);
+
+        let residual = simulation.pressure_residual().unwrap();
+        assert!(residual < 1e-4, "residual {} after {} iterations", residual, simulation.pressure_iterations());
+
+        // The collocated grid can not cancel the central differences of the divergence view, what the
+        // projection removes is the divergence of the pressure equation, which is left over as its residual
+        let along = along_obstacles(&mut simulation);
+        let Ok(divergence) = simulation.backend.read_texture(&simulation.temp_store);
+        let residuals = residuals(&mut simulation);
+        assert!(along.iter().any(|&i| divergence[i * 4].abs() > 10.0), "the splat does not reach the obstacle");
+        for i in along {
+            assert!(residuals[i].abs() < 1e-3, "divergence {} left at texel {}", residuals[i], i);
+        }
+    }
+
+    #[test]
+    fn over_relaxation_beats_jacobi() {
+        let residual = |solver: Solver, omega: f32, passes: usize| {
+            let mut config = SimulationConfig::new();
+            config.set_solver(solver);
+            config.set_iterations(passes);
+            config.set_omega(omega).unwrap();
+            let mut simulation = splashed();
+            project(&mut simulation, &config);
+            rms(&residuals(&mut simulation))
+        };
+
+        // The default omega saves about a quarter of the passes of a short solve, an omega close to the
+        // optimum pays off once the solve is long enough
+        let (jacobi, sor) = (residual(Solver::JACOBI, 1.0, 80), residual(Solver::SOR, 1.7, 80));
+        assert!(sor < 0.8 * jacobi, "{} after 80 SOR passes, {} after 80 Jacobi iterations", sor, jacobi);
+        let (jacobi, sor) = (residual(Solver::JACOBI, 1.0, 320), residual(Solver::SOR, 1.9, 320));
+        assert!(sor < 0.5 * jacobi, "{} after 320 SOR passes, {} after 320 Jacobi iterations", sor, jacobi);
+    }
+
+    #[test]
+    fn multigrid_reaches_a_tolerance_in_a_few_cycles() {
+        let solve = |solver: Solver| {
+            let mut config = SimulationConfig::new();
+            config.set_solver(solver);
+            config.set_tolerance(0.1).unwrap();
+            config.set_max_iterations(1000);
+            let mut simulation = splashed();
+            project(&mut simulation, &config);
+
+            // The residual is measured on the backend, it has to agree with the one of the equation
+            let residual = simulation.pressure_residual().unwrap();
+            assert!(residual <= 0.1, "{:?} stopped at a residual of {}", solver, residual);
+            assert!((rms(&residuals(&mut simulation)) - residual).abs() < 1e-3);
+            simulation.pressure_iterations()
+        };
+
+        let cycles = solve(Solver::MULTIGRID);
+        let iterations = solve(Solver::JACOBI);
+        // A V-cycle costs about eight passes at the full resolution
+        assert!(cycles <= 5, "multigrid took {} cycles", cycles);
+        assert!(iterations > 5 * 8 * cycles, "Jacobi took {} iterations, multigrid {} cycles", iterations, cycles);
+    }
+
+    #[test]
+    fn jacobi_stops_at_the_first_measurement_below_the_tolerance() {
+        let solve = |max_iterations: usize| {
+            let mut config = SimulationConfig::new();
+            config.set_tolerance(0.5).unwrap();
+            config.set_max_iterations(max_iterations);
+            let mut simulation = splashed();
+            project(&mut simulation, &config);
+            let residual = simulation.pressure_residual().unwrap();
+            assert!((rms(&residuals(&mut simulation)) - residual).abs() < 1e-3);
+            (simulation.pressure_iterations(), residual)
+        };
+
+        let (iterations, residual) = solve(1000);
+        assert!(residual <= 0.5, "stopped at a residual of {}", residual);
+        assert!(iterations > 0 && iterations < 1000, "stopped after {} iterations", iterations);
+        assert!(iterations.is_multiple_of(RESIDUAL_INTERVAL), "stopped after {} iterations", iterations);
+
+        // One measurement earlier, the solve was still above the tolerance
+        let (earlier, residual) = solve(iterations - RESIDUAL_INTERVAL);
+        assert_eq!(earlier, iterations - RESIDUAL_INTERVAL);
+        assert!(residual > 0.5, "{} after {} iterations", residual, earlier);
+    }
+
+    #[test]
+    fn a_body_dropped_against_a_wall_stays_inside() {
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 48, Resolution::FOUR, Resolution::TWO);
+        let mut obstacle = Obstacle::rectangle(&[12.0, 4.0], &[24.0, 12.0], 0.4, &[1.0, 1.0, 1.0]);
+        obstacle.set_linear_velocity(&[60.0, -400.0]);
+        obstacle.set_angular_velocity(2.0);
+        let body = RigidBody::from_density(&obstacle, 4.0).unwrap();
+        let Ok(id) = simulation.add_obstacle(obstacle);
+        simulation.set_rigid_body(id, Some(body));
+
+        let config = SimulationConfig::new();
+        let mut lowest = f32::INFINITY;
+        for _ in 0..60 {
+            let Ok(()) = simulation.step_fixed(1, &config);
+
+            let obstacle = simulation.obstacle(id).unwrap();
+            let (sin, cos) = obstacle.rotation.sin_cos();
+            for point in obstacle.shape.outline() {
+                let x = obstacle.position[0] + cos * point[0] - sin * point[1];
+                let y = obstacle.position[1] + sin * point[0] + cos * point[1];
+                assert!((-1e-3..=64.001).contains(&x) && (-1e-3..=48.001).contains(&y), "({}, {}) left the domain", x, y);
+                lowest = lowest.min(y);
+            }
+        }
+
+        assert!(lowest < 0.5, "the body never reached the bottom wall, it got down to {}", lowest);
+        let velocity = simulation.obstacle(id).unwrap().linear_velocity;
+        assert!(velocity[1].abs() < 100.0, "the wall did not stop the body, it moves at {:?}", velocity);
+    }
+
+    #[test]
+    fn drag_on_a_circle_points_downstream() {
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 128, 64, Resolution::TWO, Resolution::TWO);
+        let Ok(id) = simulation.add_obstacle(Obstacle::circle(8.0, &[48.0, 32.0], &[1.0, 1.0, 1.0]));
+        simulation.set_wind_tunnel(Some(WindTunnel::new(100.0, 0, &[1.0, 1.0, 1.0])));
+        simulation.set_measure_forces(true);
+
+        let config = SimulationConfig::new();
+        let Ok(()) = simulation.step_fixed(40, &config);
+
+        // The flow is symmetric about the axis of the circle, so the fluid pushes it along and not across
+        let (drag, lift) = simulation.obstacle_force(id).unwrap().drag_lift([1.0, 0.0]);
+        assert!(drag[0] > 0.0, "drag {:?} points upstream", drag);
+        assert!(lift[1].abs() < 0.01 * drag[0], "lift {:?} next to a drag of {:?}", lift, drag);
+    }
+
+    #[test]
+    fn shapes_are_rasterised() {
+        use std::f32::consts::FRAC_PI_4;
+
+        // Points relative to the center, the first ones inside of the shape and the others outside
+        type Points<'a> = &'a [[f32; 2]];
+
+        let color = [1.0; 3];
+        let center = [64.0, 64.0];
+        let shapes: [(Obstacle, Points, Points); 4] = [
+            (
+                // 40 x 10 along the diagonal
+                Obstacle::rectangle(&[40.0, 10.0], &center, FRAC_PI_4, &color),
+                &[[0.0, 0.0], [12.0, 12.0], [-12.0, -12.0], [-2.0, 2.0]],
+                &[[20.0, 0.0], [0.0, -20.0], [16.0, 4.0], [-5.0, 5.0]],
+            ),
+            (
+                Obstacle::capsule(40.0, 8.0, &center, 0.0, &color),
+                &[[0.0, 6.0], [25.0, 0.0], [-25.0, 0.0], [24.0, 5.0]],
+                &[[0.0, 10.0], [30.0, 0.0], [26.0, 7.0], [-26.0, -7.0]],
+            ),
+            (
+                Obstacle::annulus(10.0, 20.0, &center, &color),
+                &[[15.0, 0.0], [0.0, -15.0], [-10.0, 10.0]],
+                &[[0.0, 0.0], [5.0, 5.0], [25.0, 0.0], [15.0, 15.0]],
+            ),
+            (
+                // An L, which is concave around its inner corner
+                Obstacle::polygon(
+                    &[-15.0, -15.0, 15.0, -15.0, 15.0, -5.0, -5.0, -5.0, -5.0, 15.0, -15.0, 15.0],
+                    &center,
+                    0.0,
+                    &color,
+                ).unwrap(),
+                &[[-10.0, -10.0], [10.0, -10.0], [-10.0, 10.0]],
+                &[[5.0, 5.0], [0.0, 10.0], [10.0, 0.0], [20.0, -10.0]],
+            ),
+        ];
+
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 128, 128, Resolution::ONE, Resolution::ONE);
+        for (obstacle, inside, outside) in shapes {
+            let kind = obstacle.kind();
+            let Ok(()) = simulation.clear_obstacles();
+            let Ok(_) = simulation.add_obstacle(obstacle);
+
+            let solid = solid(&mut simulation);
+            for point in inside {
+                assert!(solid(center[0] + point[0], center[1] + point[1]), "{:?} is open at {:?}", kind, point);
+            }
+            for point in outside {
+                assert!(!solid(center[0] + point[0], center[1] + point[1]), "{:?} is solid at {:?}", kind, point);
+            }
+        }
+    }
+
+    #[test]
+    fn naca_0012_is_twelve_percent_thick() {
+        let airfoil = Obstacle::naca(12, 100.0, &[64.0, 64.0], 0.0, &[1.0; 3]).unwrap();
+        let Shape::Polygon { vertices } = airfoil.shape() else {
+            panic!("an airfoil is a polygon");
+        };
+        let (low, high) = vertices
+            .iter()
+            .fold((0.0f32, 0.0f32), |(low, high), vertex| (low.min(vertex[1]), high.max(vertex[1])));
+        assert!((high - low - 12.0).abs() < 0.1, "the profile is {} thick", high - low);
+
+        // The leading edge lies at x = 14, so the column at 0.3 of the chord is the one at x = 44
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 128, 128, Resolution::ONE, Resolution::ONE);
+        let Ok(_) = simulation.add_obstacle(airfoil);
+        let solid = solid(&mut simulation);
+        let thickness = (0..128).filter(|&y| solid(44.5, y as f32 + 0.5)).count();
+        assert_eq!(thickness, 12);
+        let columns: Vec<usize> = (0..128).filter(|&x| (0..128).any(|y| solid(x as f32 + 0.5, y as f32 + 0.5))).collect();
+        // The trailing edge thins out below a texel before it ends at x = 114
+        assert_eq!(columns[0], 14);
+        assert!((108..114).contains(columns.last().unwrap()), "the airfoil ends at {:?}", columns.last());
+    }
+
+    #[test]
+    fn periodic_edges_wrap_a_splat_around() {
+        for periodic in [false, true] {
+            let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 32, Resolution::TWO, Resolution::ONE);
+            if periodic {
+                simulation.set_boundaries(Boundaries::periodic_all());
+            }
+            let Ok(()) = simulation.splat(4.0, &[63.0, 16.0], &[0.0, 0.0], &[1.0, 1.0, 1.0]);
+
+            // The first and the last texel of the middle row of the dye
+            let Ok(dye) = simulation.backend.read_texture(simulation.dye_buffer.read());
+            let (left, right) = (dye[16 * 64 * 4], dye[(16 * 64 + 63) * 4]);
+            assert!(right > 0.25, "the splat did not color its center");
+            match periodic {
+                true => assert!(left > 0.25, "the splat did not wrap around, {} on the left edge", left),
+                false => assert_eq!(left, 0.0),
+            }
+        }
+    }
+
+    #[test]
+    fn outflow_keeps_the_pressure_at_zero() {
+        let edge_pressure = |mode: BoundaryMode| {
+            let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 64, Resolution::ONE, Resolution::ONE);
+            let mut boundaries = Boundaries::new();
+            boundaries.set_mode(Edge::RIGHT, mode);
+            simulation.set_boundaries(boundaries);
+            let Ok(()) = simulation.splat(8.0, &[48.0, 32.0], &[800.0, 0.0], &[1.0, 1.0, 1.0]);
+
+            let mut config = SimulationConfig::new();
+            config.set_solver(Solver::CONJUGATE_GRADIENT);
+            project(&mut simulation, &config);
+            let Ok(pressure) = simulation.backend.read_texture(simulation.pressure_buffer.read());
+            let largest = |texels: &mut dyn Iterator<Item = usize>| texels.map(|i| pressure[i * 4].abs()).fold(0.0, f32::max);
+            (largest(&mut (0..64).map(|row| row * 64 + 63)), largest(&mut (0..64 * 64)))
+        };
+
+        // The pressure outside of an outflow is zero, which holds that of its texels close to zero, while the
+        // splat piles the pressure up against a wall
+        let (edge, largest) = edge_pressure(BoundaryMode::OUTFLOW);
+        assert!(edge < 0.05 * largest, "{} on the outflow, {} inside", edge, largest);
+        let (edge, largest) = edge_pressure(BoundaryMode::FREE_SLIP);
+        assert!(edge > 0.2 * largest, "{} on the wall, {} inside", edge, largest);
+    }
+
+    #[test]
+    fn inflow_imposes_its_velocity() {
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 64, Resolution::TWO, Resolution::ONE);
+        let mut boundaries = Boundaries::new();
+        boundaries.set_mode(Edge::LEFT, BoundaryMode::INFLOW);
+        boundaries.set_inflow(Edge::LEFT, &[50.0, 10.0]);
+        simulation.set_boundaries(boundaries);
+        let Ok(()) = simulation.splat(8.0, &[10.0, 32.0], &[0.0, 800.0], &[1.0, 1.0, 1.0]);
+        let Ok(()) = simulation.step_fixed(3, &SimulationConfig::new());
+
+        // Velocities are stored in texels of the simulation per second, half of the pixels at this resolution
+        let Ok(velocity) = simulation.backend.read_texture(simulation.velocity_buffer.read());
+        for row in 0..32 {
+            let texel = &velocity[row * 32 * 4..][..2];
+            assert_eq!(texel, [25.0, 5.0], "row {}", row);
+        }
+        assert_ne!(&velocity[(16 * 32 + 1) * 4..][..2], [25.0, 5.0], "the inflow reaches past its edge");
+    }
+
+    #[test]
+    fn particles_follow_a_uniform_flow() {
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 32, Resolution::TWO, Resolution::ONE);
+        set_velocity(&mut simulation, |_, _| [8.0, -4.0]);
+        let Ok(()) = simulation.set_particles(Some(Particles::new(256)));
+
+        // The first step emits every particle, the second one moves them
+        let particles = |simulation: &mut CpuSolver| {
+            let Ok(()) = simulation.advect_particles(&[32.0, 16.0], 0.25);
+            let Ok(particles) = simulation.backend.read_texture(simulation.particle_buffer.as_ref().unwrap().read());
+            particles.chunks_exact(4).take(256).map(|texel| [texel[0], texel[1], texel[2]]).collect::<Vec<_>>()
+        };
+        let emitted = particles(&mut simulation);
+        let moved = particles(&mut simulation);
+
+        // A quarter of a second moves them by 2 x -1 texels, those that left the domain were emitted again
+        let mut followed = 0;
+        for (before, after) in emitted.iter().zip(&moved) {
+            if after[2] == 0.0 {
+                assert!(before[0] + 2.0 / 32.0 > 1.0 || before[1] - 1.0 / 16.0 < 0.0, "{:?} died inside", before);
+                continue;
+            }
+
+            assert!((after[0] - before[0] - 2.0 / 32.0).abs() < 1e-5, "{:?} moved to {:?}", before, after);
+            assert!((after[1] - before[1] + 1.0 / 16.0).abs() < 1e-5, "{:?} moved to {:?}", before, after);
+            assert_eq!(after[2], 0.25);
+            followed += 1;
+        }
+        assert!(followed > 128, "only {} particles followed the flow", followed);
+    }
+
+    #[test]
+    fn streamlines_follow_a_uniform_flow() {
+        let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 32, Resolution::TWO, Resolution::ONE);
+        set_velocity(&mut simulation, |_, _| [30.0, 10.0]);
+        let mut streamlines = Streamlines::new();
+        streamlines.set_length(32.0);
+        streamlines.set_steps(8);
+        streamlines.add_seed(&[16.0, 16.0]);
+        let Ok(()) = simulation.set_streamlines(streamlines);
+        let Ok(()) = simulation.draw(Mode::STREAMLINES);
+
+        // Every step goes 4 pixels along the flow, and every point carries the speed in texels per second
+        let Ok(line) = simulation.backend.read_texture(simulation.streamline_store.as_ref().unwrap());
+        assert_eq!(line.len(), 9 * 4);
+        let direction = [3.0 / 10f32.sqrt(), 1.0 / 10f32.sqrt()];
+        for (step, point) in line.chunks_exact(4).enumerate() {
+            let expected = [16.0 + 4.0 * step as f32 * direction[0], 16.0 + 4.0 * step as f32 * direction[1]];
+            let position = [point[0] * 64.0, point[1] * 32.0];
+            assert!(
+                (position[0] - expected[0]).abs() < 1e-3 && (position[1] - expected[1]).abs() < 1e-3,
+                "step {} at {:?}, expected {:?}", step, position, expected,
+            );
+            assert!((point[2] - 1000f32.sqrt()).abs() < 1e-3, "speed {} at step {}", point[2], step);
+            assert_eq!(point[3], 1.0, "the line stopped at step {}", step);
+        }
+    }
+
+    #[test]
+    fn legend_brackets_a_known_field() {
+        let legend = |mode: Mode, velocity: &dyn Fn(f32, f32) -> [f32; 2]| {
+            let Ok(mut simulation) = CpuSolver::new(CpuBackend::new(), 64, 64, Resolution::TWO, Resolution::ONE);
+            // Walls would turn the velocity around at the edges, outflows let the fields run on
+            let mut boundaries = Boundaries::new();
+            for edge in [Edge::LEFT, Edge::RIGHT, Edge::BOTTOM, Edge::TOP] {
+                boundaries.set_mode(edge, BoundaryMode::OUTFLOW);
+            }
+            simulation.set_boundaries(boundaries);
+            set_velocity(&mut simulation, velocity);
+            let pressure: Vec<f32> = (0..32 * 32).flat_map(|i| [(i % 32) as f32 - 15.5, 0.0, 0.0, 0.0]).collect();
+            let Ok(()) = simulation.pressure_buffer.upload(&mut simulation.backend, 32, 32, &pressure);
+
+            let Ok(()) = simulation.draw(mode);
+            simulation.legend()
+        };
+
+        // Speeds are in pixels, twice the texels of the simulation, the curl and divergence are per second.
+        // Away from the edges the divergence and curl of these fields are the same everywhere.
+        assert_eq!(legend(Mode::SPEED, &|_, _| [3.0, 4.0]), Some([0.0, 10.0]));
+        assert_eq!(legend(Mode::CURL, &|x, y| [16.0 - y, x - 16.0]), Some([-2.0, 2.0]));
+        assert_eq!(legend(Mode::DIVERGENCE, &|x, y| [1.5 * (x - 16.0), 1.5 * (y - 16.0)]), Some([-3.0, 3.0]));
+        assert_eq!(legend(Mode::PRESSURE, &|_, _| [0.0, 0.0]), Some([-15.5, 15.5]));
+        assert_eq!(legend(Mode::OBSTACLES, &|_, _| [0.0, 0.0]), None);
+        assert_eq!(legend(Mode::DYE, &|_, _| [0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn corrected_advection_stays_within_the_source_range() {
+        let advect = |advection: Advection| {
+            let mut backend = CpuBackend::new();
+            let texels = |value: &dyn Fn(u32, u32) -> f32| -> Vec<f32> {
+                (0..32 * 16).flat_map(|i| [value(i % 32, i / 32); 4]).collect()
+            };
+            let Ok(mut velocity) = PingPong::new(&mut backend, 32, 16, None);
+            let flow: Vec<f32> = (0..32 * 16).flat_map(|_| [2.3, 0.0, 0.0, 0.0]).collect();
+            let Ok(()) = velocity.upload(&mut backend, 32, 16, &flow);
+            // A block of 0.9 in a field of 0.2
+            let Ok(mut quantity) = PingPong::new(&mut backend, 32, 16, None);
+            let block = |x, y| if (8..16).contains(&x) && (4..12).contains(&y) { 0.9 } else { 0.2 };
+            let Ok(()) = quantity.upload(&mut backend, 32, 16, &texels(&block));
+            let Ok(mut store) = backend.create_texture(32, 16, Filter::Linear);
+            let Ok(mut obstacles) = backend.create_texture(32, 16, Filter::Linear);
+            let Ok(()) = backend.write_texture(&mut obstacles, &texels(&|_, _| 1.0));
+
+            for _ in 0..10 {
+                let Ok(()) = Simulation::advect(
+                    &mut backend,
+                    advection,
+                    &[32.0, 16.0],
+                    0.5,
+                    0.0,
+                    Some(&velocity),
+                    &mut quantity,
+                    Some(&mut store),
+                    &obstacles,
+                );
+
+                let Ok(values) = backend.read_texture(quantity.read());
+                let (min, max) = values.iter().fold((f32::MAX, f32::MIN), |(min, max), &v| (min.min(v), max.max(v)));
+                assert!(min >= 0.2 - 1e-6 && max <= 0.9 + 1e-6, "{:?} left [0.2, 0.9]: [{}, {}]", advection, min, max);
+            }
+
+            let Ok(values) = backend.read_texture(quantity.read());
+            values
+        };
+
+        // Even clamped, the corrections keep the edges of the block a little sharper than semi-Lagrangian
+        // advection does
+        let smeared = advect(Advection::SEMI_LAGRANGIAN);
+        for advection in [Advection::MACCORMACK, Advection::BFECC] {
+            let corrected = advect(advection);
+            // How far the values are from the nearer of the two of the block
+            let spread = |values: &[f32]| values.iter().map(|v| (v - 0.2).min(0.9 - v)).sum::<f32>();
+            assert!(
+                spread(&corrected) < spread(&smeared),
+                "{:?} spreads the block by {}, without a correction {}", advection, spread(&corrected), spread(&smeared),
+            );
+        }
+    }
+}
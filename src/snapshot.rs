@@ -0,0 +1,601 @@
+//! Saved state of a [simulation](crate::Simulation)
+//!
+//! A [snapshot](Snapshot) holds the contents of every texture of the simulation together with the
+//! little state that lives outside of them, so that a flow can be restored later on, on any backend.
+
+use std::fmt;
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+use crate::bytes::{ Reader, UnexpectedEnd };
+use crate::boundary::Boundaries;
+use crate::obstacle::{ DecodeError, Obstacle };
+use crate::rigid_body::RigidBody;
+use crate::wind_tunnel::WindTunnel;
+use crate::Resolution;
+
+/// Magic bytes at the start of every snapshot
+const MAGIC: [u8; 8] = *b"FLUIDSNP";
+/// Version of the format written by [to_bytes](Snapshot::to_bytes)
+pub const SNAPSHOT_VERSION: u16 = 1;
+/// Number of channels stored per texel
+const CHANNELS: u8 = 4;
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZLIB: u8 = 1;
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// Precision of the field data of an encoded [snapshot](Snapshot), the value is the size of a channel in bytes
+pub enum Precision {
+    HALF = 2,
+    SINGLE = 4,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Error returned when bytes can not be read as a [snapshot](Snapshot)
+pub enum SnapshotError {
+    /// The bytes do not start with the magic bytes of a snapshot
+    InvalidMagic,
+    /// The snapshot was written by another version of the format
+    UnsupportedVersion(u16),
+    /// The bytes end before the snapshot does
+    UnexpectedEnd,
+    /// A scaling that is not a valid [Resolution](Resolution)
+    InvalidResolution(u8),
+    /// A channel count, precision or compression that is not known
+    InvalidLayout,
+    /// A field of zero or unrepresentable size
+    InvalidField,
+    /// An obstacle that can not be read
+    InvalidObstacle,
+    /// A rigid body without a positive mass and inertia, or with a restitution outside of [0, 1]
+    InvalidRigidBody,
+    /// A value that is not a valid [BoundaryMode](crate::BoundaryMode)
+    InvalidBoundary(u8),
+    /// The compressed field data is corrupt
+    Decompression,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::InvalidMagic => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {} is not supported, expected {}",
+                version,
+                SNAPSHOT_VERSION,
+            ),
+            SnapshotError::UnexpectedEnd => write!(f, "snapshot ends unexpectedly"),
+            SnapshotError::InvalidResolution(value) => write!(f, "invalid resolution {} in snapshot", value),
+            SnapshotError::InvalidLayout => write!(f, "invalid channel layout in snapshot"),
+            SnapshotError::InvalidField => write!(f, "invalid field in snapshot"),
+            SnapshotError::InvalidObstacle => write!(f, "invalid obstacle in snapshot"),
+            SnapshotError::InvalidRigidBody => write!(f, "invalid rigid body in snapshot"),
+            SnapshotError::InvalidBoundary(value) => write!(f, "invalid boundary mode {} in snapshot", value),
+            SnapshotError::Decompression => write!(f, "corrupt field data in snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<UnexpectedEnd> for SnapshotError {
+    fn from(_: UnexpectedEnd) -> SnapshotError {
+        SnapshotError::UnexpectedEnd
+    }
+}
+
+impl From<DecodeError> for SnapshotError {
+    fn from(error: DecodeError) -> SnapshotError {
+        match error {
+            DecodeError::UnexpectedEnd => SnapshotError::UnexpectedEnd,
+            DecodeError::InvalidObstacle => SnapshotError::InvalidObstacle,
+            DecodeError::InvalidRigidBody => SnapshotError::InvalidRigidBody,
+        }
+    }
+}
+
+#[cfg(feature = "webgl")]
+impl From<SnapshotError> for JsValue {
+    fn from(error: SnapshotError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Contents of a single texture, four channels per texel, row by row from the bottom
+pub struct Field {
+    width: u32,
+    height: u32,
+    data: Vec<f32>,
+}
+
+impl Field {
+    /// # Panics
+    /// If `data` does not hold exactly four values per texel.
+    pub fn new(width: u32, height: u32, data: Vec<f32>) -> Field {
+        assert_eq!(data.len(), width as usize * height as usize * 4, "data does not match the size of the field");
+        Field {
+            width,
+            height,
+            data,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The full state of a [simulation](crate::Simulation)
+///
+/// Created by [Simulation::snapshot](crate::Simulation::snapshot) and loaded again with
+/// [Simulation::restore](crate::Simulation::restore).
+///
+/// # Format
+/// Encoded snapshots are little-endian and start with a fixed header:
+///
+/// | Offset | Size | Content                                                        |
+/// |--------|------|----------------------------------------------------------------|
+/// | 0      | 8    | Magic bytes `FLUIDSNP`                                         |
+/// | 8      | 2    | Format version (`u16`), currently 1                            |
+/// | 10     | 1    | Simulation [Resolution](Resolution)                            |
+/// | 11     | 1    | Dye [Resolution](Resolution)                                   |
+/// | 12     | 8    | Width and height of the domain in pixels (`u32`)               |
+/// | 20     | 1    | Channels per texel, always 4 (`RGBA`)                          |
+/// | 21     | 1    | [Precision] of the field data, 2 (`f16`) or 4 (`f32`)          |
+/// | 22     | 1    | Compression of the field data, 0 (none) or 1 (zlib)            |
+/// | 23     | 1    | Reserved, 0                                                    |
+/// | 24     | 4    | Time of the last update (`f32`)                                |
+/// | 28     | 32   | Width and height (`u32`) of the velocity, pressure, dye and obstacle fields |
+/// | 60     | 4    | Length of the field data in bytes (`u32`)                      |
+///
+/// The field data follows the header: every field in the order of the header, texel by texel and row
+/// by row from the bottom. Then come the next free obstacle id (`u32`), the number of obstacles (`u32`)
+/// and every obstacle as its id (`u32`) followed by the [encoded obstacle](Obstacle#format). A flag
+/// (`u8`) follows, 1 if the snapshot has a [wind tunnel](WindTunnel#format) whose encoding comes next,
+/// then the [boundaries](Boundaries#format), and last the number of rigid bodies (`u32`) and every body
+/// as the id of its obstacle (`u32`) followed by the [encoded body](RigidBody#format). Readers reject
+/// every version but their own.
+pub struct Snapshot {
+    pub(crate) sim_resolution: Resolution,
+    pub(crate) dye_resolution: Resolution,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) velocity: Field,
+    pub(crate) pressure: Field,
+    pub(crate) dye: Field,
+    pub(crate) obstacles: Field,
+    pub(crate) obstacle_list: Vec<(u32, Obstacle)>,
+    pub(crate) next_obstacle_id: u32,
+    pub(crate) wind_tunnel: Option<WindTunnel>,
+    pub(crate) boundaries: Boundaries,
+    pub(crate) rigid_bodies: Vec<(u32, RigidBody)>,
+    pub(crate) last_time: f32,
+}
+
+impl Snapshot {
+    pub fn sim_resolution(&self) -> Resolution {
+        self.sim_resolution
+    }
+
+    pub fn dye_resolution(&self) -> Resolution {
+        self.dye_resolution
+    }
+
+    /// Width of the domain the snapshot was taken at, in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the domain the snapshot was taken at, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn velocity(&self) -> &Field {
+        &self.velocity
+    }
+
+    pub fn pressure(&self) -> &Field {
+        &self.pressure
+    }
+
+    pub fn dye(&self) -> &Field {
+        &self.dye
+    }
+
+    pub fn obstacles(&self) -> &Field {
+        &self.obstacles
+    }
+
+    /// Every obstacle together with its id, in drawing order
+    pub fn obstacle_list(&self) -> &[(u32, Obstacle)] {
+        &self.obstacle_list
+    }
+
+    pub fn wind_tunnel(&self) -> Option<&WindTunnel> {
+        self.wind_tunnel.as_ref()
+    }
+
+    pub fn boundaries(&self) -> &Boundaries {
+        &self.boundaries
+    }
+
+    /// Every rigid body together with the id of its obstacle
+    pub fn rigid_bodies(&self) -> &[(u32, RigidBody)] {
+        &self.rigid_bodies
+    }
+
+    pub fn last_time(&self) -> f32 {
+        self.last_time
+    }
+
+    /// Encode the snapshot in the [snapshot format](Snapshot#format)
+    ///
+    /// # Arguments
+    /// * `precision` - [Precision] of the field data, half precision halves the size at the cost of accuracy
+    /// * `compress` - Should the field data be compressed?
+    pub fn to_bytes(&self, precision: Precision, compress: bool) -> Vec<u8> {
+        let fields = [&self.velocity, &self.pressure, &self.dye, &self.obstacles];
+
+        // FIELD DATA
+        let mut data = Vec::new();
+        for value in fields.iter().flat_map(|field| &field.data) {
+            match precision {
+                Precision::HALF => data.extend(f32_to_f16(*value).to_le_bytes()),
+                Precision::SINGLE => data.extend(value.to_le_bytes()),
+            }
+        }
+
+        if compress {
+            data = miniz_oxide::deflate::compress_to_vec_zlib(&data, 6);
+        }
+
+        // HEADER
+        let mut bytes = Vec::with_capacity(64 + data.len());
+        bytes.extend(MAGIC);
+        bytes.extend(SNAPSHOT_VERSION.to_le_bytes());
+        bytes.extend([self.sim_resolution as u8, self.dye_resolution as u8]);
+        bytes.extend(self.width.to_le_bytes());
+        bytes.extend(self.height.to_le_bytes());
+        bytes.extend([
+            CHANNELS,
+            precision as u8,
+            if compress { COMPRESSION_ZLIB } else { COMPRESSION_NONE },
+            0,
+        ]);
+        bytes.extend(self.last_time.to_le_bytes());
+
+        for field in fields {
+            bytes.extend(field.width.to_le_bytes());
+            bytes.extend(field.height.to_le_bytes());
+        }
+
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+
+        // OBSTACLES
+        bytes.extend(self.next_obstacle_id.to_le_bytes());
+        bytes.extend((self.obstacle_list.len() as u32).to_le_bytes());
+        for (id, obstacle) in &self.obstacle_list {
+            bytes.extend(id.to_le_bytes());
+            obstacle.write(&mut bytes);
+        }
+
+        // WIND TUNNEL
+        match &self.wind_tunnel {
+            Some(wind_tunnel) => {
+                bytes.push(1);
+                wind_tunnel.write(&mut bytes);
+            }
+            None => bytes.push(0),
+        }
+
+        // BOUNDARIES
+        self.boundaries.write(&mut bytes);
+
+        // RIGID BODIES
+        bytes.extend((self.rigid_bodies.len() as u32).to_le_bytes());
+        for (id, body) in &self.rigid_bodies {
+            bytes.extend(id.to_le_bytes());
+            body.write(&mut bytes);
+        }
+
+        bytes
+    }
+
+    /// Decode a snapshot in the [snapshot format](Snapshot#format)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+        let mut reader = Reader::new(bytes);
+
+        // HEADER
+        if reader.take::<8>().ok() != Some(MAGIC) {
+            return Err(SnapshotError::InvalidMagic);
+        }
+
+        let version = reader.u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let sim_resolution = Resolution::try_from(reader.u8()?).map_err(SnapshotError::InvalidResolution)?;
+        let dye_resolution = Resolution::try_from(reader.u8()?).map_err(SnapshotError::InvalidResolution)?;
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+
+        let [channels, precision, compression, _] = reader.take()?;
+        let precision = match (channels, precision) {
+            (CHANNELS, 2) => Precision::HALF,
+            (CHANNELS, 4) => Precision::SINGLE,
+            _ => return Err(SnapshotError::InvalidLayout),
+        };
+
+        let last_time = reader.f32()?;
+
+        let mut sizes = [(0, 0, 0); 4];
+        for size in &mut sizes {
+            let width = reader.u32()?;
+            let height = reader.u32()?;
+            let len = (width as usize)
+                .checked_mul(height as usize)
+                .and_then(|texels| texels.checked_mul(CHANNELS as usize))
+                .filter(|&len| len > 0)
+                .ok_or(SnapshotError::InvalidField)?;
+            *size = (width, height, len);
+        }
+
+        // FIELD DATA
+        let data_len = reader.u32()? as usize;
+        if reader.remaining().len() < data_len {
+            return Err(SnapshotError::UnexpectedEnd);
+        }
+
+        let expected_len = sizes
+            .iter()
+            .try_fold(0usize, |total, &(_, _, len)| total.checked_add(len.checked_mul(precision as usize)?))
+            .ok_or(SnapshotError::InvalidField)?;
+
+        let data = &reader.remaining()[..data_len];
+        reader.skip(data_len);
+        let data = match compression {
+            COMPRESSION_NONE => data.to_vec(),
+            COMPRESSION_ZLIB => miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, expected_len)
+                .map_err(|_| SnapshotError::Decompression)?,
+            _ => return Err(SnapshotError::InvalidLayout),
+        };
+
+        if data.len() != expected_len {
+            return Err(SnapshotError::UnexpectedEnd);
+        }
+
+        let mut values = data.chunks_exact(precision as usize).map(|value| match precision {
+            Precision::HALF => f16_to_f32(u16::from_le_bytes([value[0], value[1]])),
+            Precision::SINGLE => f32::from_le_bytes([value[0], value[1], value[2], value[3]]),
+        });
+        let [velocity, pressure, dye, obstacles] = sizes.map(|(width, height, len)| {
+            Field::new(width, height, values.by_ref().take(len).collect())
+        });
+
+        // OBSTACLES
+        let next_obstacle_id = reader.u32()?;
+        let mut obstacle_list = Vec::new();
+        for _ in 0..reader.u32()? {
+            let id = reader.u32()?;
+            obstacle_list.push((id, Obstacle::read(&mut reader)?));
+        }
+
+        // WIND TUNNEL
+        let wind_tunnel = match reader.u8()? != 0 {
+            true => Some(WindTunnel::read(&mut reader)?),
+            false => None,
+        };
+
+        // BOUNDARIES
+        let boundaries = Boundaries::read(&mut reader, SnapshotError::InvalidBoundary)?;
+
+        // RIGID BODIES
+        let mut rigid_bodies = Vec::new();
+        for _ in 0..reader.u32()? {
+            rigid_bodies.push((reader.u32()?, RigidBody::read(&mut reader)?));
+        }
+
+        Ok(Snapshot {
+            sim_resolution,
+            dye_resolution,
+            width,
+            height,
+            velocity,
+            pressure,
+            dye,
+            obstacles,
+            obstacle_list,
+            next_obstacle_id,
+            wind_tunnel,
+            boundaries,
+            rigid_bodies,
+            last_time,
+        })
+    }
+}
+
+/// Convert to the nearest half precision float, rounding ties to even
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // INFINITY AND NAN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // SUBNORMAL
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let halfway = 1 << (shift - 1);
+        let remainder = mantissa & ((1 << shift) - 1);
+        let mut half = mantissa >> shift;
+        if remainder > halfway || (remainder == halfway && half & 1 == 1) {
+            half += 1;
+        }
+
+        return sign | half as u16;
+    }
+
+    // NORMAL
+    let remainder = mantissa & 0x1fff;
+    let mut half = ((exponent as u32) << 10) | (mantissa >> 13);
+    if remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1) {
+        half += 1;
+    }
+
+    sign | half as u16
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    match exponent {
+        0 => {
+            let value = mantissa as f32 / 16_777_216.0;
+            if sign != 0 { -value } else { value }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::{ BoundaryMode, Edge };
+
+    /// A small snapshot whose field values are all exact in half precision
+    fn snapshot() -> Snapshot {
+        let field = |width: u32, height: u32, offset: f32| {
+            let data = (0..width * height * 4).map(|i| (i as f32 - offset) * 0.25).collect();
+            Field::new(width, height, data)
+        };
+
+        let mut boundaries = Boundaries::new();
+        boundaries.set_mode(Edge::LEFT, BoundaryMode::INFLOW);
+        boundaries.set_inflow(Edge::LEFT, &[2.0, 0.0]);
+        boundaries.set_mode(Edge::RIGHT, BoundaryMode::OUTFLOW);
+
+        Snapshot {
+            sim_resolution: Resolution::FOUR,
+            dye_resolution: Resolution::ONE,
+            width: 16,
+            height: 8,
+            velocity: field(4, 2, 3.0),
+            pressure: field(4, 2, 8.0),
+            dye: field(16, 8, 0.0),
+            obstacles: field(16, 8, 100.0),
+            obstacle_list: vec![
+                (0, Obstacle::circle(2.0, &[4.0, 4.0], &[1.0, 0.5, 0.25])),
+                (3, Obstacle::rectangle(&[3.0, 1.0], &[10.0, 2.0], 0.5, &[0.0, 0.0, 1.0])),
+            ],
+            next_obstacle_id: 4,
+            wind_tunnel: Some(WindTunnel::new(50.0, 6, &[1.0, 1.0, 1.0])),
+            boundaries,
+            rigid_bodies: vec![(3, RigidBody::new(3.0, 2.5).unwrap())],
+            last_time: 1.5,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let snapshot = snapshot();
+        for precision in [Precision::SINGLE, Precision::HALF] {
+            for compress in [false, true] {
+                let decoded = Snapshot::from_bytes(&snapshot.to_bytes(precision, compress));
+                assert_eq!(decoded.as_ref(), Ok(&snapshot), "{:?}, compressed: {}", precision, compress);
+            }
+        }
+    }
+
+    #[test]
+    fn half_precision_rounds_the_fields() {
+        let mut snapshot = snapshot();
+        snapshot.pressure.data[0] = 0.1;
+
+        let decoded = Snapshot::from_bytes(&snapshot.to_bytes(Precision::HALF, false)).unwrap();
+        assert_eq!(decoded.pressure.data[0], 0.099975586);
+        assert_eq!(decoded.pressure.data[1..], snapshot.pressure.data[1..]);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = snapshot().to_bytes(Precision::SINGLE, false);
+        bytes[0] = b'X';
+        assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::InvalidMagic));
+        assert_eq!(Snapshot::from_bytes(b"FLUID"), Err(SnapshotError::InvalidMagic));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = snapshot().to_bytes(Precision::SINGLE, false);
+        for version in [0, SNAPSHOT_VERSION + 1] {
+            bytes[8..10].copy_from_slice(&version.to_le_bytes());
+            assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::UnsupportedVersion(version)));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        for compress in [false, true] {
+            let bytes = snapshot().to_bytes(Precision::SINGLE, compress);
+            for len in 0..bytes.len() {
+                assert!(Snapshot::from_bytes(&bytes[..len]).is_err(), "{} of {} bytes", len, bytes.len());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_rigid_bodies_that_can_not_move() {
+        let bytes = snapshot().to_bytes(Precision::SINGLE, false);
+        let body: Vec<u8> = [3.0f32, 2.5, 0.5].iter().flat_map(|value| value.to_le_bytes()).collect();
+        let start = bytes.windows(body.len()).position(|window| window == body).unwrap();
+
+        for (offset, value) in [(0, 0.0), (0, -3.0), (4, f32::NAN), (4, f32::INFINITY), (8, 1.5)] {
+            let mut bytes = bytes.clone();
+            bytes[start + offset..start + offset + 4].copy_from_slice(&f32::to_le_bytes(value));
+            assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::InvalidRigidBody), "{} at {}", value, offset);
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_fields() {
+        let mut bytes = snapshot().to_bytes(Precision::SINGLE, true);
+        bytes[70] ^= 0xff;
+        assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::Decompression));
+
+        let mut bytes = snapshot().to_bytes(Precision::SINGLE, false);
+        bytes[21] = 3;
+        assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::InvalidLayout));
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+
+/// Largest number of steps of a [streamline](Streamlines)
+pub const MAX_STREAMLINE_STEPS: u32 = 128;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// How the [streamlines](crate::Mode::STREAMLINES) mode traces the flow
+///
+/// Every streamline starts at a seed and follows the current velocity of the fluid for a fixed length,
+/// integrated with RK4 in steps of equal length, so that slow regions get lines as long as fast ones.
+/// A line stops early where the fluid stands still, where it leaves the domain or where it runs into an
+/// obstacle. Lines are colored by the speed of the fluid and fade out along the flow, drawn over the dye.
+///
+/// Seeds are spread over the domain on a regular grid, unless seeds are placed with
+/// [add_seed](Streamlines::add_seed).
+pub struct Streamlines {
+    spacing: f32,
+    length: f32,
+    steps: u32,
+    max_speed: f32,
+    seeds: Vec<[f32; 2]>,
+}
+
+impl Default for Streamlines {
+    fn default() -> Streamlines {
+        Streamlines {
+            spacing: 32.0,
+            length: 160.0,
+            steps: 32,
+            max_speed: 500.0,
+            seeds: Vec::new(),
+        }
+    }
+}
+
+impl Streamlines {
+    /// Every seed placed with [add_seed](Streamlines::add_seed) as its x and y position in pixels
+    pub fn seeds(&self) -> &[[f32; 2]] {
+        &self.seeds
+    }
+
+    /// Number of seeds along either axis of the grid that covers a domain of `width` x `height` pixels
+    pub(crate) fn grid(&self, width: u32, height: u32) -> [u32; 2] {
+        [width, height].map(|size| (size as f32 / self.spacing).ceil().max(1.0) as u32)
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl Streamlines {
+    /// Create streamlines 160 pixels long, seeded every 32 pixels
+    #[cfg_attr(feature = "webgl", wasm_bindgen(constructor))]
+    pub fn new() -> Streamlines {
+        Streamlines::default()
+    }
+
+    /// Distance between the seeds of the grid in pixels
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn spacing(&self) -> f32 {
+        self.spacing
+    }
+
+    /// Set the distance between the seeds of the grid, at least 4 pixels
+    pub fn set_spacing(&mut self, spacing: f32) {
+        self.spacing = spacing.max(4.0);
+    }
+
+    /// Length of a streamline in pixels
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    pub fn set_length(&mut self, length: f32) {
+        self.length = length.max(0.0);
+    }
+
+    /// Number of segments of a streamline, each of them a step of the integration
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Set the number of segments of a streamline, between 1 and [MAX_STREAMLINE_STEPS]
+    pub fn set_steps(&mut self, steps: u32) {
+        self.steps = steps.clamp(1, MAX_STREAMLINE_STEPS);
+    }
+
+    /// Speed in pixels per second at which streamlines turn red
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    pub fn set_max_speed(&mut self, max_speed: f32) {
+        self.max_speed = max_speed.max(f32::EPSILON);
+    }
+
+    /// Start a streamline at a point, which replaces the grid of seeds
+    ///
+    /// # Arguments
+    /// * `position` - A float array that should have two values, the x and y position of the seed in pixels
+    ///
+    /// # Panics
+    /// If `position` contains fewer than two values.
+    pub fn add_seed(&mut self, position: &[f32]) {
+        self.seeds.push([position[0], position[1]]);
+    }
+
+    /// Remove every seed, so that streamlines start on a grid again
+    pub fn clear_seeds(&mut self) {
+        self.seeds.clear();
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn seed_count(&self) -> u32 {
+        self.seeds.len() as u32
+    }
+}
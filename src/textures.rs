@@ -4,10 +4,7 @@ use web_sys::{
     WebGlTexture,
     WebGlFramebuffer,
 };
-use std::mem;
-use crate::Renderer;
-use crate::shader_program::ShaderProgram;
-use crate::shaders;
+use crate::backend::BackendTexture;
 
 pub struct TextureFramebuffer {
     texture: WebGlTexture,
@@ -22,6 +19,23 @@ impl TextureFramebuffer {
         width: u32,
         height: u32,
         param: u32,
+    ) -> Result<TextureFramebuffer, JsValue> {
+        TextureFramebuffer::with_format(
+            gl,
+            width,
+            height,
+            param,
+            false,
+        )
+    }
+
+    /// Create a texture with `f32` instead of `f16` channels if `full_precision` is `true`
+    pub fn with_format(
+        gl: &WebGl2RenderingContext,
+        width: u32,
+        height: u32,
+        param: u32,
+        full_precision: bool,
     ) -> Result<TextureFramebuffer, JsValue> {
         gl.active_texture(WebGl2RenderingContext::TEXTURE0);
         let texture = gl.create_texture().unwrap();
@@ -48,16 +62,27 @@ impl TextureFramebuffer {
             WebGl2RenderingContext::CLAMP_TO_EDGE as i32,
         );
 
-        let data = unsafe { js_sys::Uint16Array::view(&vec![0; (width * height * 4) as usize]) };
+        let (internal_format, data_type, data): (u32, u32, js_sys::Object) = match full_precision {
+            true => (
+                WebGl2RenderingContext::RGBA32F,
+                WebGl2RenderingContext::FLOAT,
+                js_sys::Float32Array::new_with_length(width * height * 4).into(),
+            ),
+            false => (
+                WebGl2RenderingContext::RGBA16F,
+                WebGl2RenderingContext::HALF_FLOAT,
+                js_sys::Uint16Array::new_with_length(width * height * 4).into(),
+            ),
+        };
         gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
             WebGl2RenderingContext::TEXTURE_2D,
             0,
-            WebGl2RenderingContext::RGBA16F as i32,
+            internal_format as i32,
             width as i32,
             height as i32,
             0,
             WebGl2RenderingContext::RGBA,
-            WebGl2RenderingContext::HALF_FLOAT,
+            data_type,
             Some(&data),
         )?;
         
@@ -79,6 +104,22 @@ impl TextureFramebuffer {
         })
     }
 
+    /// Set the wrapping along the x and the y axis, `CLAMP_TO_EDGE` or `REPEAT`
+    pub fn set_wrap(&self, gl: &WebGl2RenderingContext, wrap_s: u32, wrap_t: u32) {
+        gl.active_texture(WebGl2RenderingContext::TEXTURE0);
+        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&self.texture));
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_WRAP_S,
+            wrap_s as i32,
+        );
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_WRAP_T,
+            wrap_t as i32,
+        );
+    }
+
     pub fn bind(
         &self,
         gl: &WebGl2RenderingContext,
@@ -86,7 +127,7 @@ impl TextureFramebuffer {
     ) -> Result<i32, JsValue> {
         if id >= 32 {
             return Err(JsValue::from_str(
-                "id >= 32".into()
+                "id >= 32"
             ));
         }
 
@@ -96,6 +137,46 @@ impl TextureFramebuffer {
         Ok(id as i32)
     }
 
+    /// Read the texture back from the GPU as `RGBA` floats, row by row from the bottom
+    pub fn read_pixels(&self, gl: &WebGl2RenderingContext) -> Result<Vec<f32>, JsValue> {
+        let data = js_sys::Float32Array::new_with_length(self.width * self.height * 4);
+        gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+        gl.read_pixels_with_opt_array_buffer_view(
+            0,
+            0,
+            self.width as i32,
+            self.height as i32,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::FLOAT,
+            Some(&data),
+        )?;
+
+        Ok(data.to_vec())
+    }
+
+    /// Upload `RGBA` floats, row by row from the bottom, to the texture
+    pub fn write_pixels(&self, gl: &WebGl2RenderingContext, data: &[f32]) -> Result<(), JsValue> {
+        if data.len() != (self.width * self.height * 4) as usize {
+            return Err(JsValue::from_str("data does not match the size of the texture"));
+        }
+
+        gl.active_texture(WebGl2RenderingContext::TEXTURE0);
+        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&self.texture));
+
+        let data = unsafe { js_sys::Float32Array::view(data) };
+        gl.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
+            WebGl2RenderingContext::TEXTURE_2D,
+            0,
+            0,
+            0,
+            self.width as i32,
+            self.height as i32,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::FLOAT,
+            Some(&data),
+        )
+    }
+
     pub fn delete(&self, gl: &WebGl2RenderingContext) {
         gl.delete_texture(Some(&self.texture));
         gl.delete_framebuffer(Some(&self.framebuffer));
@@ -104,111 +185,15 @@ impl TextureFramebuffer {
     pub fn buffer(&self) -> &WebGlFramebuffer {
         &self.framebuffer
     }
+}
 
-    pub fn width(&self) -> u32 {
+impl BackendTexture for TextureFramebuffer {
+    fn width(&self) -> u32 {
         self.width
     }
 
-    pub fn height(&self) -> u32 {
+    fn height(&self) -> u32 {
         self.height
     }
 }
 
-pub struct RWTextureBuffer {
-    read: TextureFramebuffer,
-    write: TextureFramebuffer,
-    param: u32,
-}
-
-impl RWTextureBuffer {
-    pub fn new(
-        gl: &WebGl2RenderingContext,
-        width: u32,
-        height: u32,
-        param: Option<u32>,
-    ) -> Result<RWTextureBuffer, JsValue> {
-        let param = param.unwrap_or(WebGl2RenderingContext::LINEAR);
-
-        let read = TextureFramebuffer::new(
-            gl,
-            width,
-            height,
-            param,
-        )?;
-        let write = TextureFramebuffer::new(
-            gl,
-            width,
-            height,
-            param,
-        )?;
-
-        Ok(RWTextureBuffer {
-            read,
-            write,
-            param,
-        })
-    }
-
-    pub fn resize(
-        &mut self,
-        gl: &WebGl2RenderingContext,
-        copy_program: Option<&ShaderProgram>,
-        width: u32,
-        height: u32,
-    ) -> Result<(), JsValue> {
-        if width == self.read.width && height == self.read.height {
-            return Ok(());
-        }
-
-        let new_buffer = RWTextureBuffer::new(
-            gl,
-            width,
-            height, 
-            Some(self.param),
-        )?;
-
-        // COPY
-        if let Some(copy_program) = copy_program {
-            copy_program.bind(gl);
-            gl.uniform1f(
-                copy_program.uniforms.get(shaders::U_FACTOR),
-                1.0,
-            );
-            gl.uniform1f(
-                copy_program.uniforms.get(shaders::U_OFFSET),
-                0.0,
-            );
-            gl.uniform1i(
-                copy_program.uniforms.get(shaders::U_TEXTURE),
-                self.read.bind(gl, 0)?,
-            );
-
-            Renderer::blit(
-                gl,
-                Some(&new_buffer.read),
-                None,
-            );
-        }
-
-        // DELETE AND SET
-        self.read.delete(gl);
-        self.write.delete(gl);
-
-        self.read = new_buffer.read;
-        self.write = new_buffer.write;
-
-        Ok(())
-    }
-
-    pub fn swap(&mut self) {
-        mem::swap(&mut self.read, &mut self.write);
-    }
-
-    pub fn read(&self) -> &TextureFramebuffer {
-        &self.read
-    }
-
-    pub fn write(&self) -> &TextureFramebuffer {
-        &self.write
-    }
-}
\ No newline at end of file
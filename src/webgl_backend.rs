@@ -0,0 +1,885 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{ WebGl2RenderingContext, WebGlBuffer };
+use crate::backend::{ BackendTexture, Pass, SimulationBackend };
+use crate::grid::{ Filter, Wrap };
+use crate::shader_program::ShaderProgram;
+use crate::textures::TextureFramebuffer;
+use crate::shaders;
+
+/// Backend running every pass of the simulation as a WebGL2 shader program
+pub struct WebGlBackend {
+    gl: WebGl2RenderingContext,
+    copy_program: ShaderProgram,
+    advection_program: ShaderProgram,
+    advection_error_program: ShaderProgram,
+    corrected_advection_program: ShaderProgram,
+    jacobi_program: ShaderProgram,
+    sor_program: ShaderProgram,
+    residual_program: ShaderProgram,
+    restrict_program: ShaderProgram,
+    prolong_program: ShaderProgram,
+    reduce_program: ShaderProgram,
+    divergence_program: ShaderProgram,
+    subtraction_program: ShaderProgram,
+    curl_program: ShaderProgram,
+    vorticity_program: ShaderProgram,
+    splat_program: ShaderProgram,
+    obstacle_program: ShaderProgram,
+    obstacle_mask_program: ShaderProgram,
+    obstacle_polygon_program: ShaderProgram,
+    force_program: ShaderProgram,
+    streaklines_program: ShaderProgram,
+    color_obstacle_program: ShaderProgram,
+    colormap_program: ShaderProgram,
+    particles_program: ShaderProgram,
+    draw_particles_program: ShaderProgram,
+    streamlines_program: ShaderProgram,
+    draw_streamlines_program: ShaderProgram,
+    quad_buffer: WebGlBuffer,
+    /// Vertices of the particle texture, see [texel_buffer](WebGlBackend::texel_buffer)
+    particle_buffer: Option<(WebGlBuffer, u32, u32)>,
+    /// Vertices of the streamline texture, see [texel_buffer](WebGlBackend::texel_buffer)
+    streamline_buffer: Option<(WebGlBuffer, u32, u32)>,
+}
+
+impl WebGlBackend {
+    pub fn new(gl: WebGl2RenderingContext) -> Result<WebGlBackend, JsValue> {
+        gl.get_extension("EXT_color_buffer_float")?;
+        gl.disable(WebGl2RenderingContext::BLEND);
+
+        let copy_program = ShaderProgram::new(
+            &gl,
+            shaders::COPY_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let advection_program = ShaderProgram::new(
+            &gl,
+            shaders::ADVECTION_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let advection_error_program = ShaderProgram::new(
+            &gl,
+            shaders::ADVECTION_ERROR_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let corrected_advection_program = ShaderProgram::new(
+            &gl,
+            shaders::CORRECTED_ADVECTION_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let jacobi_program = ShaderProgram::new(
+            &gl,
+            shaders::PRESSURE_SOLVER_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let sor_program = ShaderProgram::new(
+            &gl,
+            shaders::PRESSURE_SOR_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let residual_program = ShaderProgram::new(
+            &gl,
+            shaders::RESIDUAL_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let restrict_program = ShaderProgram::new(
+            &gl,
+            shaders::RESTRICT_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let prolong_program = ShaderProgram::new(
+            &gl,
+            shaders::PROLONG_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let reduce_program = ShaderProgram::new(
+            &gl,
+            shaders::REDUCE_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let divergence_program = ShaderProgram::new(
+            &gl,
+            shaders::DIVERGENCE_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let subtraction_program = ShaderProgram::new(
+            &gl,
+            shaders::GRADIENT_SUBTRACT_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let curl_program = ShaderProgram::new(
+            &gl,
+            shaders::CURL_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let vorticity_program = ShaderProgram::new(
+            &gl,
+            shaders::VORTICITY_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let splat_program = ShaderProgram::new(
+            &gl,
+            shaders::SPLAT_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let obstacle_program = ShaderProgram::new(
+            &gl,
+            shaders::OBSTACLE_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let obstacle_mask_program = ShaderProgram::new(
+            &gl,
+            shaders::OBSTACLE_MASK_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let obstacle_polygon_program = ShaderProgram::new(
+            &gl,
+            shaders::OBSTACLE_POLYGON_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let force_program = ShaderProgram::new(
+            &gl,
+            shaders::FORCE_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let streaklines_program = ShaderProgram::new(
+            &gl,
+            shaders::STREAKLINES_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let color_obstacle_program = ShaderProgram::new(
+            &gl,
+            shaders::COLOR_OBSTACLE_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+
+        let colormap_program = ShaderProgram::new(
+            &gl,
+            shaders::COLORMAP_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let particles_program = ShaderProgram::new(
+            &gl,
+            shaders::PARTICLES_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let draw_particles_program = ShaderProgram::new(
+            &gl,
+            shaders::DRAW_PARTICLES_SHADER_SOURCE,
+            shaders::PARTICLE_VERTEX_SHADER_SOURCE,
+        )?;
+        let streamlines_program = ShaderProgram::new(
+            &gl,
+            shaders::STREAMLINES_SHADER_SOURCE,
+            shaders::VERTEX_SHADER_SOURCE,
+        )?;
+        let draw_streamlines_program = ShaderProgram::new(
+            &gl,
+            shaders::DRAW_PARTICLES_SHADER_SOURCE,
+            shaders::STREAMLINE_VERTEX_SHADER_SOURCE,
+        )?;
+
+        let quad_buffer = WebGlBackend::init_quad_buffers(&gl)?;
+
+        Ok(WebGlBackend {
+            gl,
+            copy_program,
+            advection_program,
+            advection_error_program,
+            corrected_advection_program,
+            jacobi_program,
+            sor_program,
+            residual_program,
+            restrict_program,
+            prolong_program,
+            reduce_program,
+            divergence_program,
+            subtraction_program,
+            curl_program,
+            vorticity_program,
+            splat_program,
+            obstacle_program,
+            obstacle_mask_program,
+            obstacle_polygon_program,
+            force_program,
+            streaklines_program,
+            color_obstacle_program,
+            colormap_program,
+            particles_program,
+            draw_particles_program,
+            streamlines_program,
+            draw_streamlines_program,
+            quad_buffer,
+            particle_buffer: None,
+            streamline_buffer: None,
+        })
+    }
+
+    fn init_quad_buffers(gl: &WebGl2RenderingContext) -> Result<WebGlBuffer, JsValue> {
+        let vertex_buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&vertex_buffer));
+
+        let vertices = [
+            -1.0, -1.0, 0.0, 0.0,
+             1.0, -1.0, 1.0, 0.0,
+            -1.0,  1.0, 0.0, 1.0,
+             1.0,  1.0, 1.0, 1.0,
+        ];
+        let vertices = unsafe { js_sys::Float32Array::view(&vertices) };
+        gl.buffer_data_with_array_buffer_view(
+            WebGl2RenderingContext::ARRAY_BUFFER,
+            &vertices,
+            WebGl2RenderingContext::STATIC_DRAW,
+        );
+
+        WebGlBackend::bind_quad_attributes(gl);
+
+        Ok(vertex_buffer)
+    }
+
+    /// Point the coordinates and uvs of the vertex shader at the bound quad buffer
+    fn bind_quad_attributes(gl: &WebGl2RenderingContext) {
+        gl.vertex_attrib_pointer_with_i32(
+            0,
+            2,
+            WebGl2RenderingContext::FLOAT,
+            false,
+            16,
+            0,
+        );
+        gl.vertex_attrib_pointer_with_i32(
+            1,
+            2,
+            WebGl2RenderingContext::FLOAT,
+            false,
+            16,
+            8,
+        );
+
+        gl.enable_vertex_attrib_array(0);
+        gl.enable_vertex_attrib_array(1);
+    }
+
+    /// The vertices of every texel of a `width` x `height` texture, two per texel, taken from `cache` if it
+    /// was made for that size
+    ///
+    /// Both vertices are at the texture coordinates of the texel, the second one is shifted by two along x
+    /// so that vertex shaders can tell them apart.
+    fn texel_buffer(
+        gl: &WebGl2RenderingContext,
+        cache: &mut Option<(WebGlBuffer, u32, u32)>,
+        width: u32,
+        height: u32,
+    ) -> Result<WebGlBuffer, JsValue> {
+        if let Some((buffer, w, h)) = cache {
+            if (*w, *h) == (width, height) {
+                return Ok(buffer.clone());
+            }
+            gl.delete_buffer(Some(buffer));
+        }
+
+        let mut vertices = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                vertices.extend([u, v, u + 2.0, v]);
+            }
+        }
+
+        let buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
+        let vertices = unsafe { js_sys::Float32Array::view(&vertices) };
+        gl.buffer_data_with_array_buffer_view(
+            WebGl2RenderingContext::ARRAY_BUFFER,
+            &vertices,
+            WebGl2RenderingContext::STATIC_DRAW,
+        );
+        *cache = Some((buffer.clone(), width, height));
+
+        Ok(buffer)
+    }
+
+    /// Blend `count` vertices of `buffer` over `target` as `mode` primitives with the bound program, and
+    /// bind the quad again for every other pass
+    ///
+    /// Every vertex takes `stride` bytes, only its coordinates are read.
+    fn draw_vertices(
+        &self,
+        buffer: &WebGlBuffer,
+        mode: u32,
+        stride: i32,
+        count: u32,
+        target: &TextureFramebuffer,
+    ) {
+        let gl = &self.gl;
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(buffer));
+        gl.disable_vertex_attrib_array(1);
+        gl.vertex_attrib_pointer_with_i32(
+            0,
+            2,
+            WebGl2RenderingContext::FLOAT,
+            false,
+            stride,
+            0,
+        );
+
+        gl.viewport(
+            0,
+            0,
+            target.width() as i32,
+            target.height() as i32,
+        );
+        gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, Some(target.buffer()));
+        gl.enable(WebGl2RenderingContext::BLEND);
+        gl.blend_func_separate(
+            WebGl2RenderingContext::SRC_ALPHA,
+            WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            WebGl2RenderingContext::ZERO,
+            WebGl2RenderingContext::ONE,
+        );
+        gl.draw_arrays(mode, 0, count as i32);
+        gl.disable(WebGl2RenderingContext::BLEND);
+
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.quad_buffer));
+        WebGlBackend::bind_quad_attributes(gl);
+    }
+
+    pub fn blit(
+        gl: &WebGl2RenderingContext,
+        target: Option<&TextureFramebuffer>,
+        clear: Option<bool>,
+    ) {
+        match target {
+            Some(tfb) => {
+                gl.viewport(
+                    0,
+                    0,
+                    tfb.width() as i32,
+                    tfb.height() as i32,
+                );
+                gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, Some(tfb.buffer()));
+            }
+            None => {
+                gl.viewport(
+                    0,
+                    0,
+                    gl.drawing_buffer_width(),
+                    gl.drawing_buffer_height(),
+                );
+                gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+            }
+        }
+
+        if clear.unwrap_or(false) {
+            gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+        }
+
+        gl.draw_arrays(
+            WebGl2RenderingContext::TRIANGLE_STRIP,
+            0,
+            4,
+        );
+    }
+}
+
+impl SimulationBackend for WebGlBackend {
+    type Error = JsValue;
+    type Texture = TextureFramebuffer;
+
+    fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        filter: Filter,
+    ) -> Result<TextureFramebuffer, JsValue> {
+        TextureFramebuffer::new(
+            &self.gl,
+            width,
+            height,
+            match filter {
+                Filter::Linear => WebGl2RenderingContext::LINEAR,
+                Filter::Nearest => WebGl2RenderingContext::NEAREST,
+            },
+        )
+    }
+
+    fn create_data_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<TextureFramebuffer, JsValue> {
+        TextureFramebuffer::with_format(
+            &self.gl,
+            width,
+            height,
+            WebGl2RenderingContext::NEAREST,
+            true,
+        )
+    }
+
+    fn set_wrap(&mut self, texture: &mut TextureFramebuffer, wrap: [Wrap; 2]) {
+        let [wrap_s, wrap_t] = wrap.map(|wrap| match wrap {
+            Wrap::Clamp => WebGl2RenderingContext::CLAMP_TO_EDGE,
+            Wrap::Repeat => WebGl2RenderingContext::REPEAT,
+        });
+        texture.set_wrap(&self.gl, wrap_s, wrap_t);
+    }
+
+    fn delete_texture(&mut self, texture: TextureFramebuffer) {
+        texture.delete(&self.gl);
+    }
+
+    fn read_texture(&mut self, texture: &TextureFramebuffer) -> Result<Vec<f32>, JsValue> {
+        texture.read_pixels(&self.gl)
+    }
+
+    fn write_texture(
+        &mut self,
+        texture: &mut TextureFramebuffer,
+        data: &[f32],
+    ) -> Result<(), JsValue> {
+        texture.write_pixels(&self.gl, data)
+    }
+
+    fn dispatch(
+        &mut self,
+        pass: Pass<'_, TextureFramebuffer>,
+        target: &mut TextureFramebuffer,
+    ) -> Result<(), JsValue> {
+        let gl = &self.gl;
+
+        match pass {
+            Pass::Copy { factor, offset, texture } => {
+                let program = &self.copy_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_FACTOR), factor);
+                gl.uniform1f(program.uniforms.get(shaders::U_OFFSET), offset);
+                gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 0)?);
+            }
+            Pass::Advection { dissipation, delta_time, resolution, velocity, quantity, obstacles } => {
+                let program = &self.advection_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_DISSIPATION), dissipation);
+                gl.uniform1f(program.uniforms.get(shaders::U_DELTA_TIME), delta_time);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_QUANTITY), quantity.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+            }
+            Pass::AdvectionError { delta_time, resolution, velocity, quantity, advected } => {
+                let program = &self.advection_error_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_DELTA_TIME), delta_time);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_QUANTITY), quantity.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_ADVECTED), advected.bind(gl, 2)?);
+            }
+            Pass::CorrectedAdvection {
+                bfecc,
+                dissipation,
+                delta_time,
+                resolution,
+                quantity_resolution,
+                velocity,
+                quantity,
+                error,
+                obstacles,
+            } => {
+                let program = &self.corrected_advection_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_BFECC), bfecc as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_DISSIPATION), dissipation);
+                gl.uniform1f(program.uniforms.get(shaders::U_DELTA_TIME), delta_time);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_QUANTITY_RESOLUTION), &quantity_resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_QUANTITY), quantity.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_ERROR), error.bind(gl, 3)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+            }
+            Pass::Divergence { boundary, inflow, r_half_texel_size, resolution, velocity, obstacles, obstacle_velocity } => {
+                let program = &self.divergence_program;
+                program.bind(gl);
+
+                let [left, right, bottom, top] = boundary;
+                gl.uniform4i(program.uniforms.get(shaders::U_BOUNDARY), left as i32, right as i32, bottom as i32, top as i32);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_LEFT), &inflow[0]);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_RIGHT), &inflow[1]);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_BOTTOM), &inflow[2]);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_TOP), &inflow[3]);
+                gl.uniform1f(program.uniforms.get(shaders::U_R_HALF_TEXEL_SIZE), r_half_texel_size);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLE_VELOCITY), obstacle_velocity.bind(gl, 2)?);
+            }
+            Pass::PressureSolver { boundary, alpha, r_beta, omega, resolution, x, b, obstacles } => {
+                let program = &self.jacobi_program;
+                program.bind(gl);
+
+                let [left, right, bottom, top] = boundary;
+                gl.uniform4i(program.uniforms.get(shaders::U_BOUNDARY), left as i32, right as i32, bottom as i32, top as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_ALPHA), alpha);
+                gl.uniform1f(program.uniforms.get(shaders::U_R_BETA), r_beta);
+                gl.uniform1f(program.uniforms.get(shaders::U_OMEGA), omega);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_X), x.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_B), b.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+            }
+            Pass::PressureSor { boundary, alpha, omega, parity, resolution, x, b, obstacles } => {
+                let program = &self.sor_program;
+                program.bind(gl);
+
+                let [left, right, bottom, top] = boundary;
+                gl.uniform4i(program.uniforms.get(shaders::U_BOUNDARY), left as i32, right as i32, bottom as i32, top as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_ALPHA), alpha);
+                gl.uniform1f(program.uniforms.get(shaders::U_OMEGA), omega);
+                gl.uniform1i(program.uniforms.get(shaders::U_PARITY), parity as i32);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_X), x.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_B), b.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+            }
+            Pass::Residual { boundary, alpha, resolution, x, b, obstacles } => {
+                let program = &self.residual_program;
+                program.bind(gl);
+
+                let [left, right, bottom, top] = boundary;
+                gl.uniform4i(program.uniforms.get(shaders::U_BOUNDARY), left as i32, right as i32, bottom as i32, top as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_ALPHA), alpha);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_X), x.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_B), b.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+            }
+            Pass::Restrict { resolution, texture } => {
+                let program = &self.restrict_program;
+                program.bind(gl);
+
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 0)?);
+            }
+            Pass::Prolong { x, correction } => {
+                let program = &self.prolong_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_X), x.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_CORRECTION), correction.bind(gl, 1)?);
+            }
+            Pass::Reduce { square, resolution, texture } => {
+                let program = &self.reduce_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_SQUARE), square as i32);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 0)?);
+            }
+            Pass::GradientSubtract {
+                boundary,
+                inflow,
+                r_half_texel_size,
+                resolution,
+                velocity,
+                pressure,
+                obstacles,
+                obstacle_velocity,
+            } => {
+                let program = &self.subtraction_program;
+                program.bind(gl);
+
+                let [left, right, bottom, top] = boundary;
+                gl.uniform4i(program.uniforms.get(shaders::U_BOUNDARY), left as i32, right as i32, bottom as i32, top as i32);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_LEFT), &inflow[0]);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_RIGHT), &inflow[1]);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_BOTTOM), &inflow[2]);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_INFLOW_TOP), &inflow[3]);
+                gl.uniform1f(program.uniforms.get(shaders::U_R_HALF_TEXEL_SIZE), r_half_texel_size);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_PRESSURE), pressure.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLE_VELOCITY), obstacle_velocity.bind(gl, 3)?);
+            }
+            Pass::Curl { r_half_texel_size, resolution, velocity } => {
+                let program = &self.curl_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_R_HALF_TEXEL_SIZE), r_half_texel_size);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 0)?);
+            }
+            Pass::Vorticity { curl_scale, r_half_texel_size, resolution, curl, velocity } => {
+                let program = &self.vorticity_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_CURL_SCALE), curl_scale);
+                gl.uniform1f(program.uniforms.get(shaders::U_R_HALF_TEXEL_SIZE), r_half_texel_size);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_CURL), curl.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 1)?);
+            }
+            Pass::Splat { scaled_radius, position, period, color, texture, obstacles } => {
+                let program = &self.splat_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_SCALED_RADIUS), scaled_radius);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_POSITION), &position);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_PERIOD), &period);
+                gl.uniform3fv_with_f32_array(program.uniforms.get(shaders::U_COLOR), &color);
+                gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 1)?);
+            }
+            Pass::Obstacle {
+                shape,
+                write_velocity,
+                index,
+                size,
+                position,
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color,
+                obstacles,
+            } => {
+                let program = &self.obstacle_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_SHAPE), shape as i32);
+                gl.uniform1i(program.uniforms.get(shaders::U_WRITE_VELOCITY), write_velocity as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_INDEX), index as f32);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_SIZE), &size);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_POSITION), &position);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_ROTATION), &rotation);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_LINEAR_VELOCITY), &linear_velocity);
+                gl.uniform1f(program.uniforms.get(shaders::U_ANGULAR_VELOCITY), angular_velocity);
+                gl.uniform3fv_with_f32_array(program.uniforms.get(shaders::U_OBSTACLE_COLOR), &obstacle_color);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 0)?);
+            }
+            Pass::ObstacleMask {
+                write_velocity,
+                index,
+                threshold,
+                position,
+                half_size,
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color,
+                mask,
+                obstacles,
+            } => {
+                let program = &self.obstacle_mask_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_WRITE_VELOCITY), write_velocity as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_INDEX), index as f32);
+                gl.uniform1f(program.uniforms.get(shaders::U_THRESHOLD), threshold);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_POSITION), &position);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_HALF_SIZE), &half_size);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_ROTATION), &rotation);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_LINEAR_VELOCITY), &linear_velocity);
+                gl.uniform1f(program.uniforms.get(shaders::U_ANGULAR_VELOCITY), angular_velocity);
+                gl.uniform3fv_with_f32_array(program.uniforms.get(shaders::U_OBSTACLE_COLOR), &obstacle_color);
+                gl.uniform1i(program.uniforms.get(shaders::U_MASK), mask.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 1)?);
+            }
+            Pass::ObstaclePolygon {
+                write_velocity,
+                index,
+                position,
+                rotation,
+                linear_velocity,
+                angular_velocity,
+                obstacle_color,
+                vertices,
+                obstacles,
+            } => {
+                let program = &self.obstacle_polygon_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_WRITE_VELOCITY), write_velocity as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_INDEX), index as f32);
+                gl.uniform1i(program.uniforms.get(shaders::U_VERTEX_COUNT), vertices.width() as i32);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_POSITION), &position);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_ROTATION), &rotation);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_LINEAR_VELOCITY), &linear_velocity);
+                gl.uniform1f(program.uniforms.get(shaders::U_ANGULAR_VELOCITY), angular_velocity);
+                gl.uniform3fv_with_f32_array(program.uniforms.get(shaders::U_OBSTACLE_COLOR), &obstacle_color);
+                gl.uniform1i(program.uniforms.get(shaders::U_VERTICES), vertices.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 1)?);
+            }
+            Pass::Force { pressure_scale, viscous_scale, resolution, pressure, velocity, obstacles, obstacle_velocity } => {
+                let program = &self.force_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_PRESSURE_SCALE), pressure_scale);
+                gl.uniform1f(program.uniforms.get(shaders::U_VISCOUS_SCALE), viscous_scale);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_PRESSURE), pressure.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLE_VELOCITY), obstacle_velocity.bind(gl, 3)?);
+            }
+            Pass::Particles { delta_time, lifetime, seed, period, resolution, particles, velocity, obstacles, emitters } => {
+                let program = &self.particles_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_DELTA_TIME), delta_time);
+                gl.uniform1f(program.uniforms.get(shaders::U_LIFETIME), lifetime);
+                gl.uniform1f(program.uniforms.get(shaders::U_SEED), seed);
+                gl.uniform1i(
+                    program.uniforms.get(shaders::U_EMITTER_COUNT),
+                    emitters.map_or(0, |emitters| emitters.width() as i32),
+                );
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_PERIOD), &period);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_PARTICLES), particles.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 1)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 2)?);
+                // Without emitters the sampler is never read, but it still needs a texture
+                gl.uniform1i(program.uniforms.get(shaders::U_EMITTERS), emitters.unwrap_or(particles).bind(gl, 3)?);
+            }
+            Pass::DrawParticles { count, size, streak, color_mode, color, max_speed, resolution, particles, velocity } => {
+                let buffer = WebGlBackend::texel_buffer(
+                    gl,
+                    &mut self.particle_buffer,
+                    particles.width(),
+                    particles.height(),
+                )?;
+
+                let program = &self.draw_particles_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_SIZE), size);
+                gl.uniform1f(program.uniforms.get(shaders::U_STREAK), streak);
+                gl.uniform1i(program.uniforms.get(shaders::U_COLOR_MODE), color_mode as i32);
+                gl.uniform3fv_with_f32_array(program.uniforms.get(shaders::U_COLOR), &color);
+                gl.uniform1f(program.uniforms.get(shaders::U_MAX_SPEED), max_speed);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_PARTICLES), particles.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 1)?);
+
+                // Points use the first vertex of every particle, streaks both
+                match streak > 0.0 {
+                    true => self.draw_vertices(&buffer, WebGl2RenderingContext::LINES, 8, 2 * count, target),
+                    false => self.draw_vertices(&buffer, WebGl2RenderingContext::POINTS, 16, count, target),
+                }
+
+                return Ok(());
+            }
+            Pass::Streamlines { step_size, grid, period, resolution, seeds, velocity, obstacles } => {
+                let program = &self.streamlines_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_STEP_SIZE), step_size);
+                gl.uniform1i(
+                    program.uniforms.get(shaders::U_SEED_COUNT),
+                    seeds.map_or(0, |seeds| seeds.width() as i32),
+                );
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_GRID), &grid);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_PERIOD), &period);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform1i(program.uniforms.get(shaders::U_VELOCITY), velocity.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 1)?);
+                // Without seeds the sampler is never read, but it still needs a texture
+                gl.uniform1i(program.uniforms.get(shaders::U_SEEDS), seeds.unwrap_or(velocity).bind(gl, 2)?);
+            }
+            Pass::DrawStreamlines { max_speed, lines } => {
+                let buffer = WebGlBackend::texel_buffer(
+                    gl,
+                    &mut self.streamline_buffer,
+                    lines.width(),
+                    lines.height(),
+                )?;
+
+                let program = &self.draw_streamlines_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_POINT_SPACING), 1.0 / lines.width() as f32);
+                gl.uniform1f(program.uniforms.get(shaders::U_MAX_SPEED), max_speed);
+                gl.uniform1i(program.uniforms.get(shaders::U_LINES), lines.bind(gl, 0)?);
+
+                // A segment from every point to the next one
+                self.draw_vertices(
+                    &buffer,
+                    WebGl2RenderingContext::LINES,
+                    8,
+                    2 * lines.width() * lines.height(),
+                    target,
+                );
+
+                return Ok(());
+            }
+            Pass::Colormap { colormap, range, magnitude, texture, obstacles } => {
+                let program = &self.colormap_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_COLORMAP), colormap as i32);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RANGE), &range);
+                gl.uniform1i(program.uniforms.get(shaders::U_MAGNITUDE), magnitude as i32);
+                gl.uniform1i(program.uniforms.get(shaders::U_COLOR_OBSTACLES), obstacles.is_some() as i32);
+                gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 0)?);
+                // Without obstacles the sampler is never read, but it still needs a texture
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.unwrap_or(texture).bind(gl, 1)?);
+            }
+            Pass::Streaklines { count, radius, length, resolution, color, texture } => {
+                let program = &self.streaklines_program;
+                program.bind(gl);
+
+                gl.uniform1i(program.uniforms.get(shaders::U_COUNT), count as i32);
+                gl.uniform1f(program.uniforms.get(shaders::U_RADIUS), radius);
+                gl.uniform1f(program.uniforms.get(shaders::U_LENGTH), length);
+                gl.uniform2fv_with_f32_array(program.uniforms.get(shaders::U_RESOLUTION), &resolution);
+                gl.uniform3fv_with_f32_array(program.uniforms.get(shaders::U_COLOR), &color);
+                gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 0)?);
+            }
+            Pass::ColorObstacle { intensity, obstacles, texture } => {
+                let program = &self.color_obstacle_program;
+                program.bind(gl);
+
+                gl.uniform1f(program.uniforms.get(shaders::U_INTENSITY), intensity);
+                gl.uniform1i(program.uniforms.get(shaders::U_OBSTACLES), obstacles.bind(gl, 0)?);
+                gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 1)?);
+            }
+        }
+
+        WebGlBackend::blit(
+            gl,
+            Some(target),
+            None,
+        );
+
+        Ok(())
+    }
+
+    fn present(
+        &mut self,
+        texture: &TextureFramebuffer,
+        factor: f32,
+        offset: f32,
+    ) -> Result<(), JsValue> {
+        let gl = &self.gl;
+        let program = &self.copy_program;
+        program.bind(gl);
+
+        gl.uniform1f(program.uniforms.get(shaders::U_FACTOR), factor);
+        gl.uniform1f(program.uniforms.get(shaders::U_OFFSET), offset);
+        gl.uniform1i(program.uniforms.get(shaders::U_TEXTURE), texture.bind(gl, 0)?);
+
+        WebGlBackend::blit(
+            gl,
+            None,
+            Some(true),
+        );
+
+        Ok(())
+    }
+}
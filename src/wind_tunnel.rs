@@ -0,0 +1,112 @@
+#[cfg(feature = "webgl")]
+use wasm_bindgen::prelude::*;
+use crate::boundary::{ Boundaries, BoundaryMode, Edge };
+use crate::bytes::{ Reader, UnexpectedEnd };
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+/// A flow through the [simulation](crate::Simulation) from the left to the right edge
+///
+/// Fluid enters through the left edge with a constant velocity and leaves through the right edge, while
+/// the top and bottom edges stay closed. Lines of dye are injected at regular heights along the left
+/// edge, so that the flow around obstacles becomes visible as streaklines.
+///
+/// # Format
+/// [Snapshots](crate::Snapshot) and [recordings](crate::Recording) encode a wind tunnel as the
+/// `inflow` (`f32`), the number of `streaklines` (`u32`) and the `streakline_color` (`f32` x 3), all
+/// little endian.
+pub struct WindTunnel {
+    inflow: f32,
+    streaklines: u32,
+    streakline_color: [f32; 3],
+}
+
+impl Default for WindTunnel {
+    fn default() -> WindTunnel {
+        WindTunnel {
+            inflow: 200.0,
+            streaklines: 12,
+            streakline_color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl WindTunnel {
+    /// An inflow on the left edge, an outflow on the right edge and free-slip walls on the others
+    pub(crate) fn boundaries(&self) -> Boundaries {
+        let mut boundaries = Boundaries::default();
+        boundaries.set_mode(Edge::LEFT, BoundaryMode::INFLOW);
+        boundaries.set_inflow(Edge::LEFT, &[self.inflow, 0.0]);
+        boundaries.set_mode(Edge::RIGHT, BoundaryMode::OUTFLOW);
+
+        boundaries
+    }
+
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.inflow.to_le_bytes());
+        bytes.extend(self.streaklines.to_le_bytes());
+        for value in self.streakline_color {
+            bytes.extend(value.to_le_bytes());
+        }
+    }
+
+    pub(crate) fn read(reader: &mut Reader) -> Result<WindTunnel, UnexpectedEnd> {
+        Ok(WindTunnel {
+            inflow: reader.f32()?,
+            streaklines: reader.u32()?,
+            streakline_color: [reader.f32()?, reader.f32()?, reader.f32()?],
+        })
+    }
+}
+
+#[cfg_attr(feature = "webgl", wasm_bindgen)]
+impl WindTunnel {
+    /// Create a wind tunnel
+    ///
+    /// # Arguments
+    /// * `inflow` - Velocity of the fluid entering through the left edge in pixels per second
+    /// * `streaklines` - Number of lines of dye injected along the left edge, 0 for none
+    /// * `streakline_color` - A float array that should have three values, a red, a green, and a blue color value
+    ///
+    /// # Panics
+    /// If `streakline_color` contains fewer than three values.
+    #[cfg_attr(feature = "webgl", wasm_bindgen(constructor))]
+    pub fn new(inflow: f32, streaklines: u32, streakline_color: &[f32]) -> WindTunnel {
+        WindTunnel {
+            inflow,
+            streaklines,
+            streakline_color: [streakline_color[0], streakline_color[1], streakline_color[2]],
+        }
+    }
+
+    /// Velocity of the fluid entering through the left edge in pixels per second
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn inflow(&self) -> f32 {
+        self.inflow
+    }
+
+    pub fn set_inflow(&mut self, inflow: f32) {
+        self.inflow = inflow;
+    }
+
+    /// Number of lines of dye injected along the left edge
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn streaklines(&self) -> u32 {
+        self.streaklines
+    }
+
+    pub fn set_streaklines(&mut self, streaklines: u32) {
+        self.streaklines = streaklines;
+    }
+
+    #[cfg_attr(feature = "webgl", wasm_bindgen(getter))]
+    pub fn streakline_color(&self) -> Vec<f32> {
+        self.streakline_color.to_vec()
+    }
+
+    /// # Panics
+    /// If `color` contains fewer than three values.
+    pub fn set_streakline_color(&mut self, color: &[f32]) {
+        self.streakline_color = [color[0], color[1], color[2]];
+    }
+}